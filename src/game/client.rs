@@ -1,4 +1,8 @@
-use crate::game::graphics::{generator_from_mesh_type, health_bar, MeshType, inventory_mesh};
+use crate::game::ecs::{EffectKind, Hitbox, Shape};
+use crate::game::graphics::{
+    effect_color, effect_ring, generator_from_mesh_type, health_bar, hitbox_mesh, inventory_mesh, item_color, progress_bar, worn_color, MeshType,
+    INVENTORY_SLOT_SIZE, ITEM_SIZE,
+};
 use crate::misc::constants::DEFAULT_COLOR;
 use crate::misc::{constants::ALL_KEYS, State};
 use crate::net::{Connection, Event, EventListener, Handle, Protocol, NULL_HANDLE};
@@ -10,6 +14,7 @@ use ggez::input::keyboard;
 use ggez::{graphics as gg_graphics, Context, ContextBuilder, GameResult};
 use std::collections::{HashMap, VecDeque};
 use std::marker::PhantomData;
+use std::time::Instant;
 use ggez::nalgebra::Vector;
 use crate::game::menu::MapMenu;
 use crate::game::{PLAYER1_HANDLE, PLAYER2_HANDLE};
@@ -88,12 +93,72 @@ struct MyGame<PROTOCOL: Protocol> {
     /// Keys and whether they're held down or not. Wrapped in a State to record change
     key_states: HashMap<KeyCode, State<bool>>,
     player_handle: Handle,
-    health: HashMap<Handle, u8>,
+    // The health of each entity along with its max health
+    health: HashMap<Handle, (u16, u16)>,
     color: HashMap<Handle, Color>,
-    inventory: Option<MeshType>,
+    /// What's in each slot of the player's inventory, and which slot is selected
+    inventory: Vec<(MeshType, u16)>,
+    selected_slot: usize,
     starting_events: VecDeque<Event>,
     should_continue: bool,
     game_over: bool,
+    /// What each entity collides with, only drawn when show_hitboxes is toggled on with F3
+    hitboxes: HashMap<Handle, Hitbox>,
+    show_hitboxes: bool,
+    /// How far along things like capture points are, drawn as bars above them
+    progress: HashMap<Handle, f32>,
+    /// The weapons of tanks, only the player's is shown on the HUD
+    weapons: HashMap<Handle, WeaponStatus>,
+    /// The effects tanks are under
+    effects: HashMap<Handle, EffectStatus>,
+    /// How long until things like item spawners are done counting down
+    countdowns: HashMap<Handle, CountdownStatus>,
+}
+
+/// What a weapon was like when the server last told us about it
+struct WeaponStatus {
+    ammo: u16,
+    // 0 if it never runs out
+    magazine: u16,
+    until_ready: f32,
+    wait: f32,
+    received: Instant,
+}
+
+/// What effects something was under when the server last told us about it,
+/// each with how long it had left and how long it lasted to begin with
+struct EffectStatus {
+    effects: Vec<(EffectKind, f32, f32)>,
+    received: Instant,
+}
+
+/// How long a countdown had left when the server last told us about it, out of how long it is in total
+struct CountdownStatus {
+    remaining: f32,
+    total: f32,
+    received: Instant,
+}
+
+impl CountdownStatus {
+    /// How far along the countdown is from 0 to 1, None once it's done
+    fn progress(&self) -> Option<f32> {
+        let remaining = self.remaining - self.received.elapsed().as_secs_f32();
+        if remaining <= 0.0 || self.total <= 0.0 {
+            return None;
+        }
+        Some((1.0 - remaining / self.total).clamp(0.0, 1.0))
+    }
+}
+
+impl WeaponStatus {
+    /// How ready the weapon is to fire from 0 to 1, counting down since we last heard of it
+    fn readiness(&self) -> f32 {
+        if self.wait <= 0.0 {
+            return 1.0;
+        }
+        let until_ready = self.until_ready - self.received.elapsed().as_secs_f32();
+        (1.0 - until_ready / self.wait).clamp(0.0, 1.0)
+    }
 }
 
 fn new_key_map() -> HashMap<KeyCode, State<bool>> {
@@ -118,10 +183,17 @@ impl<PROTOCOL: Protocol> MyGame<PROTOCOL> {
             player_handle: NULL_HANDLE,
             health: HashMap::new(),
             color: HashMap::new(),
-            inventory: None,
+            inventory: Vec::new(),
+            selected_slot: 0,
             starting_events: VecDeque::new(),
             should_continue: false,
             game_over: false,
+            hitboxes: HashMap::new(),
+            show_hitboxes: false,
+            progress: HashMap::new(),
+            weapons: HashMap::new(),
+            effects: HashMap::new(),
+            countdowns: HashMap::new(),
         }
     }
 
@@ -158,6 +230,16 @@ impl<PROTOCOL: Protocol> MyGame<PROTOCOL> {
         }
     }
 
+    /// Toggles the hitbox overlay whenever F3 is pressed
+    fn check_debug_keys(&mut self) {
+        if let Some(key_state) = self.key_states.get(&KeyCode::F3) {
+            // Only just pressed if it hasn't been sent to the server yet
+            if **key_state && key_state.invalidated() {
+                self.show_hitboxes = !self.show_hitboxes;
+            }
+        }
+    }
+
     fn send_keys(&mut self) {
         for (key, key_state) in &mut self.key_states {
             if key_state.invalidated_since() {
@@ -198,17 +280,17 @@ impl<PROTOCOL: Protocol> MyGame<PROTOCOL> {
                 .get(&self.player_handle)
                 .cloned()
                 .unwrap_or(DEFAULT_COLOR);
-            let player_health = self.health.get(&self.player_handle).cloned().unwrap_or(0);
+            let player_health = self.health.get(&self.player_handle).cloned().unwrap_or((0, 0));
             let player_x = self.coords.get(&self.player_handle).cloned().unwrap_or((0.0, 0.0, 0.0)).0;
             let mut opponent_color = DEFAULT_COLOR;
-            let mut opponent_health = 0;
+            let mut opponent_health = (0, 0);
             let mut opponent_x = 0.0;
             // Check for other health values to get one to render
             for (handle, _) in &self.health {
                 // Find the one that isn't our players, but that belongs to a player (i.e not an NPC)
                 if *handle != self.player_handle && (*handle == PLAYER1_HANDLE || *handle == PLAYER2_HANDLE) {
                     opponent_color = self.color.get(&handle).cloned().unwrap_or(DEFAULT_COLOR);
-                    opponent_health = self.health.get(&handle).cloned().unwrap_or(0);
+                    opponent_health = self.health.get(&handle).cloned().unwrap_or((0, 0));
                     opponent_x = self.coords.get(&handle).cloned().unwrap_or((0.0, 0.0, 0.0)).0;
                 } else {
                     continue;
@@ -222,20 +304,18 @@ impl<PROTOCOL: Protocol> MyGame<PROTOCOL> {
             }
         };
         // Render some health bars
-        let health_bar_right = health_bar(ctx, 15.0, 15.0, right_color, right_health, 50)?;
+        let health_bar_right = health_bar(ctx, 15.0, 15.0, right_color, right_health.0, right_health.1)?;
         let bar_dim = health_bar_right.dimensions(ctx).unwrap_or_default();
         let health_bar_left = health_bar(
             ctx,
             WINDOW_WIDTH - bar_dim.w - 15.0,
             15.0,
             left_color,
-            left_health,
-            50,
+            left_health.0,
+            left_health.1,
         )?;
-        // If there's any inventory item for my player, render that too
-        if self.inventory.is_some() {
-            self.render_inventory(ctx)?;
-        }
+        self.render_inventory(ctx)?;
+        self.render_weapon(ctx)?;
         gg_graphics::draw(ctx, &health_bar_right, DrawParam::default())?;
         gg_graphics::draw(ctx, &health_bar_left, DrawParam::default())
     }
@@ -244,19 +324,130 @@ impl<PROTOCOL: Protocol> MyGame<PROTOCOL> {
         self.dimension.get(&handle).cloned().unwrap_or((1.0, 1.0))
     }
 
+    /// Draws the outline of every hitbox on top of everything else, in a garish green
+    fn render_hitboxes(&self, ctx: &mut Context) -> GameResult<()> {
+        let shapes: Vec<_> = self
+            .hitboxes
+            .iter()
+            .filter_map(|(handle, hitbox)| {
+                let (x, y, angle) = self.coords.get(handle)?;
+                Some(hitbox.place(*x, *y, *angle))
+            })
+            .flatten()
+            .collect();
+        if shapes.is_empty() {
+            return Ok(());
+        }
+        let mesh = hitbox_mesh(ctx, &shapes, Color::new(0.0, 1.0, 0.0, 1.0))?;
+        gg_graphics::draw(ctx, &mesh, DrawParam::default())
+    }
+
+    /// Draws a bar along the top of anything that's made some progress
+    fn render_progress(&self, ctx: &mut Context) -> GameResult<()> {
+        const BAR_OFFSET: f32 = 10.0;
+        for (handle, progress) in &self.progress {
+            if let Some((x, y, _)) = self.coords.get(handle) {
+                let color = self.color.get(handle).cloned().unwrap_or(DEFAULT_COLOR);
+                // Bars are opaque even when the thing they're over isn't
+                let color = Color::new(color.r, color.g, color.b, 1.0);
+                let (width, _) = self.get_dimensions(*handle);
+                let bar = progress_bar(ctx, *x, *y - BAR_OFFSET, width, *progress, color)?;
+                gg_graphics::draw(ctx, &bar, DrawParam::default())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws a bar filling up above anything counting down, like an item about to spawn
+    fn render_countdowns(&self, ctx: &mut Context) -> GameResult<()> {
+        const BAR_OFFSET: f32 = 15.0;
+        for (handle, countdown) in &self.countdowns {
+            let progress = match countdown.progress() {
+                Some(progress) => progress,
+                None => continue,
+            };
+            if let Some((x, y, _)) = self.coords.get(handle) {
+                let color = self.color.get(handle).cloned().unwrap_or(DEFAULT_COLOR);
+                let bar = progress_bar(ctx, *x, *y - BAR_OFFSET, ITEM_SIZE, progress, color)?;
+                gg_graphics::draw(ctx, &bar, DrawParam::default())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rings around whatever's under effects, and how long the player's effects have left on the HUD
+    fn render_effects(&self, ctx: &mut Context) -> GameResult<()> {
+        for (handle, status) in &self.effects {
+            let elapsed = status.received.elapsed().as_secs_f32();
+            let active = status.effects.iter().filter(|(_, remaining, _)| *remaining > elapsed);
+            let (x, y, _) = match self.coords.get(handle) {
+                Some(coords) => *coords,
+                None => continue,
+            };
+            for (ring, (kind, remaining, _)) in active.enumerate() {
+                let color = effect_color(*kind);
+                let mesh = effect_ring(ctx, x, y, ring, Color::new(color.r, color.g, color.b, 0.6))?;
+                gg_graphics::draw(ctx, &mesh, DrawParam::default())?;
+                if *handle != self.player_handle {
+                    continue;
+                }
+                // Listed upwards from just above the inventory
+                let text = Text::new(format!("{} {:.1}s", kind.name().replace('_', " "), remaining - elapsed));
+                let params = DrawParam::default()
+                    .dest([15.0, WINDOW_HEIGHT - INVENTORY_SLOT_SIZE - 15.0 - 20.0 * (ring + 1) as f32])
+                    .color(color);
+                gg_graphics::draw(ctx, &text, params)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows the ammo left in the player's weapon, and a bar filling up until it's ready to fire
+    fn render_weapon(&self, ctx: &mut Context) -> GameResult<()> {
+        const BAR_WIDTH: f32 = 60.0;
+        // Right next to the inventory
+        let x = 15.0 + self.inventory.len() as f32 * (INVENTORY_SLOT_SIZE + 5.0) + 5.0;
+        let weapon = match self.weapons.get(&self.player_handle) {
+            Some(weapon) => weapon,
+            None => return Ok(()),
+        };
+        let color = self.color.get(&self.player_handle).cloned().unwrap_or(DEFAULT_COLOR);
+        let bar = progress_bar(ctx, x, WINDOW_HEIGHT - 15.0 - 6.0, BAR_WIDTH, weapon.readiness(), color)?;
+        gg_graphics::draw(ctx, &bar, DrawParam::default())?;
+        // Bottomless weapons have nothing to count
+        if weapon.magazine > 0 {
+            let ammo = Text::new(format!("{}/{}", weapon.ammo, weapon.magazine));
+            let params = DrawParam::default()
+                .dest([x, WINDOW_HEIGHT - 40.0 - 10.0])
+                .color(gg_graphics::BLACK);
+            gg_graphics::draw(ctx, &ammo, params)?;
+        }
+        Ok(())
+    }
+
     fn render_inventory(&self, ctx: &mut Context) -> GameResult<()> {
-        // Make a translucent background for our inventory
-        let inventory_bg = inventory_mesh(ctx,
-                                          15.0,
-                                          WINDOW_HEIGHT - 40.0 - 15.0,
-        )?;
-        let item_mesh_type = self.inventory.unwrap_or_default();
-        // Get the mesh of the inventory item to render
-        let item_mesh = (generator_from_mesh_type(item_mesh_type))(
-            ctx, 20.0, WINDOW_HEIGHT - 40.0 - 10.0, Color::new(1.0, 0.0, 0.0, 1.0),
-        )?;
-        gg_graphics::draw(ctx, &inventory_bg, DrawParam::default())?;
-        gg_graphics::draw(ctx, &item_mesh, DrawParam::default())
+        const SPACING: f32 = 5.0;
+        let y = WINDOW_HEIGHT - INVENTORY_SLOT_SIZE - 15.0;
+        for (slot, (mesh_type, count)) in self.inventory.iter().enumerate() {
+            let x = 15.0 + slot as f32 * (INVENTORY_SLOT_SIZE + SPACING);
+            // Make a translucent background for each slot, outlining the selected one
+            let slot_bg = inventory_mesh(ctx, x, y, slot == self.selected_slot)?;
+            gg_graphics::draw(ctx, &slot_bg, DrawParam::default())?;
+            if *mesh_type == MeshType::None {
+                continue;
+            }
+            // Get the mesh of the inventory item to render
+            let item_mesh = (generator_from_mesh_type(*mesh_type))(ctx, x + 5.0, y + 5.0, item_color(*mesh_type))?;
+            gg_graphics::draw(ctx, &item_mesh, DrawParam::default())?;
+            if *count > 1 {
+                let count = Text::new(count.to_string());
+                let params = DrawParam::default()
+                    .dest([x + 2.0, y + 2.0])
+                    .color(gg_graphics::BLACK);
+                gg_graphics::draw(ctx, &count, params)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -274,6 +465,7 @@ impl<PROTOCOL: Protocol> EventHandler for MyGame<PROTOCOL> {
             }
             // Check keys pressed or released
             self.check_keys(&ctx);
+            self.check_debug_keys();
             // Send info about keys whose state has changed
             self.send_keys();
             let events = self.server.recv_multiple(10000);
@@ -289,15 +481,26 @@ impl<PROTOCOL: Protocol> EventHandler for MyGame<PROTOCOL> {
             gg_graphics::draw(ctx, &text, DrawParam::default())?;
         } else {
             gg_graphics::clear(ctx, gg_graphics::WHITE);
-            for (handle, coord) in &self.coords {
+            // Zones, spawners and mines are drawn first, so that they're underneath whatever's on them
+            let (zones, others): (Vec<_>, Vec<_>) = self
+                .coords
+                .iter()
+                .partition(|(handle, _)| {
+                    matches!(self.meshes.get(handle), Some(MeshType::Zone) | Some(MeshType::Spawner) | Some(MeshType::Mine))
+                });
+            for (handle, coord) in zones.into_iter().chain(others) {
                 // Just access a bunch of properties of our game objects and render them using them
-                let color = if let Some(color) = self.color.get(handle) {
+                let mut color = if let Some(color) = self.color.get(handle) {
                     *color
                 } else {
                     DEFAULT_COLOR
                 };
                 let scale = self.get_dimensions(*handle);
                 let mesh_type = self.meshes.get(handle).cloned().unwrap_or_default();
+                // Destructible walls fade as they're worn down
+                if let (MeshType::Wall, Some((health, max_health))) = (mesh_type, self.health.get(handle)) {
+                    color = worn_color(color, *health, *max_health);
+                }
                 let mesh_generator = generator_from_mesh_type(mesh_type);
                 let mesh = (mesh_generator)(ctx, coord.0, coord.1, color)?;
                 let point = ggez::nalgebra::Point2::new(coord.0, coord.1);
@@ -307,6 +510,12 @@ impl<PROTOCOL: Protocol> EventHandler for MyGame<PROTOCOL> {
                     .offset(point);
                 gg_graphics::draw(ctx, &mesh, params)?;
             }
+            self.render_progress(ctx)?;
+            self.render_countdowns(ctx)?;
+            self.render_effects(ctx)?;
+            if self.show_hitboxes {
+                self.render_hitboxes(ctx)?;
+            }
             self.render_gui(ctx)?;
         }
         gg_graphics::present(ctx)
@@ -332,22 +541,25 @@ impl<PROTOCOL: Protocol> EventListener for MyGame<PROTOCOL> {
         self.meshes.insert(handle, mesh_type);
     }
 
-    fn on_pick_up(&mut self, _conn_index: usize, handle: Handle, mesh_type: MeshType) {
+    fn on_inventory(&mut self, _conn_index: usize, handle: Handle, selected: usize, slots: Vec<(MeshType, u16)>) {
         if handle == self.player_handle {
-            self.inventory = match mesh_type {
-                MeshType::None => None,
-                _ => Some(mesh_type)
-            }
+            self.inventory = slots;
+            self.selected_slot = selected;
         }
     }
 
     fn on_despawn(&mut self, _conn_index: usize, handle: Handle) {
         self.coords.remove(&handle);
         self.meshes.remove(&handle);
+        self.hitboxes.remove(&handle);
+        self.progress.remove(&handle);
+        self.weapons.remove(&handle);
+        self.effects.remove(&handle);
+        self.countdowns.remove(&handle);
     }
 
-    fn on_health(&mut self, _conn_index: usize, handle: Handle, health: u8) {
-        self.health.insert(handle, health);
+    fn on_health(&mut self, _conn_index: usize, handle: Handle, health: u16, max_health: u16) {
+        self.health.insert(handle, (health, max_health));
     }
 
     fn on_color(&mut self, _conn_index: usize, handle: Handle, color: Color) {
@@ -358,6 +570,42 @@ impl<PROTOCOL: Protocol> EventListener for MyGame<PROTOCOL> {
         self.dimension.insert(handle, (width, height));
     }
 
+    fn on_hitbox(&mut self, _conn_index: usize, handle: Handle, shapes: Vec<Shape>) {
+        self.hitboxes.insert(handle, Hitbox::new(shapes));
+    }
+
+    fn on_progress(&mut self, _conn_index: usize, handle: Handle, progress: f32) {
+        self.progress.insert(handle, progress);
+    }
+
+    fn on_weapon(&mut self, _conn_index: usize, handle: Handle, ammo: u16, magazine: u16, until_ready: f32, wait: f32) {
+        let status = WeaponStatus {
+            ammo,
+            magazine,
+            until_ready,
+            wait,
+            received: Instant::now(),
+        };
+        self.weapons.insert(handle, status);
+    }
+
+    fn on_effects(&mut self, _conn_index: usize, handle: Handle, effects: Vec<(EffectKind, f32, f32)>) {
+        let status = EffectStatus {
+            effects,
+            received: Instant::now(),
+        };
+        self.effects.insert(handle, status);
+    }
+
+    fn on_countdown(&mut self, _conn_index: usize, handle: Handle, remaining: f32, total: f32) {
+        let status = CountdownStatus {
+            remaining,
+            total,
+            received: Instant::now(),
+        };
+        self.countdowns.insert(handle, status);
+    }
+
     fn on_game_over(&mut self, _conn_index: usize) {
         self.should_continue = true;
         self.game_over = true;
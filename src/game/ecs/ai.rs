@@ -0,0 +1,254 @@
+use crate::net::Handle;
+
+/// How well an NPC plays. The presets go from easy to hard, any of them can be picked by name
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difficulty {
+    pub name: String,
+    /// How far away the NPC notices other tanks in px, as long as there's no wall in between
+    pub sight_range: f32,
+    /// How close the NPC gets to a tank before it stops to shoot at it, in px
+    pub attack_range: f32,
+    /// How far off the NPC's aim may be in degrees either way, picked anew after every shot
+    pub aim_error: f32,
+    /// How far off the NPC is willing to shoot in degrees, a good shot waits until it's lined up
+    pub aim_tolerance: f32,
+    /// How much the NPC aims ahead of moving tanks, 0 for where they are and 1 for where the shot will meet them
+    pub lead: f32,
+    /// Seconds from noticing a tank until the first shot at it
+    pub reaction_time: f32,
+    /// The fraction of its health at which the NPC goes to heal, if there's anything to heal with
+    pub retreat_health: f32,
+    /// Seconds a projectile has to be coming at the NPC before it tries to get out of the way
+    pub dodge_reaction: f32,
+    /// The fraction of projectiles coming at it which the NPC notices at all, from 0 to 1
+    pub dodge_chance: f32,
+}
+
+impl Difficulty {
+    /// Never retreats and can't hit anything moving
+    pub fn easy() -> Self {
+        Self {
+            name: "easy".to_string(),
+            sight_range: 400.0,
+            attack_range: 250.0,
+            aim_error: 12.0,
+            aim_tolerance: 15.0,
+            lead: 0.0,
+            reaction_time: 0.8,
+            retreat_health: 0.0,
+            dodge_reaction: 0.4,
+            dodge_chance: 0.3,
+        }
+    }
+
+    pub fn normal() -> Self {
+        Self {
+            name: "normal".to_string(),
+            sight_range: 600.0,
+            attack_range: 300.0,
+            aim_error: 5.0,
+            aim_tolerance: 10.0,
+            lead: 0.5,
+            reaction_time: 0.4,
+            retreat_health: 0.3,
+            dodge_reaction: 0.2,
+            dodge_chance: 0.6,
+        }
+    }
+
+    pub fn hard() -> Self {
+        Self {
+            name: "hard".to_string(),
+            sight_range: 800.0,
+            attack_range: 350.0,
+            aim_error: 1.0,
+            aim_tolerance: 4.0,
+            lead: 1.0,
+            reaction_time: 0.15,
+            retreat_health: 0.5,
+            dodge_reaction: 0.05,
+            dodge_chance: 0.9,
+        }
+    }
+
+    /// One of the presets by its name, e.g hard
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Self::easy()),
+            "normal" => Some(Self::normal()),
+            "hard" => Some(Self::hard()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self::normal()
+    }
+}
+
+/// What an NPC is up to
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AiState {
+    /// Nothing to do, so drive around the map
+    Patrol,
+    /// Drive to where a tank was last seen
+    Chase(Handle),
+    /// Stop and shoot at a tank in sight
+    Attack(Handle),
+    /// Heal up, either with an item in the inventory or by going for one
+    Retreat,
+    /// Drive over a pickup
+    Collect(Handle),
+    /// Get out of the way of a projectile
+    Evade(Handle),
+}
+
+/// A tank seen by an NPC, where it was and how fast it was going
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sighting {
+    pub handle: Handle,
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+}
+
+/// A projectile in flight, where it is and how fast it's going
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Projectile {
+    pub handle: Handle,
+    pub position: (f32, f32),
+    pub velocity: (f32, f32),
+}
+
+/// A projectile about to hit an NPC
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Threat {
+    pub handle: Handle,
+    /// Seconds until it hits
+    pub time_to_impact: f32,
+    /// A unit vector pointing the quickest way out of its path
+    pub away: (f32, f32),
+}
+
+/// Everything an NPC knows about the world when deciding what to do
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Perception {
+    pub here: (f32, f32),
+    /// From 0 to 1
+    pub health_fraction: f32,
+    /// The inventory slot of a heal item, if it's got one
+    pub heal_slot: Option<usize>,
+    /// The closest enemy tank in sight
+    pub enemy: Option<Sighting>,
+    /// Where an enemy tank was when last seen, if it's not in sight anymore but was recently
+    pub remembered: Option<Sighting>,
+    /// The closest pickup which heals, and the closest pickup of any kind which can be picked up
+    pub heal_pickup: Option<(Handle, (f32, f32))>,
+    pub pickup: Option<(Handle, (f32, f32))>,
+    /// The projectile which is going to hit it soonest, out of those it's noticed
+    pub threat: Option<Threat>,
+}
+
+fn distance(first: (f32, f32), second: (f32, f32)) -> f32 {
+    (first.0 - second.0).hypot(first.1 - second.1)
+}
+
+/// Picks what an NPC should do next. Dodging comes first, then healing when it's hurt, then fighting whatever
+/// it sees or has just seen, then picking stuff up, and driving around when there's nothing else to do
+pub fn decide(perception: &Perception, difficulty: &Difficulty) -> AiState {
+    // Nothing's more important than not getting shot
+    if let Some(threat) = perception.threat {
+        return AiState::Evade(threat.handle);
+    }
+    let wounded = perception.health_fraction <= difficulty.retreat_health;
+    if wounded && (perception.heal_slot.is_some() || perception.heal_pickup.is_some()) {
+        return AiState::Retreat;
+    }
+    if let Some(enemy) = perception.enemy {
+        if distance(perception.here, enemy.position) <= difficulty.attack_range {
+            return AiState::Attack(enemy.handle);
+        }
+        return AiState::Chase(enemy.handle);
+    }
+    if let Some(enemy) = perception.remembered {
+        return AiState::Chase(enemy.handle);
+    }
+    match perception.pickup {
+        Some((item, _)) => AiState::Collect(item),
+        None => AiState::Patrol,
+    }
+}
+
+/// Where to aim from a point so that a projectile of some speed hits a target moving in a straight
+/// line, or as much ahead of the target as the lead says, from 0 to 1. Targets too fast to catch up
+/// with are led as far as they'll get while the shot flies to where they are now.
+/// Shots of infinite speed, i.e hitscan ones, are aimed right at the target
+pub fn aim_point(from: (f32, f32), target: Sighting, projectile_speed: f32, lead: f32) -> (f32, f32) {
+    if projectile_speed.is_infinite() {
+        return target.position;
+    }
+    let (p_x, p_y) = (target.position.0 - from.0, target.position.1 - from.1);
+    let (v_x, v_y) = target.velocity;
+    // When the shot meets the target: |p + v·t| = speed·t, a quadratic in t
+    let a = v_x * v_x + v_y * v_y - projectile_speed * projectile_speed;
+    let b = 2.0 * (p_x * v_x + p_y * v_y);
+    let c = p_x * p_x + p_y * p_y;
+    let straight = c.sqrt() / projectile_speed.max(1.0);
+    let time = if a.abs() < f32::EPSILON {
+        if b < 0.0 { -c / b } else { straight }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            straight
+        } else {
+            let root = discriminant.sqrt();
+            let (first, second) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+            // The soonest time that isn't in the past
+            match (first > 0.0, second > 0.0) {
+                (true, true) => first.min(second),
+                (true, false) => first,
+                (false, true) => second,
+                (false, false) => straight,
+            }
+        }
+    };
+    (target.position.0 + v_x * time * lead, target.position.1 + v_y * time * lead)
+}
+
+/// Whether a projectile flying straight on is going to hit a circle around a point, e.g the body of a
+/// tank, and if so when and which way to go to get out of its path
+pub fn predict_hit(here: (f32, f32), radius: f32, projectile: Projectile) -> Option<Threat> {
+    let (r_x, r_y) = (projectile.position.0 - here.0, projectile.position.1 - here.1);
+    let (v_x, v_y) = projectile.velocity;
+    let speed_squared = v_x * v_x + v_y * v_y;
+    if speed_squared < f32::EPSILON {
+        return None;
+    }
+    // When it's closest, it's moving away after that
+    let approach = r_x * v_x + r_y * v_y;
+    let closest_time = -approach / speed_squared;
+    if closest_time <= 0.0 {
+        return None;
+    }
+    let (closest_x, closest_y) = (r_x + v_x * closest_time, r_y + v_y * closest_time);
+    let miss = closest_x.hypot(closest_y);
+    if miss >= radius {
+        return None;
+    }
+    // When it first touches the circle: |r + v·t| = radius, already inside if that's in the past
+    let discriminant = approach * approach - speed_squared * (r_x * r_x + r_y * r_y - radius * radius);
+    let time_to_impact = ((-approach - discriminant.max(0.0).sqrt()) / speed_squared).max(0.0);
+    // Straight away from where it passes closest, or to either side if it's coming dead on
+    let away = if miss > 1e-3 {
+        (-closest_x / miss, -closest_y / miss)
+    } else {
+        let speed = speed_squared.sqrt();
+        (-v_y / speed, v_x / speed)
+    };
+    Some(Threat {
+        handle: projectile.handle,
+        time_to_impact,
+        away,
+    })
+}
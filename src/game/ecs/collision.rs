@@ -1,13 +1,26 @@
-use crate::game::ecs::{System, Entity, Velocity, Position};
+use crate::game::ecs::{system_id, System, SystemId, Entity, Velocity, VelocitySystem, Position, ScriptDescription};
 use crate::game::ServerContext;
-use std::collections::HashMap;
-use collider::{HbId, Collider, HbProfile, Hitbox, HbEvent};
-use crate::net::Handle;
-use collider::geom::{Shape, v2};
+use crate::game::ecs::{bounding_radius, hitbox_contact, Contact, Hitbox, PlacedShape};
+use std::collections::HashSet;
+use crate::net::{Handle, NULL_HANDLE};
+use std::rc::Rc;
 
 /// A collision script is just a handler that takes regular system parameters as well as two indices
-/// to the collided objects
-pub type CollisionScript = fn(me: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext);
+/// to the collided objects and how they overlap, the contact normal pointing from me to other
+pub trait CollisionScript {
+    fn on_collision(&self, me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext);
+
+    /// How to refer to this script by name, if it can be
+    fn description(&self) -> Option<&ScriptDescription> {
+        None
+    }
+}
+
+impl<F: Fn(usize, usize, Contact, &mut [Entity], &mut ServerContext)> CollisionScript for F {
+    fn on_collision(&self, me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext) {
+        (self)(me, other, contact, entities, ctx)
+    }
+}
 
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum CollisionClass {
@@ -26,86 +39,163 @@ impl CollisionClass {
     }
 }
 
-#[derive(Copy, Clone)]
-/// It's just a handle (for the sake of the collision library), a class which dictates which
-/// components can collide with which, and a script to call upon a collision
-pub struct CollisionComponent {
-    handle: Handle,
-    class: CollisionClass,
-    script: CollisionScript,
+/// The layers collidable things can be on, one bit each. Entities of new kinds can use any bits
+/// not taken here, or share a layer with something similar
+pub const TANK_LAYER: u32 = 1;
+pub const BULLET_LAYER: u32 = 1 << 1;
+pub const WALL_LAYER: u32 = 1 << 2;
+pub const ITEM_LAYER: u32 = 1 << 3;
+pub const ALL_LAYERS: u32 = u32::MAX;
+/// The team of things which are on nobody's team, and so can't be friends with anyone
+pub const NO_TEAM: u8 = 0;
+
+/// Decides what an entity can collide with, regardless of what kind of entity it is
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct CollisionFilter {
+    /// The layers the entity is on
+    pub layers: u32,
+    /// The layers the entity collides with. Both entities have to want to collide for it to happen
+    pub mask: u32,
+    /// Who the entity belongs to, e.g the tank which shot a bullet. A tank belongs to itself
+    pub owner: Handle,
+    /// Whether to pass through anything else belonging to the same owner
+    pub ignore_owner: bool,
+    pub team: u8,
+    /// Whether to pass through anything else on the same team, unless that's NO_TEAM
+    pub ignore_team: bool,
 }
 
-impl CollisionComponent {
-    pub fn new_tank(handle: Handle, script: CollisionScript) -> Self {
+impl CollisionFilter {
+    /// Collides with everything on any of the layers of the mask
+    pub fn new(layers: u32, mask: u32) -> Self {
         Self {
-            handle,
-            class: CollisionClass::Tank(handle),
-            script,
+            layers,
+            mask,
+            owner: NULL_HANDLE,
+            ignore_owner: false,
+            team: NO_TEAM,
+            ignore_team: false,
         }
     }
-    pub fn new_bullet(handle: Handle, shooter_handle: Handle, script: CollisionScript) -> Self {
-        Self {
-            handle,
-            class: CollisionClass::Bullet(shooter_handle),
-            script,
+
+    /// How each class of things collides, unless told otherwise
+    pub fn from_class(class: CollisionClass) -> Self {
+        match class {
+            // Tanks don't get hit by their own bullets, and bullets of the same tank don't hit each other
+            CollisionClass::Tank(owner) => Self::new(TANK_LAYER, ALL_LAYERS).with_owner(owner, true),
+            // Bullets of a team don't hit anyone on it, tanks of a team still bump into each other
+            CollisionClass::Bullet(owner) => Self::new(BULLET_LAYER, ALL_LAYERS)
+                .with_owner(owner, true)
+                .with_team(NO_TEAM, true),
+            // Two walls shouldn't collide
+            CollisionClass::Wall(..) => Self::new(WALL_LAYER, ALL_LAYERS & !WALL_LAYER),
+            CollisionClass::Item => Self::new(ITEM_LAYER, ALL_LAYERS),
         }
     }
 
-    pub fn new_wall(handle: Handle, script: CollisionScript, width: f32, height: f32) -> Self {
-        Self {
-            handle,
-            class: CollisionClass::Wall(width, height),
-            script,
+    pub fn with_owner(mut self, owner: Handle, ignore_owner: bool) -> Self {
+        self.owner = owner;
+        self.ignore_owner = ignore_owner;
+        self
+    }
+
+    pub fn with_team(mut self, team: u8, ignore_team: bool) -> Self {
+        self.team = team;
+        self.ignore_team = ignore_team;
+        self
+    }
+
+    /// Decides whether two things can collide with one another
+    pub fn can_collide(&self, other: &CollisionFilter) -> bool {
+        if self.mask & other.layers == 0 || other.mask & self.layers == 0 {
+            return false;
         }
+        let ignoring_owner = self.ignore_owner || other.ignore_owner;
+        if ignoring_owner && self.owner != NULL_HANDLE && self.owner == other.owner {
+            return false;
+        }
+        let ignoring_team = self.ignore_team || other.ignore_team;
+        !(ignoring_team && self.team != NO_TEAM && self.team == other.team)
+    }
+}
+
+#[derive(Clone)]
+/// It's just a handle, a class which says what kind of thing this is to the scripts of others,
+/// a filter which dictates what it can collide with, and a script to call upon a collision
+pub struct CollisionComponent {
+    handle: Handle,
+    class: CollisionClass,
+    filter: CollisionFilter,
+    script: Rc<dyn CollisionScript>,
+}
+
+impl CollisionComponent {
+    pub fn new_tank(handle: Handle, script: impl CollisionScript + 'static) -> Self {
+        Self::from_shared(handle, CollisionClass::Tank(handle), Rc::new(script))
+    }
+    pub fn new_bullet(handle: Handle, shooter_handle: Handle, script: impl CollisionScript + 'static) -> Self {
+        Self::from_shared(handle, CollisionClass::Bullet(shooter_handle), Rc::new(script))
+    }
+
+    pub fn new_wall(handle: Handle, script: impl CollisionScript + 'static, width: f32, height: f32) -> Self {
+        Self::from_shared(handle, CollisionClass::Wall(width, height), Rc::new(script))
     }
 
-    pub fn new_item(handle: Handle, script: CollisionScript) -> Self {
+    pub fn new_item(handle: Handle, script: impl CollisionScript + 'static) -> Self {
+        Self::from_shared(handle, CollisionClass::Item, Rc::new(script))
+    }
+
+    /// Creates a component from an already shared script, e.g one constructed from a ScriptLibrary.
+    /// It collides like any other of its class until given a filter of its own
+    pub fn from_shared(handle: Handle, class: CollisionClass, script: Rc<dyn CollisionScript>) -> Self {
         Self {
             handle,
-            class: CollisionClass::Item,
+            class,
+            filter: CollisionFilter::from_class(class),
             script,
         }
     }
 
+    pub fn with_filter(mut self, filter: CollisionFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
     pub fn get_collision_class(&self) -> CollisionClass {
         self.class
     }
-}
 
-/// Use of the collider crate mandates implementation of this struct. See the collider docs.
-impl HbProfile for CollisionComponent {
-    fn id(&self) -> u64 {
-        self.handle
-    }
-
-    fn can_interact(&self, other: &Self) -> bool {
-        let item_involved = self.class == CollisionClass::Item || other.class == CollisionClass::Item;
-        if item_involved {
-            true
-        } else if !self.class.is_wall() && !other.class.is_wall() {
-            let my_handle = match self.class {
-                CollisionClass::Tank(handle) => handle,
-                CollisionClass::Bullet(handle) => handle,
-                _ => unreachable!()
-            };
-            let other_handle = match other.class {
-                CollisionClass::Tank(handle) => handle,
-                CollisionClass::Bullet(handle) => handle,
-                _ => unreachable!()
-            };
-            my_handle != other_handle
-        } else {
-            // Two walls shouldn't collide
-            !(self.class.is_wall() && other.class.is_wall())
-        }
+    pub fn get_filter(&self) -> CollisionFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: CollisionFilter) {
+        self.filter = filter;
     }
 
-    fn cell_width() -> f64 {
-        50.0 // idunno
+    pub fn get_script(&self) -> Rc<dyn CollisionScript> {
+        self.script.clone()
     }
+}
 
-    fn padding() -> f64 {
-        0.01
+/// What the CollisionSystem needs to know about a collidable entity during a tick
+struct Body {
+    index: usize,
+    filter: CollisionFilter,
+    hitbox: Hitbox,
+    // Where the entity is at the start of the tick, x, y and angle
+    start: (f32, f32, f32),
+    // Velocity in px/s along x and y
+    velocity: (f32, f32),
+    // Nothing of the hitbox is further away than this from the entity's position
+    reach: f32,
+}
+
+impl Body {
+    fn place(&self, time: f32) -> (f32, f32, Vec<PlacedShape>) {
+        let x = self.start.0 + self.velocity.0 * time;
+        let y = self.start.1 + self.velocity.1 * time;
+        (x, y, self.hitbox.place(x, y, self.start.2))
     }
 }
 
@@ -113,76 +203,106 @@ pub struct CollisionSystem;
 
 impl System for CollisionSystem {
     fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
-        let mut entity_indices_by_handle = HashMap::new();
-        for i in 0..entities.len() {
-            let handle = entities[i].get_handle();
-            if entities[i].has_component::<CollisionComponent>() {
-                entity_indices_by_handle.insert(handle, i);
-            }
-            if let Some(collision_comp) = entities[i].get_component_mut::<CollisionComponent>() {
-                // Ensure that handles match, due to certain server side code, they might not
-                collision_comp.handle = handle;
-            }
-        }
-        let mut collider = Collider::new();
-        for (handle, i) in &entity_indices_by_handle {
-            let entity = &entities[*i];
-            let collision_comp =
-                *entity.get_component::<CollisionComponent>().unwrap();
-            let velocity = entity.get_component::<Velocity>();
-            let position = entity.get_component::<Position>();
-            if position.is_some() {
-                let position = position.unwrap();
-                let (vel_x, vel_y) = if let Some(velocity) = velocity {
-                    let angle = position.get_angle();
-                    (
-                        angle.to_radians().cos() * velocity.get_velocity(),
-                        angle.to_radians().sin() * velocity.get_velocity()
-                    )
-                } else {
-                    (0.0, 0.0)
-                };
-                let hitbox = hitbox_from_class(
-                    collision_comp.class,
-                    position.get_x(), position.get_y(),
-                    vel_x, vel_y,
-                );
-                collider.add_hitbox(collision_comp, hitbox);
-            }
+        let mut bodies = Vec::new();
+        for (index, entity) in entities.iter_mut().enumerate() {
+            let handle = entity.get_handle();
+            let (class, filter) = match entity.get_component_mut::<CollisionComponent>() {
+                Some(collision_comp) => {
+                    // Ensure that handles match, due to certain server side code, they might not
+                    collision_comp.handle = handle;
+                    (collision_comp.class, collision_comp.filter)
+                }
+                None => continue,
+            };
+            let (x, y, angle) = match entity.get_component::<Position>() {
+                Some(position) => (position.get_x(), position.get_y(), position.get_angle()),
+                None => continue,
+            };
+            let velocity = entity
+                .get_component::<Velocity>()
+                .map(|velocity| velocity.get_velocity())
+                .unwrap_or((0.0, 0.0));
+            // Entities without a hitbox of their own get the default one of their class
+            let hitbox = entity
+                .get_component::<Hitbox>()
+                .cloned()
+                .unwrap_or_else(|| Hitbox::from_class(class));
+            let reach = bounding_radius(&hitbox.place(x, y, angle), (x, y));
+            bodies.push(Body {
+                index,
+                filter,
+                hitbox,
+                start: (x, y, angle),
+                velocity,
+                reach,
+            });
         }
-        let delta_time = ctx.delta_time() as f64;
-        // simulate collisions, but not for longer than delta_time
-        while collider.time() < delta_time {
-            // time_step forward into the next collision. Doesn't actually happen in "real" time
-            // it's quite fast.
-            let time = collider.next_time().min(delta_time);
-            collider.set_time(time);
-            if let Some((event, profile_1, profile_2)) = collider.next() {
-                let entity_index1 = entity_indices_by_handle[&profile_1.handle];
-                let entity_index2 = entity_indices_by_handle[&profile_2.handle];
-                // Call collision scripts
-                (profile_1.script)(entity_index1, entity_index2, entities, ctx);
-                (profile_2.script)(entity_index2, entity_index1, entities, ctx);
+        // Fast things could pass right through thin things between two ticks, so the tick is
+        // split up into steps during which nothing moves further than MAX_STEP_DISTANCE
+        const MAX_STEP_DISTANCE: f32 = 5.0;
+        const MAX_STEPS: usize = 32;
+        let delta_time = ctx.delta_time();
+        let fastest = bodies
+            .iter()
+            .map(|body| (body.velocity.0 * body.velocity.0 + body.velocity.1 * body.velocity.1).sqrt())
+            .fold(0.0, f32::max);
+        let steps = ((fastest * delta_time / MAX_STEP_DISTANCE).ceil() as usize).clamp(1, MAX_STEPS);
+        // Each pair only collides once per tick
+        let mut collided = HashSet::new();
+        for step in 0..steps {
+            let time = delta_time * step as f32 / steps as f32;
+            let placed: Vec<_> = bodies.iter().map(|body| body.place(time)).collect();
+            for first in 0..bodies.len() {
+                for second in first + 1..bodies.len() {
+                    let (body_1, body_2) = (&bodies[first], &bodies[second]);
+                    if collided.contains(&(first, second)) || !body_1.filter.can_collide(&body_2.filter) {
+                        continue;
+                    }
+                    // Scripts may have deleted either of them already
+                    if entities[body_1.index].deleted() || entities[body_2.index].deleted() {
+                        continue;
+                    }
+                    let (x1, y1, shapes_1) = &placed[first];
+                    let (x2, y2, shapes_2) = &placed[second];
+                    // Don't bother with the shapes if they aren't even close to each other
+                    let distance = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+                    if distance > body_1.reach + body_2.reach {
+                        continue;
+                    }
+                    if let Some(contact) = hitbox_contact(shapes_1, shapes_2) {
+                        collided.insert((first, second));
+                        // Scripts resolve the overlap from where things are now rather than where
+                        // they'll overlap later during the tick, so subtract how far they'll have
+                        // moved towards each other until then
+                        let relative = (body_1.velocity.0 - body_2.velocity.0, body_1.velocity.1 - body_2.velocity.1);
+                        let approach = (relative.0 * contact.normal.0 + relative.1 * contact.normal.1) * time;
+                        let contact = Contact {
+                            normal: contact.normal,
+                            depth: (contact.depth - approach).max(0.0),
+                        };
+                        let flipped = Contact {
+                            normal: (-contact.normal.0, -contact.normal.1),
+                            depth: contact.depth,
+                        };
+                        // Call collision scripts
+                        let (index_1, index_2) = (body_1.index, body_2.index);
+                        let script_1 = collision_script(&entities[index_1]);
+                        let script_2 = collision_script(&entities[index_2]);
+                        script_1.on_collision(index_1, index_2, contact, entities, ctx);
+                        script_2.on_collision(index_2, index_1, flipped, entities, ctx);
+                    }
+                }
             }
         }
     }
+
+    // Collisions are predicted from velocities, so they should be handled before anything moves
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<VelocitySystem>()]
+    }
 }
 
-/// Generates default hitboxes from each collision class, because passing hitboxes into the
-/// prefab functions is way too much of a pain. See the collider docs for more info
-fn hitbox_from_class(class: CollisionClass, x: f32, y: f32, vel_x: f32, vel_y: f32) -> Hitbox {
-    match class {
-        CollisionClass::Tank(_) => Shape::circle(50.0)
-            .place(v2(x as f64, y as f64))
-            .moving(v2(vel_x as f64, vel_y as f64)),
-        CollisionClass::Bullet(_) => Shape::circle(2.5)
-            .place(v2(x as f64, y as f64))
-            .moving(v2(vel_x as f64, vel_y as f64)),
-        CollisionClass::Wall(width, height) => Shape::rect(v2(width as f64, height as f64))
-            .place(v2(x as f64 + 0.5 * (width as f64), y as f64 + 0.5 * (height as f64)))
-            .moving(v2(vel_x as f64, vel_y as f64)),
-        CollisionClass::Item => Shape::circle(25.0)
-            .place(v2(x as f64, y as f64))
-            .moving(v2(vel_x as f64, vel_y as f64))
-    }
-}
\ No newline at end of file
+/// Gets a hold of an entity's collision script, so it can be called with all the entities
+fn collision_script(entity: &Entity) -> Rc<dyn CollisionScript> {
+    entity.get_component::<CollisionComponent>().unwrap().script.clone()
+}
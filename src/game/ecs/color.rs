@@ -1,15 +1,13 @@
-use crate::game::ecs::{Entity, System};
-use crate::game::ServerContext;
-use crate::net::Event;
+use crate::game::ecs::Replicated;
+use crate::net::{Event, Handle};
 use ggez::graphics::Color;
 
-/// It's a wrapper for a color that keeps track of when its color changes
+/// It's a wrapper for a color
 pub struct ColorComponent {
     red: f32,
     green: f32,
     blue: f32,
     alpha: f32,
-    has_changed: bool,
 }
 
 impl ColorComponent {
@@ -19,7 +17,6 @@ impl ColorComponent {
             green,
             blue,
             alpha,
-            has_changed: true,
         }
     }
     pub fn from_color(color: Color) -> Self {
@@ -43,45 +40,23 @@ impl ColorComponent {
     pub fn get_rgba(&self) -> (f32, f32, f32, f32) {
         (self.red, self.green, self.blue, self.blue)
     }
-    pub fn set_red(&mut self, red: f32)  {
-        if red != self.red {
-            self.has_changed = true;
-        }
+    pub fn set_red(&mut self, red: f32) {
         self.red = red;
     }
-    pub fn set_green(&mut self, green: f32)  {
-        if green != self.green {
-            self.has_changed = true;
-        }
+    pub fn set_green(&mut self, green: f32) {
         self.green = green;
     }
-    pub fn set_blue(&mut self, blue: f32)  {
-        if blue != self.blue {
-            self.has_changed = true;
-        }
+    pub fn set_blue(&mut self, blue: f32) {
         self.blue = blue;
     }
-    pub fn set_alpha(&mut self, alpha: f32)  {
-        if alpha != self.alpha {
-            self.has_changed = true;
-        }
+    pub fn set_alpha(&mut self, alpha: f32) {
         self.alpha = alpha;
     }
 }
 
-/// A system which checks if any colored entity changes color and relays that change to the clients
-pub struct ColorSystem;
-
-impl System for ColorSystem {
-    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
-        for entity in entities {
-            let handle = entity.get_handle();
-            if let Some(color) = entity.get_component_mut::<ColorComponent>() {
-                if color.has_changed {
-                    ctx.push_event(Event::Color(handle, color.get_color()));
-                    color.has_changed = false;
-                }
-            }
-        }
+/// Any change in color is relayed to the clients
+impl Replicated for ColorComponent {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Color(handle, self.get_color())
     }
 }
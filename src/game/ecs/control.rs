@@ -1,19 +1,42 @@
-use crate::game::ecs::{Entity, System};
+use crate::game::ecs::{Entity, ScriptDescription, Stage, System};
 use crate::game::ServerContext;
 use ggez::event::KeyCode;
 use std::collections::HashSet;
+use std::rc::Rc;
 
-pub type ControlScript = fn(&mut Entity, &mut ServerContext, HashSet<KeyCode>, f32);
+/// A script deciding what an entity does given the keys held down on its input device
+pub trait ControlScript {
+    // Parameters: owning entity, keys pressed and delta_time
+    fn on_input(&self, entity: &mut Entity, ctx: &mut ServerContext, keys: HashSet<KeyCode>, delta_time: f32);
+
+    /// How to refer to this script by name, if it can be
+    fn description(&self) -> Option<&ScriptDescription> {
+        None
+    }
+}
+
+impl<F: Fn(&mut Entity, &mut ServerContext, HashSet<KeyCode>, f32)> ControlScript for F {
+    fn on_input(&self, entity: &mut Entity, ctx: &mut ServerContext, keys: HashSet<KeyCode>, delta_time: f32) {
+        (self)(entity, ctx, keys, delta_time)
+    }
+}
 
 pub struct ControlComponent {
-    // Parameters: owning entity, keys pressed and delta_time
-    script: ControlScript,
+    script: Rc<dyn ControlScript>,
     // Index of whatever input device the component is subscribed to
     input_device_index: usize,
 }
 
 impl ControlComponent {
-    pub fn new(input_device_index: usize, script: ControlScript) -> Self {
+    pub fn new(input_device_index: usize, script: impl ControlScript + 'static) -> Self {
+        Self {
+            script: Rc::new(script),
+            input_device_index,
+        }
+    }
+
+    /// Creates a component from an already shared script, e.g one constructed from a ScriptLibrary
+    pub fn from_shared(input_device_index: usize, script: Rc<dyn ControlScript>) -> Self {
         Self {
             script,
             input_device_index,
@@ -23,6 +46,10 @@ impl ControlComponent {
     pub fn get_input_device_index(&self) -> usize {
         self.input_device_index
     }
+
+    pub fn get_script(&self) -> Rc<dyn ControlScript> {
+        self.script.clone()
+    }
 }
 
 /// A system which gets input/keyboard info from the clients via the server and serves it up
@@ -33,15 +60,16 @@ impl System for ControlSystem {
     fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
         for entity in entities {
             if let Some(component) = entity.get_component::<ControlComponent>() {
-                let input_device_index = component.input_device_index;
+                let script = component.script.clone();
+                let keys = ctx.pressed_keys(component.input_device_index).clone();
+                let delta_time = ctx.delta_time();
                 // Call the control script, passing in the relevant keyboard information
-                (component.script)(
-                    entity,
-                    ctx,
-                    ctx.pressed_keys(input_device_index).clone(),
-                    ctx.delta_time(),
-                );
+                script.on_input(entity, ctx, keys, delta_time);
             }
         }
     }
+
+    fn stage(&self) -> Stage {
+        Stage::Input
+    }
 }
@@ -1,19 +1,45 @@
-use crate::game::ecs::{Entity, System};
+use crate::game::ecs::{Entity, ScriptDescription, Stage, System};
 use crate::game::ServerContext;
+use std::rc::Rc;
 
-pub type DeathScript = fn(&mut Entity, &mut ServerContext);
+/// A script executed right before an entity is removed from the game world
+pub trait DeathScript {
+    fn on_death(&self, me: &mut Entity, ctx: &mut ServerContext);
+
+    /// How to refer to this script by name, if it can be
+    fn description(&self) -> Option<&ScriptDescription> {
+        None
+    }
+}
+
+impl<F: Fn(&mut Entity, &mut ServerContext)> DeathScript for F {
+    fn on_death(&self, me: &mut Entity, ctx: &mut ServerContext) {
+        (self)(me, ctx)
+    }
+}
 
 /// A struct holding a script to be executed before the owning entity is removed from the game world
 pub struct DeathComponent {
-    script: DeathScript
+    script: Rc<dyn DeathScript>
 }
 
 impl DeathComponent {
-    pub fn new(script: DeathScript) -> Self {
+    pub fn new(script: impl DeathScript + 'static) -> Self {
+        Self {
+            script: Rc::new(script)
+        }
+    }
+
+    /// Creates a component from an already shared script, e.g one constructed from a ScriptLibrary
+    pub fn from_shared(script: Rc<dyn DeathScript>) -> Self {
         Self {
             script
         }
     }
+
+    pub fn get_script(&self) -> Rc<dyn DeathScript> {
+        self.script.clone()
+    }
 }
 
 /// It keeps track of the dying
@@ -26,10 +52,16 @@ impl System for ReaperSystem {
                 if let Some(death_component) = entity.get_component::<DeathComponent>() {
                     // This is the last frame before the entity is removed from the game world
                     // by the server, so trigger it's death script.
-                    (death_component.script)(entity, ctx);
+                    let script = death_component.script.clone();
+                    script.on_death(entity, ctx);
                 }
             }
         }
     }
+
+    // Handles deleted entities, so it comes last
+    fn stage(&self) -> Stage {
+        Stage::Cleanup
+    }
 }
 
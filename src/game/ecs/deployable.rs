@@ -0,0 +1,77 @@
+use crate::game::ecs::{CollisionComponent, Entity, Stage, System, NO_TEAM};
+use crate::game::ServerContext;
+use crate::net::{Handle, NULL_HANDLE};
+
+/// Who put something into the world and which team they were on, e.g the tank which laid a mine.
+/// Whatever it hurts is hurt on their behalf, and it leaves them and their teammates alone
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Owner {
+    handle: Handle,
+    team: u8,
+}
+
+impl Owner {
+    pub fn new(handle: Handle, team: u8) -> Self {
+        Self { handle, team }
+    }
+
+    /// Owned by whoever owns the entity, i.e the tank itself, on the team it collides for
+    pub fn of(entity: &Entity) -> Self {
+        let team = match entity.get_component::<CollisionComponent>() {
+            Some(collision) => collision.get_filter().team,
+            None => NO_TEAM,
+        };
+        Self::new(entity.get_handle(), team)
+    }
+
+    pub fn get_handle(&self) -> Handle {
+        self.handle
+    }
+
+    pub fn get_team(&self) -> u8 {
+        self.team
+    }
+
+    /// Whether an entity is the owner or on their team. Nobody's on NO_TEAM together
+    pub fn is_friendly(&self, entity: &Entity) -> bool {
+        if self.handle != NULL_HANDLE && entity.get_handle() == self.handle {
+            return true;
+        }
+        self.team != NO_TEAM && Owner::of(entity).team == self.team
+    }
+}
+
+/// Keeps something harmless for a number of seconds after it's been put down, like a mine
+pub struct Arming(f32);
+
+impl Arming {
+    pub fn new(seconds: f32) -> Self {
+        Self(seconds)
+    }
+
+    pub fn get_time_left(&self) -> f32 {
+        self.0
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.0 <= 0.0
+    }
+}
+
+/// Counts down until things are armed
+pub struct ArmingSystem;
+
+impl System for ArmingSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let delta_time = ctx.delta_time();
+        for entity in entities {
+            if let Some(arming) = entity.get_component_mut::<Arming>() {
+                arming.0 = (arming.0 - delta_time).max(0.0);
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+}
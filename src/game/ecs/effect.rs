@@ -0,0 +1,209 @@
+use crate::game::ecs::{system_id, ControlSystem, Entity, Replicated, Stage, System, SystemId, WeaponSystem};
+use crate::game::ServerContext;
+use crate::net::{Event, Handle};
+
+/// The kinds of status effects. Each has a strength, which is a multiplier for whatever it modifies
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum EffectKind {
+    // Multiplies how fast a tank accelerates and turns
+    SpeedBoost,
+    // Multiplies how fast weapons cool down and reload
+    RapidFire,
+    // Multiplies the damage taken, so anything below 1 protects
+    Shield,
+    // Like a SpeedBoost, only meant to be below 1
+    Slow,
+}
+
+/// Every kind of effect, in the order they're sent in
+pub const ALL_EFFECT_KINDS: [EffectKind; 4] = [EffectKind::SpeedBoost, EffectKind::RapidFire, EffectKind::Shield, EffectKind::Slow];
+
+/// How long an effect can be extended to at most, in seconds
+pub const MAX_EFFECT_DURATION: f32 = 30.0;
+
+/// What happens when an effect is applied while another of its kind is still going
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Stacking {
+    // Whichever lasts longer and is stronger wins, the effect doesn't get any better
+    Refresh,
+    // The time left adds up, up to MAX_EFFECT_DURATION
+    Extend,
+}
+
+impl EffectKind {
+    /// The name effects of this kind are saved and scripted under
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffectKind::SpeedBoost => "speed_boost",
+            EffectKind::RapidFire => "rapid_fire",
+            EffectKind::Shield => "shield",
+            EffectKind::Slow => "slow",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL_EFFECT_KINDS.iter().find(|kind| kind.name() == name).cloned()
+    }
+
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            // Shields would get out of hand if they could be kept up forever
+            EffectKind::Shield | EffectKind::Slow => Stacking::Refresh,
+            EffectKind::SpeedBoost | EffectKind::RapidFire => Stacking::Extend,
+        }
+    }
+}
+
+/// An effect with some time left on it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub strength: f32,
+    // Seconds until it wears off
+    pub remaining: f32,
+    // How long it lasted to begin with, for showing how much of it is left
+    pub duration: f32,
+}
+
+impl Effect {
+    pub fn new(kind: EffectKind, strength: f32, duration: f32) -> Self {
+        Self {
+            kind,
+            strength,
+            remaining: duration,
+            duration,
+        }
+    }
+}
+
+/// The effects an entity is under, at most one of each kind.
+/// The clients get told about them whenever one's applied or wears off, they count down on their own
+#[derive(Default)]
+pub struct StatusEffects {
+    effects: Vec<Effect>,
+}
+
+impl StatusEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    /// Applies an effect, stacking it with one of the same kind if there already is one
+    pub fn apply(&mut self, effect: Effect) {
+        let existing = match self.effects.iter_mut().find(|existing| existing.kind == effect.kind) {
+            Some(existing) => existing,
+            None => {
+                self.effects.push(effect);
+                return;
+            }
+        };
+        match effect.kind.stacking() {
+            Stacking::Refresh => {
+                if effect.remaining > existing.remaining {
+                    existing.remaining = effect.remaining;
+                    existing.duration = effect.duration;
+                }
+            }
+            Stacking::Extend => {
+                existing.remaining = (existing.remaining + effect.remaining).min(MAX_EFFECT_DURATION);
+                existing.duration = existing.duration.max(existing.remaining);
+            }
+        }
+        // Slows are stronger the lower they go
+        let stronger = match effect.kind {
+            EffectKind::Shield | EffectKind::Slow => effect.strength < existing.strength,
+            EffectKind::SpeedBoost | EffectKind::RapidFire => effect.strength > existing.strength,
+        };
+        if stronger {
+            existing.strength = effect.strength;
+        }
+    }
+
+    /// Counts down all effects, returning whether any of them wore off
+    pub fn wait(&mut self, delta_time: f32) -> bool {
+        let count = self.effects.len();
+        for effect in &mut self.effects {
+            effect.remaining -= delta_time;
+        }
+        self.effects.retain(|effect| effect.remaining > 0.0);
+        self.effects.len() != count
+    }
+
+    /// Multiplies the strengths of all effects of some kinds, 1 if there aren't any
+    fn multiplier(&self, kinds: &[EffectKind]) -> f32 {
+        self.effects
+            .iter()
+            .filter(|effect| kinds.contains(&effect.kind))
+            .map(|effect| effect.strength)
+            .product()
+    }
+
+    /// How much faster than usual it accelerates and turns
+    pub fn speed_multiplier(&self) -> f32 {
+        self.multiplier(&[EffectKind::SpeedBoost, EffectKind::Slow])
+    }
+
+    /// How much faster than usual its weapons cool down and reload
+    pub fn fire_rate_multiplier(&self) -> f32 {
+        self.multiplier(&[EffectKind::RapidFire])
+    }
+
+    /// How much of the damage dealt to it is actually taken
+    pub fn damage_multiplier(&self) -> f32 {
+        self.multiplier(&[EffectKind::Shield])
+    }
+}
+
+/// Tell the clients which effects an entity is under and how long they'll last
+impl Replicated for StatusEffects {
+    fn to_event(&self, handle: Handle) -> Event {
+        let effects = self.effects.iter().map(|effect| (effect.kind, effect.remaining, effect.duration)).collect();
+        Event::Effects(handle, effects)
+    }
+}
+
+/// Applies an effect to an entity, if it can be under effects at all.
+/// Returns whether it could
+pub fn apply_effect(entity: &mut Entity, effect: Effect) -> bool {
+    match entity.get_component_mut::<StatusEffects>() {
+        Some(effects) => {
+            effects.apply(effect);
+            true
+        }
+        None => false,
+    }
+}
+
+/// A multiplier of the effects an entity is under, 1 if it isn't under any
+pub fn effect_multiplier(entity: &Entity, multiplier: fn(&StatusEffects) -> f32) -> f32 {
+    entity.get_component::<StatusEffects>().map(multiplier).unwrap_or(1.0)
+}
+
+/// Counts down the effects everything is under, before they get to modify anything
+pub struct StatusEffectSystem;
+
+impl System for StatusEffectSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let delta_time = ctx.delta_time();
+        for entity in entities {
+            if let Some(state) = entity.get_replicated_state::<StatusEffects>() {
+                // The clients count down on their own, they only need to hear when something wears off
+                if state.untracked().wait(delta_time) {
+                    state.invalidate();
+                }
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Input
+    }
+
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<ControlSystem>(), system_id::<WeaponSystem>()]
+    }
+}
@@ -0,0 +1,81 @@
+use crate::game::ecs::DamageType;
+use crate::game::graphics::MeshType;
+use crate::net::Handle;
+use std::collections::VecDeque;
+
+/// Things that happen in the game world which other systems might want to react to,
+/// e.g for scoring, sound or stats. Unlike network Events these never leave the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    // An entity (first handle) took damage of some type from another (second handle, or NULL_HANDLE if no one)
+    Damaged(Handle, Handle, u16, DamageType),
+    // An entity (first handle) was killed by another (second handle, or NULL_HANDLE if no one)
+    Killed(Handle, Handle),
+    // Something which isn't a tank, like a barrier, was destroyed by an entity (or NULL_HANDLE if no one)
+    Destroyed(Handle, Handle),
+    // An entity picked up an item of a certain mesh type
+    PickedUp(Handle, MeshType),
+    // An entity dropped an item of a certain mesh type back into the world
+    Dropped(Handle, MeshType),
+    // An entity used an item of a certain mesh type
+    ItemUsed(Handle, MeshType),
+    // An entity has been spawned into the game world
+    Spawned(Handle, MeshType),
+    // A capture point (first handle) has been captured by an entity (second handle)
+    Captured(Handle, Handle),
+}
+
+/// Where a subscriber is at in the stream of game events. Each subscriber should keep its own
+#[derive(Default)]
+pub struct GameEventReader {
+    cursor: u64,
+}
+
+impl GameEventReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A queue of game events that any system can publish to and subscribe to.
+/// Events are kept around until the end of the tick after they were published, so every
+/// subscriber gets to see every event exactly once, no matter whether it runs before or
+/// after the publisher
+#[derive(Default)]
+pub struct GameEventBus {
+    // Events along with an id which increases by one for each event
+    events: VecDeque<(u64, GameEvent)>,
+    next_id: u64,
+    // The id of the first event published during this tick
+    tick_start: u64,
+}
+
+impl GameEventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn publish(&mut self, event: GameEvent) {
+        self.events.push_back((self.next_id, event));
+        self.next_id += 1;
+    }
+
+    /// Returns all events the reader hasn't seen yet
+    pub fn read(&self, reader: &mut GameEventReader) -> Vec<GameEvent> {
+        let unread = self
+            .events
+            .iter()
+            .filter(|(id, _)| *id >= reader.cursor)
+            .map(|(_, event)| event.clone())
+            .collect();
+        reader.cursor = self.next_id;
+        unread
+    }
+
+    /// Forgets the events published before this tick, everyone's had their chance to read them
+    pub fn end_tick(&mut self) {
+        let tick_start = self.tick_start;
+        self.events.retain(|(id, _)| *id >= tick_start);
+        self.tick_start = self.next_id;
+    }
+}
@@ -1,51 +1,215 @@
-use crate::game::ecs::{Entity, System};
+use crate::game::ecs::{effect_multiplier, CollisionClass, CollisionComponent, Entity, GameEvent, Replicated, Stage, StatusEffects, System};
 use crate::game::ServerContext;
-use crate::net::Event;
+use crate::net::{Event, Handle};
 
-/// A struct which keeps track of health and when it changes
+/// A struct which keeps track of health
 pub struct Health {
-    max_health: u8,
-    health: u8,
-    has_changed: bool,
+    max_health: u16,
+    health: u16,
 }
 
 impl Health {
-    pub fn new(health: u8, max_health: u8) -> Self {
+    pub fn new(health: u16, max_health: u16) -> Self {
         Self {
             max_health,
             health,
-            has_changed: true,
         }
     }
 
-    pub fn get_health(&self) -> u8 {
+    pub fn get_health(&self) -> u16 {
         self.health
     }
 
-    pub fn set_health(&mut self, new_health: u8) {
+    pub fn get_max_health(&self) -> u16 {
+        self.max_health
+    }
+
+    pub fn set_health(&mut self, new_health: u16) {
         if new_health <= self.max_health {
-            if new_health != self.health {
-                self.has_changed = true;
-            }
             self.health = new_health;
         }
     }
 }
 
-/// A system which relays any changes in health back to the clients
-pub struct HealthSystem;
+/// Tell the clients the new health amount of this entity whenever it changes,
+/// along with the max health so they know how full the health bar should be
+impl Replicated for Health {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Health(handle, self.health, self.max_health)
+    }
+}
+
+/// What kind of thing dealt some damage, which decides what can protect against it
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum DamageType {
+    // Bullets and anything else hitting something directly
+    Bullet,
+    // Blasts hitting everything around them, like rockets
+    Explosion,
+    // The map itself, like hazards. Armor doesn't help against it
+    Environment,
+}
+
+/// Soaks up a share of the damage its entity takes until it's worn through, as put on by armor items.
+/// Damage from the environment goes right around it
+pub struct Armor {
+    points: u16,
+    max_points: u16,
+    // The fraction of each hit it takes instead of the health, from 0 to 1
+    absorption: f32,
+    // Whatever share of earlier hits didn't add up to a whole point yet, so small hits wear it down too
+    carried: f32,
+}
+
+impl Armor {
+    pub fn new(points: u16, max_points: u16, absorption: f32) -> Self {
+        Self {
+            points,
+            max_points,
+            absorption,
+            carried: 0.0,
+        }
+    }
+
+    pub fn get_points(&self) -> u16 {
+        self.points
+    }
 
-impl System for HealthSystem {
+    pub fn get_max_points(&self) -> u16 {
+        self.max_points
+    }
+
+    pub fn get_absorption(&self) -> f32 {
+        self.absorption
+    }
+
+    pub fn set_points(&mut self, points: u16) {
+        self.points = points.min(self.max_points);
+    }
+
+    /// Takes its share of some damage, returning whatever's left over for the health
+    pub fn absorb(&mut self, damage: u16, kind: DamageType) -> u16 {
+        if kind == DamageType::Environment {
+            return damage;
+        }
+        let share = damage as f32 * self.absorption + self.carried;
+        self.carried = share.fract();
+        let absorbed = (share as u16).min(self.points).min(damage);
+        self.points -= absorbed;
+        damage - absorbed
+    }
+}
+
+/// Makes its entity shrug off all damage for a number of seconds, such as right after spawning
+pub struct Invulnerable(f32);
+
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Self(seconds)
+    }
+
+    pub fn get_time_left(&self) -> f32 {
+        self.0
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.0 > 0.0
+    }
+}
+
+/// Damages an entity with Health on behalf of another entity (or NULL_HANDLE if no one),
+/// announcing it and deleting the entity if it dies. Any shield and Armor take their share first,
+/// and Invulnerable entities aren't hurt at all. Returns whether any damage was dealt
+pub fn deal_damage(entity: &mut Entity, source: Handle, damage: u16, kind: DamageType, ctx: &mut ServerContext) -> bool {
+    if entity.get_component::<Invulnerable>().is_some_and(Invulnerable::is_active) {
+        return false;
+    }
+    let target = entity.get_handle();
+    // Shields cut down the damage before the armor gets to it
+    let damage = (damage as f32 * effect_multiplier(entity, StatusEffects::damage_multiplier)).round() as u16;
+    // Only touch the armor if there's something to absorb, otherwise it'd change for nothing
+    let damage = match entity.get_component_mut::<Armor>() {
+        Some(armor) if armor.get_points() > 0 => armor.absorb(damage, kind),
+        _ => damage,
+    };
+    let new_health = match entity.get_component_mut::<Health>() {
+        // This check is important since there might be multiple hits in a single frame
+        // and since health is an unsigned integer we don't want to underflow it and crash the game!
+        Some(health) if health.get_health() > 0 && damage > 0 => {
+            let new_health = health.get_health().saturating_sub(damage);
+            health.set_health(new_health);
+            new_health
+        }
+        _ => return false,
+    };
+    ctx.publish(GameEvent::Damaged(target, source, damage, kind));
+    if new_health == 0 {
+        // o o f - death
+        entity.delete();
+        // Only tanks count as killed, breaking other things with health isn't worth any score
+        let tank = matches!(
+            entity.get_component::<CollisionComponent>().map(CollisionComponent::get_collision_class),
+            Some(CollisionClass::Tank(..))
+        );
+        ctx.publish(if tank { GameEvent::Killed(target, source) } else { GameEvent::Destroyed(target, source) });
+    }
+    true
+}
+
+/// A resource collecting damage for things which can't get at the entities they hurt themselves,
+/// like hitscan shots and explosions found through the SpatialIndex
+#[derive(Default)]
+pub struct PendingDamage {
+    // The target, whoever dealt the damage, how much and what kind
+    hits: Vec<(Handle, Handle, u16, DamageType)>,
+}
+
+impl PendingDamage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, target: Handle, source: Handle, damage: u16, kind: DamageType) {
+        self.hits.push((target, source, damage, kind));
+    }
+}
+
+/// Deals all PendingDamage, late enough in the tick that whatever's been queued up gets dealt
+pub struct DamageSystem;
+
+impl System for DamageSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let hits = match ctx.resource_mut::<PendingDamage>() {
+            Some(pending) => std::mem::take(&mut pending.hits),
+            None => return,
+        };
+        for (target, source, damage, kind) in hits {
+            // Whatever was hit may have died earlier this tick already
+            if let Some(entity) = entities.iter_mut().find(|entity| entity.get_handle() == target && !entity.deleted()) {
+                deal_damage(entity, source, damage, kind, ctx);
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+}
+
+/// Counts down how long things stay invulnerable
+pub struct InvulnerabilitySystem;
+
+impl System for InvulnerabilitySystem {
     fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let delta_time = ctx.delta_time();
         for entity in entities {
-            let handle = entity.get_handle();
-            if let Some(health) = entity.get_component_mut::<Health>() {
-                if health.has_changed {
-                    // Tell the clients the new health amount of this entity
-                    ctx.push_event(Event::Health(handle, health.health));
-                    health.has_changed = false;
-                }
+            if let Some(invulnerable) = entity.get_component_mut::<Invulnerable>() {
+                invulnerable.0 = (invulnerable.0 - delta_time).max(0.0);
             }
         }
     }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
 }
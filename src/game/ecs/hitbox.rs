@@ -0,0 +1,388 @@
+use crate::game::ecs::{CollisionClass, Position, Replicated};
+use crate::game::graphics::{
+    BULLET_RADIUS, ITEM_BOLDNESS, ITEM_SIZE, MINE_RADIUS, ROCKET_LENGTH, ROCKET_WIDTH, TANK_BARREL_LENGTH, TANK_BARREL_WIDTH, TANK_RADIUS,
+};
+use crate::net::{Event, Handle};
+
+/// A part of a hitbox in the entity's own coordinates, the same ones its mesh is built in.
+/// Shapes are rotated along with the entity, around its position
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    // A circle with a center x, y and a radius
+    Circle(f32, f32, f32),
+    // A rectangle with a left x, top y, width and height, just like a ggez Rect
+    Rect(f32, f32, f32, f32),
+    // A convex polygon, its points in either winding order
+    Polygon(Vec<(f32, f32)>),
+}
+
+/// The shapes making up what an entity can collide with.
+/// These should match the entity's mesh, otherwise bullets will be hitting empty air
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hitbox {
+    shapes: Vec<Shape>,
+}
+
+impl Hitbox {
+    pub fn new(shapes: Vec<Shape>) -> Self {
+        Self { shapes }
+    }
+
+    /// The round body and the barrel sticking out in front, see graphics::tank_mesh
+    pub fn tank() -> Self {
+        Self::new(vec![
+            Shape::Circle(0.0, 0.0, TANK_RADIUS),
+            Shape::Rect(0.0, -0.5 * TANK_BARREL_WIDTH, TANK_BARREL_LENGTH, TANK_BARREL_WIDTH),
+        ])
+    }
+
+    /// The round tip and the square tail behind it, see graphics::bullet_mesh
+    pub fn bullet() -> Self {
+        Self::new(vec![
+            Shape::Circle(0.0, 0.0, BULLET_RADIUS),
+            Shape::Rect(-2.0 * BULLET_RADIUS, -BULLET_RADIUS, 2.0 * BULLET_RADIUS, 2.0 * BULLET_RADIUS),
+        ])
+    }
+
+    /// The round tip and the long body behind it, see graphics::rocket_mesh
+    pub fn rocket() -> Self {
+        Self::new(vec![
+            Shape::Circle(0.0, 0.0, 0.5 * ROCKET_WIDTH),
+            Shape::Rect(-ROCKET_LENGTH, -0.5 * ROCKET_WIDTH, ROCKET_LENGTH, ROCKET_WIDTH),
+        ])
+    }
+
+    pub fn wall(width: f32, height: f32) -> Self {
+        Self::new(vec![Shape::Rect(0.0, 0.0, width, height)])
+    }
+
+    /// The two bars of the cross, anchored at the top left corner, see graphics::heal_item_mesh
+    pub fn heal_item() -> Self {
+        let margin = 0.5 * (ITEM_SIZE - ITEM_BOLDNESS);
+        Self::new(vec![
+            Shape::Rect(margin, 0.0, ITEM_BOLDNESS, ITEM_SIZE),
+            Shape::Rect(0.0, margin, ITEM_SIZE, ITEM_BOLDNESS),
+        ])
+    }
+
+    /// The whole crate, anchored at the top left corner, see graphics::weapon_item_mesh
+    pub fn weapon_item() -> Self {
+        Self::new(vec![Shape::Rect(0.0, 0.0, ITEM_SIZE, ITEM_SIZE)])
+    }
+
+    /// The round token, anchored at the top left corner, see graphics::speed_boost_mesh
+    pub fn power_up() -> Self {
+        Self::new(vec![Shape::Circle(0.5 * ITEM_SIZE, 0.5 * ITEM_SIZE, 0.5 * ITEM_SIZE)])
+    }
+
+    /// The round mine, anchored at the top left corner like the items, see graphics::mine_mesh
+    pub fn mine() -> Self {
+        Self::new(vec![Shape::Circle(0.5 * ITEM_SIZE, 0.5 * ITEM_SIZE, MINE_RADIUS)])
+    }
+
+    /// What to use for entities that don't have a hitbox of their own
+    pub fn from_class(class: CollisionClass) -> Self {
+        match class {
+            CollisionClass::Tank(_) => Self::tank(),
+            CollisionClass::Bullet(_) => Self::bullet(),
+            CollisionClass::Wall(width, height) => Self::wall(width, height),
+            CollisionClass::Item => Self::heal_item(),
+        }
+    }
+
+    pub fn get_shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    /// Moves and rotates the shapes into world coordinates
+    pub fn place(&self, x: f32, y: f32, angle: f32) -> Vec<PlacedShape> {
+        let transform = Transform::new(x, y, angle);
+        self.shapes.iter().map(|shape| transform.apply(shape)).collect()
+    }
+
+    pub fn place_at(&self, position: &Position) -> Vec<PlacedShape> {
+        self.place(position.get_x(), position.get_y(), position.get_angle())
+    }
+}
+
+impl Replicated for Hitbox {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Hitbox(handle, self.shapes.clone())
+    }
+}
+
+struct Transform {
+    x: f32,
+    y: f32,
+    cos: f32,
+    sin: f32,
+}
+
+impl Transform {
+    fn new(x: f32, y: f32, angle: f32) -> Self {
+        let radians = angle.to_radians();
+        Self {
+            x,
+            y,
+            cos: radians.cos(),
+            sin: radians.sin(),
+        }
+    }
+
+    fn point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.x + x * self.cos - y * self.sin,
+            self.y + x * self.sin + y * self.cos,
+        )
+    }
+
+    fn apply(&self, shape: &Shape) -> PlacedShape {
+        match shape {
+            Shape::Circle(x, y, radius) => PlacedShape::Circle(self.point((*x, *y)), *radius),
+            Shape::Rect(x, y, width, height) => PlacedShape::Polygon(vec![
+                self.point((*x, *y)),
+                self.point((x + width, *y)),
+                self.point((x + width, y + height)),
+                self.point((*x, y + height)),
+            ]),
+            Shape::Polygon(points) => {
+                PlacedShape::Polygon(points.iter().map(|point| self.point(*point)).collect())
+            }
+        }
+    }
+}
+
+/// A shape in world coordinates. Rectangles are just polygons once they've been rotated
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlacedShape {
+    // A center point and a radius
+    Circle((f32, f32), f32),
+    Polygon(Vec<(f32, f32)>),
+}
+
+/// How two overlapping shapes overlap
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Contact {
+    /// A unit vector pointing from the first shape towards the second
+    pub normal: (f32, f32),
+    /// How far the shapes would have to be moved apart along the normal to stop overlapping
+    pub depth: f32,
+}
+
+impl PlacedShape {
+    fn center(&self) -> (f32, f32) {
+        match self {
+            PlacedShape::Circle(center, _) => *center,
+            PlacedShape::Polygon(points) => {
+                let (sum_x, sum_y) = points
+                    .iter()
+                    .fold((0.0, 0.0), |(sum_x, sum_y), (x, y)| (sum_x + x, sum_y + y));
+                let count = points.len().max(1) as f32;
+                (sum_x / count, sum_y / count)
+            }
+        }
+    }
+
+    /// The smallest and largest values of the shape's points along an axis
+    fn project(&self, axis: (f32, f32)) -> (f32, f32) {
+        match self {
+            PlacedShape::Circle(center, radius) => {
+                let projected = dot(*center, axis);
+                (projected - radius, projected + radius)
+            }
+            PlacedShape::Polygon(points) => points.iter().fold(
+                (f32::INFINITY, f32::NEG_INFINITY),
+                |(min, max), point| {
+                    let projected = dot(*point, axis);
+                    (min.min(projected), max.max(projected))
+                },
+            ),
+        }
+    }
+
+    /// The axes the separating axis theorem has to check for this shape against another
+    fn axes(&self, other: &PlacedShape) -> Vec<(f32, f32)> {
+        match (self, other) {
+            (PlacedShape::Polygon(points), _) => (0..points.len())
+                .map(|i| {
+                    let (x1, y1) = points[i];
+                    let (x2, y2) = points[(i + 1) % points.len()];
+                    (y1 - y2, x2 - x1)
+                })
+                .collect(),
+            // A circle only adds the axis towards the closest point of a polygon
+            (PlacedShape::Circle(center, _), PlacedShape::Polygon(points)) => points
+                .iter()
+                .map(|point| sub(*point, *center))
+                .min_by(|a, b| length(*a).partial_cmp(&length(*b)).unwrap())
+                .into_iter()
+                .collect(),
+            (PlacedShape::Circle(..), PlacedShape::Circle(..)) => Vec::new(),
+        }
+    }
+
+    /// How this shape overlaps another, if it does at all
+    pub fn contact(&self, other: &PlacedShape) -> Option<Contact> {
+        if let (PlacedShape::Circle(a, radius_a), PlacedShape::Circle(b, radius_b)) = (self, other) {
+            let between = sub(*b, *a);
+            let distance = length(between);
+            let depth = radius_a + radius_b - distance;
+            if depth <= 0.0 {
+                return None;
+            }
+            // Circles right on top of each other can be pushed apart in any direction
+            let normal = if distance > 0.0 {
+                scale(between, 1.0 / distance)
+            } else {
+                (1.0, 0.0)
+            };
+            return Some(Contact { normal, depth });
+        }
+        let mut best: Option<Contact> = None;
+        for axis in self.axes(other).into_iter().chain(other.axes(self)) {
+            let axis_length = length(axis);
+            if axis_length == 0.0 {
+                continue;
+            }
+            let axis = scale(axis, 1.0 / axis_length);
+            let (min_a, max_a) = self.project(axis);
+            let (min_b, max_b) = other.project(axis);
+            let depth = max_a.min(max_b) - min_a.max(min_b);
+            if depth <= 0.0 {
+                // Found a gap, so they don't overlap
+                return None;
+            }
+            if best.map(|best| depth < best.depth).unwrap_or(true) {
+                best = Some(Contact { normal: axis, depth });
+            }
+        }
+        // Make the normal point from this shape towards the other one
+        best.map(|mut contact| {
+            if dot(sub(other.center(), self.center()), contact.normal) < 0.0 {
+                contact.normal = scale(contact.normal, -1.0);
+            }
+            contact
+        })
+    }
+
+    /// How far along a ray the shape is first hit, if at all. The direction should be a unit
+    /// vector, and a ray starting inside the shape hits it right away
+    pub fn raycast(&self, origin: (f32, f32), direction: (f32, f32)) -> Option<f32> {
+        match self {
+            PlacedShape::Circle(center, radius) => {
+                let from_center = sub(origin, *center);
+                let outside = dot(from_center, from_center) - radius * radius;
+                if outside <= 0.0 {
+                    return Some(0.0);
+                }
+                let along = dot(from_center, direction);
+                let discriminant = along * along - outside;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let distance = -along - discriminant.sqrt();
+                // Otherwise the circle is behind us
+                if distance >= 0.0 {
+                    Some(distance)
+                } else {
+                    None
+                }
+            }
+            PlacedShape::Polygon(points) => {
+                // Clip the ray against the inside of every edge, whatever's left of it is in the polygon
+                let center = self.center();
+                let (mut enter, mut exit) = (0.0, f32::INFINITY);
+                for i in 0..points.len() {
+                    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                    let mut normal = (b.1 - a.1, a.0 - b.0);
+                    if dot(normal, sub(a, center)) < 0.0 {
+                        normal = scale(normal, -1.0);
+                    }
+                    let towards = dot(normal, direction);
+                    let room = dot(normal, sub(a, origin));
+                    if towards == 0.0 {
+                        // Running parallel to the edge, on the wrong side of it
+                        if room < 0.0 {
+                            return None;
+                        }
+                    } else if towards < 0.0 {
+                        enter = f32::max(enter, room / towards);
+                    } else {
+                        exit = f32::min(exit, room / towards);
+                    }
+                    if enter > exit {
+                        return None;
+                    }
+                }
+                Some(enter)
+            }
+        }
+    }
+
+    /// The radius of a circle around the given point which contains the whole shape
+    fn reach_from(&self, point: (f32, f32)) -> f32 {
+        match self {
+            PlacedShape::Circle(center, radius) => length(sub(*center, point)) + radius,
+            PlacedShape::Polygon(points) => points
+                .iter()
+                .map(|corner| length(sub(*corner, point)))
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+/// The deepest contact between any two parts of a pair of placed hitboxes
+pub fn hitbox_contact(first: &[PlacedShape], second: &[PlacedShape]) -> Option<Contact> {
+    let mut deepest: Option<Contact> = None;
+    for a in first {
+        for b in second {
+            if let Some(contact) = a.contact(b) {
+                if deepest.map(|deepest| contact.depth > deepest.depth).unwrap_or(true) {
+                    deepest = Some(contact);
+                }
+            }
+        }
+    }
+    deepest
+}
+
+/// How far along a ray a placed hitbox is first hit, if at all
+pub fn hitbox_raycast(shapes: &[PlacedShape], origin: (f32, f32), direction: (f32, f32)) -> Option<f32> {
+    shapes
+        .iter()
+        .filter_map(|shape| shape.raycast(origin, direction))
+        .fold(None, |closest: Option<f32>, distance| Some(closest.map_or(distance, |closest| closest.min(distance))))
+}
+
+/// The smallest rectangle containing all of a placed hitbox, as its top left and bottom right corners
+pub fn hitbox_bounds(shapes: &[PlacedShape]) -> ((f32, f32), (f32, f32)) {
+    shapes.iter().fold(
+        ((f32::INFINITY, f32::INFINITY), (f32::NEG_INFINITY, f32::NEG_INFINITY)),
+        |((min_x, min_y), (max_x, max_y)), shape| {
+            let (left, right) = shape.project((1.0, 0.0));
+            let (top, bottom) = shape.project((0.0, 1.0));
+            ((min_x.min(left), min_y.min(top)), (max_x.max(right), max_y.max(bottom)))
+        },
+    )
+}
+
+/// A circle around a point containing all of a placed hitbox, cheap to check before doing it properly
+pub fn bounding_radius(shapes: &[PlacedShape], center: (f32, f32)) -> f32 {
+    shapes.iter().map(|shape| shape.reach_from(center)).fold(0.0, f32::max)
+}
+
+fn dot(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: (f32, f32), factor: f32) -> (f32, f32) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn length(a: (f32, f32)) -> f32 {
+    dot(a, a).sqrt()
+}
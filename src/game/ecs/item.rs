@@ -1,12 +1,27 @@
-use crate::game::ecs::{System, Entity};
-use crate::game::ServerContext;
+use crate::game::ecs::{Entity, Replicated, ScriptDescription};
 use crate::game::graphics::MeshType;
-use crate::net::Event;
+use crate::game::ServerContext;
+use crate::net::{Event, Handle};
+use std::rc::Rc;
 
 /// An item is just a mesh used to render it and a script to trigger upon use
-pub type Item = (MeshType, ItemUseScript);
+pub type Item = (MeshType, Rc<dyn ItemUseScript>);
+
+/// A script triggered when an item is used. Items can spawn things through the context
+pub trait ItemUseScript {
+    fn on_use(&self, user: &mut Entity, ctx: &mut ServerContext);
 
-pub type ItemUseScript = fn(&mut Entity);
+    /// How to refer to this script by name, if it can be
+    fn description(&self) -> Option<&ScriptDescription> {
+        None
+    }
+}
+
+impl<F: Fn(&mut Entity, &mut ServerContext)> ItemUseScript for F {
+    fn on_use(&self, user: &mut Entity, ctx: &mut ServerContext) {
+        (self)(user, ctx)
+    }
+}
 
 /// A component enabling an entity to be picked up as an item
 pub struct PickUpComponent {
@@ -14,67 +29,177 @@ pub struct PickUpComponent {
 }
 
 impl PickUpComponent {
-    pub fn new(item: Item) -> Self {
+    pub fn new(mesh_type: MeshType, script: impl ItemUseScript + 'static) -> Self {
+        Self {
+            item: (mesh_type, Rc::new(script))
+        }
+    }
+
+    /// Creates a component from an already shared script, e.g one constructed from a ScriptLibrary
+    pub fn from_item(item: Item) -> Self {
         Self {
             item
         }
     }
 
     pub fn get_item(&self) -> Item {
-        self.item
+        self.item.clone()
     }
 }
 
+/// How many slots an inventory has, unless it's made with some other number
+pub const INVENTORY_SLOTS: usize = 4;
+/// How many items of the same kind fit in a single slot
+pub const STACK_SIZE: u16 = 5;
+
+/// Whether two items are of the same kind and can go in the same stack.
+/// Items whose scripts can't be described are only the same if they share the script
+fn same_kind(a: &Item, b: &Item) -> bool {
+    a.0 == b.0
+        && match (a.1.description(), b.1.description()) {
+            (Some(a_description), Some(b_description)) => a_description == b_description,
+            _ => Rc::ptr_eq(&a.1, &b.1),
+        }
+}
+
+/// A number of slots holding stacks of items, one of which is selected for using or dropping
 pub struct InventoryComponent {
-    item: Option<Item>,
-    has_changed: bool,
+    // Each slot is either empty or holds an item and how many of it there are
+    slots: Vec<Option<(Item, u16)>>,
+    selected: usize,
+    // Whether the keys were held down the last time they were checked, so holding them
+    // doesn't go through a whole stack in a couple of ticks
+    use_held: bool,
+    drop_held: bool,
 }
 
 impl InventoryComponent {
     pub fn empty() -> Self {
+        Self::with_slots(INVENTORY_SLOTS)
+    }
+
+    pub fn with_slots(slots: usize) -> Self {
         Self {
-            item: None,
-            has_changed: true,
+            slots: (0..slots.max(1)).map(|_| None).collect(),
+            selected: 0,
+            use_held: false,
+            drop_held: false,
         }
     }
 
-    pub fn put_item(&mut self, item: Item) {
-        self.has_changed = true;
-        self.item = Some(item);
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
     }
 
-    pub fn remove_item(&mut self) -> Option<Item> {
-        if self.item.is_some() {
-            self.has_changed = true;
+    /// Selects a slot, returning whether the selection changed
+    pub fn select(&mut self, slot: usize) -> bool {
+        if slot >= self.slots.len() || slot == self.selected {
+            return false;
         }
-        let mut removed_item = None;
-        std::mem::swap(&mut self.item, &mut removed_item);
-        removed_item
+        self.selected = slot;
+        true
     }
 
-    pub fn has_item(&self) -> bool {
-        self.item.is_some()
+    /// Puts an item on a stack of its kind if there's room, in the first empty slot otherwise.
+    /// Returns whether there was room for it at all
+    pub fn put_item(&mut self, item: Item) -> bool {
+        let stack = self.slots.iter_mut().flatten().find(|(other, count)| *count < STACK_SIZE && same_kind(other, &item));
+        if let Some((_, count)) = stack {
+            *count += 1;
+            return true;
+        }
+        match self.slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some((item, 1));
+                true
+            }
+            None => false,
+        }
     }
-}
 
-/// A system which relays any item pick ups or inventory changes to the clients
-pub struct InventorySystem;
-
-impl System for InventorySystem {
-    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
-        for entity in entities {
-            let handle = entity.get_handle();
-            if let Some(inventory) = entity.get_component_mut::<InventoryComponent>() {
-                if inventory.has_changed {
-                    inventory.has_changed = false;
-                    let mesh_type = if inventory.has_item() {
-                        inventory.item.unwrap().0
-                    } else {
-                        MeshType::None
-                    };
-                    ctx.push_event(Event::PickUp(handle, mesh_type))
-                }
+    /// Fills a slot with a stack of items, replacing whatever was in it.
+    /// Returns whether the inventory has that slot at all
+    pub fn put_stack(&mut self, slot: usize, item: Item, count: u16) -> bool {
+        match self.slots.get_mut(slot) {
+            Some(slot) => {
+                *slot = if count > 0 { Some((item, count.min(STACK_SIZE))) } else { None };
+                true
             }
+            None => false,
+        }
+    }
+
+    /// Takes a single item off the stack in the selected slot
+    pub fn take_selected(&mut self) -> Option<Item> {
+        let slot = &mut self.slots[self.selected];
+        let (item, count) = slot.as_mut()?;
+        let item = item.clone();
+        *count -= 1;
+        if *count == 0 {
+            *slot = None;
         }
+        Some(item)
+    }
+
+    pub fn has_item(&self) -> bool {
+        self.slots.iter().any(Option::is_some)
+    }
+
+    /// The item in a slot and how many of it there are, if there's any
+    pub fn peek_slot(&self, slot: usize) -> Option<(&Item, u16)> {
+        self.slots.get(slot)?.as_ref().map(|(item, count)| (item, *count))
     }
-}
\ No newline at end of file
+
+    /// What's in each slot as the clients see it, empty slots hold MeshType::None
+    pub fn contents(&self) -> Vec<(MeshType, u16)> {
+        self.slots
+            .iter()
+            .map(|slot| match slot {
+                Some(((mesh_type, _), count)) => (*mesh_type, *count),
+                None => (MeshType::None, 0),
+            })
+            .collect()
+    }
+}
+
+/// Any item pick ups or inventory changes are relayed to the clients
+impl Replicated for InventoryComponent {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Inventory(handle, self.selected, self.contents())
+    }
+}
+
+/// Something done with an item in an inventory
+pub enum InventoryAction {
+    Use(Item),
+    Drop(Item),
+}
+
+/// Selects a slot of an entity's inventory if asked to, and uses or drops a single item of the
+/// selected slot when those keys are pressed. Using wins if both are. Holding the keys down does
+/// nothing more, they have to be pressed again. Only actual changes get relayed to the clients
+pub fn operate_inventory(entity: &mut Entity, select: Option<usize>, use_held: bool, drop_held: bool) -> Option<InventoryAction> {
+    let state = entity.get_replicated_state::<InventoryComponent>()?;
+    let inventory = state.untracked();
+    let used = use_held && !inventory.use_held;
+    let dropped = drop_held && !inventory.drop_held;
+    inventory.use_held = use_held;
+    inventory.drop_held = drop_held;
+    let mut changed = select.is_some_and(|slot| inventory.select(slot));
+    let action = if used {
+        inventory.take_selected().map(InventoryAction::Use)
+    } else if dropped {
+        inventory.take_selected().map(InventoryAction::Drop)
+    } else {
+        None
+    };
+    changed |= action.is_some();
+    if changed {
+        state.invalidate();
+    }
+    action
+}
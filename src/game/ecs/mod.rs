@@ -13,8 +13,27 @@ mod collision;
 mod death;
 mod scale;
 mod item;
+mod game_event;
+mod replication;
+mod schedule;
+mod score;
+mod script;
+mod hitbox;
+mod persistence;
+mod physics;
+mod spatial;
+mod zone;
+mod weapon;
+mod effect;
+mod spawner;
+mod deployable;
+mod ricochet;
+mod navigation;
+mod ai;
+mod rng;
 
 pub use item::*;
+pub use game_event::*;
 pub use scale::*;
 pub use color::*;
 pub use control::*;
@@ -25,9 +44,26 @@ pub use death::*;
 pub use ttl::*;
 pub use velocity::*;
 pub use npc::*;
+pub use replication::*;
+pub use schedule::*;
+pub use score::*;
+pub use script::*;
+pub use hitbox::*;
+pub use persistence::*;
+pub use physics::*;
+pub use spatial::*;
+pub use zone::*;
+pub use weapon::*;
+pub use effect::*;
+pub use spawner::*;
+pub use deployable::*;
+pub use ricochet::*;
+pub use navigation::*;
+pub use ai::*;
+pub use rng::*;
 
 use crate::game::ServerContext;
-use crate::misc::TypeSet;
+use crate::misc::{State, TypeSet};
 use crate::net::Handle;
 /// This is the entity component system mod. It is largely reused from the last game task
 /// Though the components and systems themselves are new, speaking to the extendability of the code ;)
@@ -98,16 +134,38 @@ impl Entity {
         self.components.insert::<C>(component);
     }
 
+    /// Puts a component which is relayed to the clients whenever it's mutably accessed.
+    /// It's stored wrapped in a State, but can be retrieved like any other component
+    pub fn put_replicated<C: Replicated>(&mut self, component: C) {
+        let mut state = State::new(component);
+        // Make sure the clients hear about the component at least once
+        state.invalidate();
+        self.components.insert::<State<C>>(state);
+    }
+
     pub fn has_component<C: Sized + 'static>(&self) -> bool {
-        self.components.contains::<C>()
+        self.components.contains::<C>() || self.components.contains::<State<C>>()
     }
 
     pub fn get_component<C: Sized + 'static>(&self) -> Option<&C> {
-        self.components.get::<C>()
+        match self.components.get::<C>() {
+            Some(component) => Some(component),
+            None => self.components.get::<State<C>>().map(|state| &**state),
+        }
     }
 
     pub fn get_component_mut<C: Sized + 'static>(&mut self) -> Option<&mut C> {
-        self.components.get_mut::<C>()
+        if self.components.contains::<C>() {
+            self.components.get_mut::<C>()
+        } else {
+            // Dereferencing mutably marks the state as changed
+            self.components.get_mut::<State<C>>().map(|state| &mut **state)
+        }
+    }
+
+    /// Returns the change tracking wrapper of a replicated component, if there is one
+    pub fn get_replicated_state<C: Replicated>(&mut self) -> Option<&mut State<C>> {
+        self.components.get_mut::<State<C>>()
     }
 
     /// Marks an entity for deletion.
@@ -128,4 +186,45 @@ impl Entity {
 /// in certain, limited ways
 pub trait System {
     fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext);
+
+    /// The stage of a tick in which this system runs
+    fn stage(&self) -> Stage {
+        Stage::Simulation
+    }
+
+    /// Systems which must run after this one, if they're scheduled at all
+    fn before(&self) -> Vec<SystemId> {
+        Vec::new()
+    }
+
+    /// Systems which must run before this one, if they're scheduled at all
+    fn after(&self) -> Vec<SystemId> {
+        Vec::new()
+    }
+}
+
+/// Registers every system of the game world. New systems go here, their order is up to them
+pub fn register_systems(scheduler: &mut Scheduler) {
+    scheduler
+        .register(NpcSystem)
+        .register(ControlSystem)
+        .register(WeaponSystem)
+        .register(StatusEffectSystem)
+        .register(RicochetRuleSystem)
+        .register(CollisionSystem)
+        .register(VelocitySystem)
+        .register(SpatialIndexSystem)
+        .register(NavigationSystem::default())
+        .register(DamageSystem)
+        .register(InvulnerabilitySystem)
+        .register(ArmingSystem)
+        .register(ZoneSystem)
+        .register(ItemSpawnerSystem)
+        .register(TtlSystem)
+        .register(ScoreSystem::default())
+        .register(ReplicationSystem::default())
+        .register(ReaperSystem);
+    // Tuning prefabs while the game is running is a development thing
+    #[cfg(debug_assertions)]
+    scheduler.register(prefabs::PrefabReloadSystem::new());
 }
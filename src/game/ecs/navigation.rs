@@ -0,0 +1,265 @@
+use crate::game::ecs::{hitbox_contact, CollisionComponent, Entity, Hitbox, MapBounds, PlacedShape, Position, Stage, System};
+use crate::game::graphics::TANK_RADIUS;
+use crate::game::ServerContext;
+use crate::net::Handle;
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+/// How wide each cell of the navigation grid is in px
+pub const NAV_CELL_SIZE: f32 = 20.0;
+/// How far from any wall the center of a cell has to be for a tank to drive through it, in px
+pub const NAV_CLEARANCE: f32 = TANK_RADIUS + 5.0;
+
+// The cost of stepping to a neighbouring cell straight or diagonally, roughly 1 and √2
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+type Cell = (usize, usize);
+
+/// A resource dividing the map into square cells, each of which a tank can drive through or not
+/// depending on the walls around it. Used to find ways around the walls from one point to another
+pub struct NavGrid {
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    // Row by row, whether a tank would be stuck in a wall at the center of each cell
+    blocked: Vec<bool>,
+}
+
+impl NavGrid {
+    /// A grid over the whole map with nothing in the way
+    pub fn new(bounds: MapBounds, cell_size: f32) -> Self {
+        let columns = ((bounds.width / cell_size).ceil() as usize).max(1);
+        let rows = ((bounds.height / cell_size).ceil() as usize).max(1);
+        Self {
+            cell_size,
+            columns,
+            rows,
+            blocked: vec![false; columns * rows],
+        }
+    }
+
+    /// A grid over the whole map, with every cell too close to one of the walls among the entities blocked
+    pub fn build(entities: &[Entity], bounds: MapBounds, cell_size: f32, clearance: f32) -> Self {
+        let mut grid = Self::new(bounds, cell_size);
+        let walls: Vec<Vec<PlacedShape>> = entities.iter().filter_map(wall_shapes).collect();
+        for row in 0..grid.rows {
+            for column in 0..grid.columns {
+                let body = [PlacedShape::Circle(grid.center_of((column, row)), clearance)];
+                if walls.iter().any(|wall| hitbox_contact(&body, wall).is_some()) {
+                    grid.blocked[row * grid.columns + column] = true;
+                }
+            }
+        }
+        grid
+    }
+
+    pub fn get_cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn get_columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The cell a point is in, points off the map are in the closest cell on it
+    pub fn cell_of(&self, point: (f32, f32)) -> Cell {
+        let column = (point.0 / self.cell_size).floor().max(0.0) as usize;
+        let row = (point.1 / self.cell_size).floor().max(0.0) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    pub fn center_of(&self, (column, row): Cell) -> (f32, f32) {
+        ((column as f32 + 0.5) * self.cell_size, (row as f32 + 0.5) * self.cell_size)
+    }
+
+    pub fn is_blocked(&self, (column, row): Cell) -> bool {
+        self.blocked[row * self.columns + column]
+    }
+
+    /// Whether a tank could be at a point without being stuck in a wall
+    pub fn is_free_at(&self, point: (f32, f32)) -> bool {
+        !self.is_blocked(self.cell_of(point))
+    }
+
+    /// Whether a tank could drive in a straight line from one point to the other without hitting a wall
+    pub fn clear_line(&self, from: (f32, f32), to: (f32, f32)) -> bool {
+        let (d_x, d_y) = (to.0 - from.0, to.1 - from.1);
+        // A few samples per cell so the line doesn't slip past the corner of one
+        let steps = ((d_x.hypot(d_y) / (0.25 * self.cell_size)).ceil() as usize).max(1);
+        (0..=steps).all(|step| {
+            let t = step as f32 / steps as f32;
+            self.is_free_at((from.0 + t * d_x, from.1 + t * d_y))
+        })
+    }
+
+    /// The free cell closest to a cell, itself if it's free
+    fn nearest_free(&self, start: Cell) -> Option<Cell> {
+        let mut visited = vec![false; self.blocked.len()];
+        let mut queue = VecDeque::from([start]);
+        visited[start.1 * self.columns + start.0] = true;
+        while let Some(cell) = queue.pop_front() {
+            if !self.is_blocked(cell) {
+                return Some(cell);
+            }
+            for (neighbour, _) in self.neighbours(cell) {
+                let index = neighbour.1 * self.columns + neighbour.0;
+                if !visited[index] {
+                    visited[index] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        None
+    }
+
+    /// The cells around a cell along with what it costs to step to them. Blocked cells are included,
+    /// but a diagonal step past the corner of a blocked cell isn't, the tank would scrape along it
+    fn neighbours(&self, (column, row): Cell) -> Vec<(Cell, u32)> {
+        let mut neighbours = Vec::with_capacity(8);
+        for d_row in -1i32..=1 {
+            for d_column in -1i32..=1 {
+                if d_row == 0 && d_column == 0 {
+                    continue;
+                }
+                let (c, r) = (column as i32 + d_column, row as i32 + d_row);
+                if c < 0 || r < 0 || c >= self.columns as i32 || r >= self.rows as i32 {
+                    continue;
+                }
+                if d_row != 0 && d_column != 0 {
+                    if self.is_blocked((c as usize, row)) || self.is_blocked((column, r as usize)) {
+                        continue;
+                    }
+                    neighbours.push(((c as usize, r as usize), DIAGONAL_COST));
+                } else {
+                    neighbours.push(((c as usize, r as usize), STRAIGHT_COST));
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// An estimate of the cost from one cell to another which is never too high, as if there were no walls
+    fn heuristic(from: Cell, to: Cell) -> u32 {
+        let d_x = from.0.abs_diff(to.0) as u32;
+        let d_y = from.1.abs_diff(to.1) as u32;
+        STRAIGHT_COST * d_x.max(d_y) + (DIAGONAL_COST - STRAIGHT_COST) * d_x.min(d_y)
+    }
+
+    /// The waypoints of a short way around the walls from one point to the other, not including
+    /// where it starts. Points in or too close to a wall are moved to the nearest spot that isn't.
+    /// None if there's no way there
+    pub fn find_path(&self, from: (f32, f32), to: (f32, f32)) -> Option<Vec<(f32, f32)>> {
+        let start = self.nearest_free(self.cell_of(from))?;
+        let goal = self.nearest_free(self.cell_of(to))?;
+        let index = |(column, row): Cell| row * self.columns + column;
+        // A* over the cells, the open set ordered by the estimated total cost
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from: Vec<Option<Cell>> = vec![None; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((Self::heuristic(start, goal), start)));
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                break;
+            }
+            for (neighbour, step) in self.neighbours(cell) {
+                if self.is_blocked(neighbour) {
+                    continue;
+                }
+                let new_cost = cost[index(cell)] + step;
+                if new_cost < cost[index(neighbour)] {
+                    cost[index(neighbour)] = new_cost;
+                    came_from[index(neighbour)] = Some(cell);
+                    open.push(Reverse((new_cost + Self::heuristic(neighbour, goal), neighbour)));
+                }
+            }
+        }
+        if cost[index(goal)] == u32::MAX {
+            return None;
+        }
+        let mut cells = vec![goal];
+        while let Some(previous) = came_from[index(*cells.last().unwrap())] {
+            cells.push(previous);
+        }
+        cells.reverse();
+        let mut points: Vec<(f32, f32)> = cells.into_iter().map(|cell| self.center_of(cell)).collect();
+        // End up right where we wanted to be rather than the middle of its cell, if it's reachable at all
+        if goal == self.cell_of(to) {
+            *points.last_mut().unwrap() = to;
+        }
+        Some(self.smooth(from, &points))
+    }
+
+    /// Skips every waypoint which can be driven past in a straight line, so the tank doesn't zigzag from cell to cell
+    fn smooth(&self, from: (f32, f32), points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        let mut smoothed = Vec::new();
+        let mut anchor = from;
+        let mut next = 0;
+        while next < points.len() {
+            let mut furthest = next;
+            while furthest + 1 < points.len() && self.clear_line(anchor, points[furthest + 1]) {
+                furthest += 1;
+            }
+            smoothed.push(points[furthest]);
+            anchor = points[furthest];
+            next = furthest + 1;
+        }
+        smoothed
+    }
+
+    /// The center of some cell a tank can be in, if there's any
+    pub fn random_free_point(&self, rng: &mut impl Rng) -> Option<(f32, f32)> {
+        let free: Vec<usize> = (0..self.blocked.len()).filter(|index| !self.blocked[*index]).collect();
+        if free.is_empty() {
+            return None;
+        }
+        let index = free[rng.gen_range(0..free.len())];
+        Some(self.center_of((index % self.columns, index / self.columns)))
+    }
+}
+
+/// Where the shapes of an entity are if it's a wall which is still standing
+fn wall_shapes(entity: &Entity) -> Option<Vec<PlacedShape>> {
+    let class = entity.get_component::<CollisionComponent>()?.get_collision_class();
+    if entity.deleted() || !class.is_wall() {
+        return None;
+    }
+    let position = entity.get_component::<Position>()?;
+    Some(match entity.get_component::<Hitbox>() {
+        Some(hitbox) => hitbox.place_at(position),
+        None => Hitbox::from_class(class).place_at(position),
+    })
+}
+
+/// Keeps the NavGrid resource in line with the walls of the map. Walls don't move, but they can
+/// crumble or be put up, so the grid is rebuilt whenever a different set of them is standing
+#[derive(Default)]
+pub struct NavigationSystem {
+    walls: Vec<Handle>,
+}
+
+impl System for NavigationSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let walls: Vec<Handle> = entities
+            .iter()
+            .filter(|entity| wall_shapes(entity).is_some())
+            .map(Entity::get_handle)
+            .collect();
+        if walls == self.walls && ctx.resource::<NavGrid>().is_some() {
+            return;
+        }
+        let bounds = ctx.resource::<MapBounds>().cloned().unwrap_or_default();
+        ctx.insert_resource(NavGrid::build(entities, bounds, NAV_CELL_SIZE, NAV_CLEARANCE));
+        self.walls = walls;
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+}
@@ -1,78 +1,130 @@
 use crate::game::ecs::position::Position;
 use crate::game::ecs::velocity::Velocity;
-use crate::game::ecs::System;
-use crate::game::ecs::{prefabs, ColorComponent, ControlComponent, Entity, Health};
-use crate::game::graphics::MeshType;
+use crate::game::ecs::{aim_point, decide, predict_hit, AiState, Difficulty, Perception, Projectile, Sighting, Threat};
+use crate::game::ecs::{system_id, ControlSystem, Stage, System, SystemId};
+use crate::game::ecs::{ControlComponent, Entity, GameRng, Health, InventoryComponent, MapBounds, PickUpComponent, Weapon};
+use crate::game::ecs::{CollisionClass, CollisionComponent, NavGrid, SpatialIndex, NO_TEAM, WALL_LAYER};
+use crate::game::graphics::{MeshType, BULLET_RADIUS, ITEM_SIZE, TANK_RADIUS};
 use crate::game::ServerContext;
-use crate::misc::constants::DEFAULT_COLOR;
-use crate::net::{Handle, NULL_HANDLE};
+use crate::net::Handle;
 use ggez::event::KeyCode;
-use ggez::graphics::{Color};
-use ggez::nalgebra::{Point2, angle, norm, wrap};
-use std::collections::HashSet;
-use crate::net::Event::KeyDown;
 use rand::Rng;
-use nalgebra::Cross;
-use ggez::input::mouse::position;
-use crate::game::ecs::CollisionComponent;
-use crate::game::ecs::DeathComponent;
+use std::collections::HashMap;
 
+//How many seconds the NPC remembers where it last saw a tank
+const MEMORY_TIME: f32 = 3.0;
+//How far away the NPC goes for pickups
+const PICKUP_RANGE: f32 = 800.0;
+//How often the NPC plans its way again, the destination might have moved
+const REPLAN_INTERVAL: f32 = 1.0;
+//How far the destination can move before the NPC plans its way there again right away
+const REPLAN_DISTANCE: f32 = 40.0;
+//How close to a waypoint counts as having reached it
+const WAYPOINT_RADIUS: f32 = 20.0;
+//Driving any slower than this for STUCK_TIME seconds means the NPC is up against something
+const STUCK_SPEED: f32 = 20.0;
+const STUCK_TIME: f32 = 0.75;
+const REVERSE_TIME: f32 = 0.4;
+//The NPC only drives forward once it's roughly facing where it's going, otherwise it turns in place
+const DRIVE_ANGLE: f32 = 45.0;
+const TURN_TOLERANCE: f32 = 1.5;
+//Projectiles hitting the NPC any later than this in seconds aren't worth dodging yet, it might have moved by then
+const DODGE_HORIZON: f32 = 1.0;
+//How far from its body a projectile has to pass for the NPC to feel safe, in px
+const DODGE_MARGIN: f32 = 5.0;
+
+//The keys selecting each inventory slot, just like a player's
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
 
 pub struct NPC{
     //The NPC has a target point that it is trying to reach constantly.
     //The point is updated continually
-    target_point: (f32, f32)
+    target_point: (f32, f32),
+    //The points on the way to the target point around the walls, the next one first
+    waypoints: Vec<(f32, f32)>,
+    //Seconds until the NPC plans its way to the target point again
+    until_replan: f32,
+    //Seconds the NPC has been driving without getting anywhere
+    stuck_for: f32,
+    //Seconds left of backing up after getting stuck
+    reversing: f32,
+    difficulty: Difficulty,
+    state: AiState,
+    //The last tank the NPC saw and how many seconds ago that was
+    last_seen: Option<Sighting>,
+    seen_ago: f32,
+    //Seconds until the NPC may shoot at what it's attacking
+    until_shot: f32,
+    //How far off its aim is right now in degrees
+    aim_offset: f32,
+    //Firing and using items only happens when the key is pressed anew, so it has to be let go of in between
+    trigger_held: bool,
+    use_held: bool,
+    //How many seconds each projectile has been coming at the NPC, and whether it's noticed it at all
+    incoming: HashMap<Handle, (f32, bool)>,
 }
 
 impl NPC{
-    fn new(target_point: (f32, f32)) -> Self{
+    pub fn new(target_point: (f32, f32)) -> Self{
+        Self::with_difficulty(target_point, Difficulty::default())
+    }
+    pub fn with_difficulty(target_point: (f32, f32), difficulty: Difficulty) -> Self{
         Self{
-            target_point
+            target_point,
+            waypoints: Vec::new(),
+            until_replan: 0.0,
+            stuck_for: 0.0,
+            reversing: 0.0,
+            difficulty,
+            state: AiState::Patrol,
+            last_seen: None,
+            seen_ago: 0.0,
+            until_shot: 0.0,
+            aim_offset: 0.0,
+            trigger_held: false,
+            use_held: false,
+            incoming: HashMap::new(),
         }
     }
     fn set_target_point(&mut self, point: (f32, f32)){
         self.target_point = point
     }
-    fn get_target_point(&mut self) -> (f32, f32){
+    pub fn get_target_point(&self) -> (f32, f32){
         self.target_point
     }
+    pub fn get_waypoints(&self) -> &[(f32, f32)]{
+        &self.waypoints
+    }
+    pub fn get_difficulty(&self) -> &Difficulty{
+        &self.difficulty
+    }
+    pub fn get_state(&self) -> AiState{
+        self.state
+    }
 }
 
-pub fn npc(
-    handle: Handle,
-    input_device_index: usize,
-    x: f32,
-    y: f32,
-    angle: f32,
-    color: Color,
-) -> Entity {
-    let mut npc = Entity::new(handle);
-    npc.put_component(Position::new(x, y, angle));
-    npc.put_component(player_control_component(input_device_index));
-    npc.put_component(Velocity::new(0.0, 0.0));
-    npc.put_component(MeshType::Tank);
-    npc.put_component(Health::new(50, 50));
-    npc.put_component(ColorComponent::from_color(color));
-    npc.put_component(CollisionComponent::new_tank(handle, prefabs::player::player_collision_script));
-    npc.put_component(false);
-    npc.put_component(NPC::new(generate_random_point()));
-    npc
-}
-
-//The actions of the tank are carried out by its controlcomponent
-fn player_control_component(input_device_index: usize) -> ControlComponent {
-    ControlComponent::new(input_device_index, prefabs::player::player_control_script)
-}
-
-fn generate_random_point() -> (f32, f32){
-    let mut rng = rand::thread_rng();
-    let mut x : f32 = rng.gen_range(0.0..1000.0);
-    let mut y : f32 = rng.gen_range(0.0..500.0);
+fn generate_random_point(bounds: MapBounds, rng: &mut impl Rng) -> (f32, f32){
+    let x : f32 = rng.gen_range(0.0..bounds.width);
+    let y : f32 = rng.gen_range(0.0..bounds.height);
     (x, y)
 }
 
-fn is_close_enough_to_point(first: (f32, f32), second: (f32, f32)) -> bool{
-    (first.0 - second.0).abs() <= 200.0 && (first.1 - second.1).abs() <= 200.0
+//Somewhere a tank can be if the map's been gridded, anywhere on it otherwise
+fn random_point(grid: Option<&NavGrid>, bounds: MapBounds, rng: &mut impl Rng) -> (f32, f32){
+    match grid.and_then(|grid| grid.random_free_point(rng)){
+        Some(point) => point,
+        None => generate_random_point(bounds, rng)
+    }
 }
 
 //This modulus works for negative numbers
@@ -88,7 +140,7 @@ fn normalize_periodicity(mut angle: f32) -> f32{
 }
 
 //Changes the range of angle to (start, end)
-fn change_range(mut angle: f32, start: f32, end: f32) -> f32{
+fn change_range(angle: f32, start: f32, end: f32) -> f32{
     let width = end - start;
     let offset_value = angle - start;
     (offset_value - (offset_value / width).floor()*width) + start
@@ -123,36 +175,347 @@ fn right_or_left(first_angle: f32, second_angle: f32) -> i32{
     }
 }
 
+fn distance(first: (f32, f32), second: (f32, f32)) -> f32{
+    (first.0 - second.0).hypot(first.1 - second.1)
+}
+
+//A tank as the NPCs see it
+struct TankSnapshot{
+    handle: Handle,
+    team: u8,
+    position: (f32, f32),
+    velocity: (f32, f32),
+}
+
+//A projectile as the NPCs see it, along with whose side it's on
+struct ProjectileSnapshot{
+    projectile: Projectile,
+    shooter: Handle,
+    team: u8,
+}
+
+//Something lying around to be picked up, by its middle
+struct PickupSnapshot{
+    handle: Handle,
+    center: (f32, f32),
+    heals: bool,
+    //Whether it goes into the inventory, or is taken right away like a weapon
+    needs_room: bool,
+}
+
+fn snapshot_tank(entity: &Entity) -> Option<TankSnapshot>{
+    let collision = entity.get_component::<CollisionComponent>()?;
+    if entity.deleted() || !matches!(collision.get_collision_class(), CollisionClass::Tank(..)){
+        return None;
+    }
+    let position = entity.get_component::<Position>()?;
+    Some(TankSnapshot{
+        handle: entity.get_handle(),
+        team: collision.get_filter().team,
+        position: (position.get_x(), position.get_y()),
+        velocity: entity.get_component::<Velocity>().map(Velocity::get_velocity).unwrap_or((0.0, 0.0)),
+    })
+}
+
+fn snapshot_projectile(entity: &Entity) -> Option<ProjectileSnapshot>{
+    let collision = entity.get_component::<CollisionComponent>()?;
+    let shooter = match collision.get_collision_class(){
+        CollisionClass::Bullet(shooter) if !entity.deleted() => shooter,
+        _ => return None
+    };
+    let position = entity.get_component::<Position>()?;
+    let velocity = entity.get_component::<Velocity>()?;
+    Some(ProjectileSnapshot{
+        projectile: Projectile{
+            handle: entity.get_handle(),
+            position: (position.get_x(), position.get_y()),
+            velocity: velocity.get_velocity(),
+        },
+        shooter,
+        team: collision.get_filter().team,
+    })
+}
+
+fn snapshot_pickup(entity: &Entity) -> Option<PickupSnapshot>{
+    let collision = entity.get_component::<CollisionComponent>()?;
+    if entity.deleted() || collision.get_collision_class() != CollisionClass::Item{
+        return None;
+    }
+    let position = entity.get_component::<Position>()?;
+    let pick_up = entity.get_component::<PickUpComponent>();
+    //Items are anchored at their top left corner
+    Some(PickupSnapshot{
+        handle: entity.get_handle(),
+        center: (position.get_x() + 0.5 * ITEM_SIZE, position.get_y() + 0.5 * ITEM_SIZE),
+        heals: pick_up.is_some_and(|pick_up| pick_up.get_item().0 == MeshType::Heal),
+        needs_room: pick_up.is_some(),
+    })
+}
+
+//What the NPC knows about itself and its surroundings, all but what it remembers
+fn perceive(npc: &Entity, difficulty: &Difficulty, tanks: &[TankSnapshot], pickups: &[PickupSnapshot], ctx: &ServerContext) -> Perception{
+    let handle = npc.get_handle();
+    let here = npc.get_component::<Position>().map(|position| (position.get_x(), position.get_y())).unwrap_or((0.0, 0.0));
+    let team = npc.get_component::<CollisionComponent>().map(|collision| collision.get_filter().team).unwrap_or(NO_TEAM);
+    let health_fraction = match npc.get_component::<Health>(){
+        Some(health) => health.get_health() as f32 / health.get_max_health().max(1) as f32,
+        None => 1.0
+    };
+    let inventory = npc.get_component::<InventoryComponent>();
+    let contents = inventory.map(InventoryComponent::contents).unwrap_or_default();
+    let heal_slot = contents.iter().position(|(mesh_type, _)| *mesh_type == MeshType::Heal);
+    let has_room = contents.iter().any(|(mesh_type, _)| *mesh_type == MeshType::None);
+
+    //Walls block the view, tanks and everything else don't
+    let index = ctx.resource::<SpatialIndex>();
+    let in_sight = |there: (f32, f32)| index.is_none_or(|index| index.line_of_sight(here, there, WALL_LAYER, handle));
+    let enemy = tanks
+        .iter()
+        .filter(|tank| tank.handle != handle && (team == NO_TEAM || tank.team != team))
+        .filter(|tank| distance(here, tank.position) <= difficulty.sight_range && in_sight(tank.position))
+        .min_by(|a, b| distance(here, a.position).total_cmp(&distance(here, b.position)))
+        .map(|tank| Sighting{handle: tank.handle, position: tank.position, velocity: tank.velocity});
+
+    let reachable = pickups.iter().filter(|pickup| (has_room || !pickup.needs_room) && distance(here, pickup.center) <= PICKUP_RANGE);
+    let closest = |pickups: Vec<&PickupSnapshot>| pickups
+        .into_iter()
+        .min_by(|a, b| distance(here, a.center).total_cmp(&distance(here, b.center)))
+        .map(|pickup| (pickup.handle, pickup.center));
+    Perception{
+        here,
+        health_fraction,
+        heal_slot,
+        enemy,
+        remembered: None,
+        heal_pickup: closest(reachable.clone().filter(|pickup| pickup.heals).collect()),
+        pickup: closest(reachable.collect()),
+        threat: None,
+    }
+}
+
+//Every projectile of somebody else's which is going to hit the NPC soon, noticed or not
+fn incoming_threats(npc: &Entity, projectiles: &[ProjectileSnapshot]) -> Vec<Threat>{
+    let handle = npc.get_handle();
+    let here = match npc.get_component::<Position>(){
+        Some(position) => (position.get_x(), position.get_y()),
+        None => return Vec::new()
+    };
+    let team = npc.get_component::<CollisionComponent>().map(|collision| collision.get_filter().team).unwrap_or(NO_TEAM);
+    projectiles
+        .iter()
+        .filter(|projectile| projectile.shooter != handle && (team == NO_TEAM || projectile.team != team))
+        .filter_map(|projectile| predict_hit(here, TANK_RADIUS + BULLET_RADIUS + DODGE_MARGIN, projectile.projectile))
+        .filter(|threat| threat.time_to_impact <= DODGE_HORIZON)
+        .collect()
+}
+
+//Turns towards a point, returning how far off the NPC is facing in degrees
+fn turn_towards(angle: f32, here: (f32, f32), point: (f32, f32), keys: &mut Vec<KeyCode>) -> f32{
+    //dir_vec is the vector of our desired direction
+    let dir_vec: (f32, f32) = (point.0 - here.0, point.1 - here.1);
+    let dir_vec_angle = change_range(dir_vec.1.atan2(dir_vec.0).to_degrees(), 0.0, 360.0);
+    let current_angle_normalized = normalize_periodicity(angle);
+    let angle_diff = (dir_vec_angle - current_angle_normalized).abs();
+    let angle_diff = angle_diff.min(360.0 - angle_diff);
+    if angle_diff > TURN_TOLERANCE{
+        if right_or_left(current_angle_normalized, dir_vec_angle) == -1{keys.push(KeyCode::Left)}
+        else{keys.push(KeyCode::Right)}
+    }
+    angle_diff
+}
+
+//Drives towards the destination around the walls, backing up whenever it gets stuck
+fn navigate(npc: &mut NPC, position: &Position, speed: f32, destination: (f32, f32), grid: Option<&NavGrid>, delta_time: f32, keys: &mut Vec<KeyCode>){
+    let here = (position.get_x(), position.get_y());
+    if npc.reversing > 0.0{
+        npc.reversing -= delta_time;
+        keys.push(KeyCode::Down);
+        return;
+    }
+    if npc.stuck_for >= STUCK_TIME{
+        npc.stuck_for = 0.0;
+        npc.reversing = REVERSE_TIME;
+        npc.waypoints.clear();
+        keys.push(KeyCode::Down);
+        return;
+    }
+    npc.until_replan -= delta_time;
+    if npc.waypoints.is_empty() || npc.until_replan <= 0.0 || distance(destination, npc.target_point) > REPLAN_DISTANCE{
+        npc.set_target_point(destination);
+        //Without a grid or a way around there's nothing to plan, just head straight there
+        npc.waypoints = grid.and_then(|grid| grid.find_path(here, destination)).unwrap_or_else(|| vec![destination]);
+        npc.until_replan = REPLAN_INTERVAL;
+    }
+    //Waypoints which have been reached are done with, the last one is where the NPC is going though
+    while npc.waypoints.len() > 1 && distance(here, npc.waypoints[0]) <= WAYPOINT_RADIUS{
+        npc.waypoints.remove(0);
+    }
+    let heading_for = npc.waypoints.first().copied().unwrap_or(npc.target_point);
+    let angle_diff = turn_towards(position.get_angle(), here, heading_for, keys);
+    let driving = distance(here, npc.target_point) > WAYPOINT_RADIUS && angle_diff <= DRIVE_ANGLE;
+    if driving{keys.push(KeyCode::Up);}
+    if driving && speed < STUCK_SPEED{npc.stuck_for += delta_time}
+    else{npc.stuck_for = 0.0}
+}
 
-fn update(npc: &mut Entity, ctx: &mut ServerContext, delta_time: f32){
-    let mut current_position = Position::new(0.0, 0.0, 0.0);
-    if let Some(position) = npc.get_component_mut::<Position>(){
-        current_position.set_x(position.get_x());
-        current_position.set_y(position.get_y());
-        current_position.set_angle(position.get_angle());
+//Drives out of a projectile's path, forwards or backwards whichever way the NPC is facing more.
+//Facing along the path doesn't get it anywhere, so it turns sideways as it goes
+fn evade(position: &Position, threat: Threat, keys: &mut Vec<KeyCode>){
+    let here = (position.get_x(), position.get_y());
+    let (sin, cos) = position.get_angle().to_radians().sin_cos();
+    let (away_x, away_y) = threat.away;
+    let along = cos * away_x + sin * away_y;
+    let direction = if along >= 0.0{1.0} else{-1.0};
+    if along.abs() < 0.5{
+        turn_towards(position.get_angle(), here, (here.0 + direction * away_x * 100.0, here.1 + direction * away_y * 100.0), keys);
     }
+    if along >= 0.0{keys.push(KeyCode::Up)}
+    else{keys.push(KeyCode::Down)}
+}
 
+//Lines up a shot at a tank, leading it as well as the NPC can, and fires once it's lined up
+fn attack(npc: &mut NPC, position: &Position, target: Sighting, projectile_speed: f32, delta_time: f32, rng: &mut impl Rng, keys: &mut Vec<KeyCode>){
+    let here = (position.get_x(), position.get_y());
+    let aim = aim_point(here, target, projectile_speed, npc.difficulty.lead);
+    //Being off by some angle is the same as aiming at a point turned about the NPC by it
+    let (sin, cos) = npc.aim_offset.to_radians().sin_cos();
+    let (d_x, d_y) = (aim.0 - here.0, aim.1 - here.1);
+    let aim = (here.0 + d_x * cos - d_y * sin, here.1 + d_x * sin + d_y * cos);
+    let angle_diff = turn_towards(position.get_angle(), here, aim, keys);
+    npc.until_shot -= delta_time;
+    let fire = npc.until_shot <= 0.0 && angle_diff <= npc.difficulty.aim_tolerance && !npc.trigger_held;
+    npc.trigger_held = fire;
+    if fire{
+        keys.push(KeyCode::Space);
+        npc.aim_offset = roll_aim_offset(&npc.difficulty, rng);
+    }
+}
+
+fn roll_aim_offset(difficulty: &Difficulty, rng: &mut impl Rng) -> f32{
+    if difficulty.aim_error <= 0.0{
+        return 0.0;
+    }
+    rng.gen_range(-difficulty.aim_error..=difficulty.aim_error)
+}
+
+fn update(npc: &mut Entity, tanks: &[TankSnapshot], projectiles: &[ProjectileSnapshot], pickups: &[PickupSnapshot], ctx: &mut ServerContext, rng: &mut GameRng, delta_time: f32){
+    let input_device_index = match npc.get_component::<ControlComponent>(){
+        Some(control) => control.get_input_device_index(),
+        None => return
+    };
+    let current_position = match npc.get_component::<Position>(){
+        Some(position) => Position::new(position.get_x(), position.get_y(), position.get_angle()),
+        None => return
+    };
+    let here = (current_position.get_x(), current_position.get_y());
+    let speed = npc.get_component::<Velocity>().map(Velocity::get_speed).unwrap_or(0.0);
+    // Hitscan shots land right away, so there's nothing to lead
+    let projectile_speed = match npc.get_component::<Weapon>().map(Weapon::get_stats) {
+        Some(stats) if stats.is_hitscan() => f32::INFINITY,
+        Some(stats) => stats.speed,
+        None => 0.0,
+    };
+    let difficulty = match npc.get_component::<NPC>(){
+        Some(npc_2) => npc_2.difficulty.clone(),
+        None => return
+    };
+    let mut perception = perceive(npc, &difficulty, tanks, pickups, ctx);
+    let incoming = incoming_threats(npc, projectiles);
+    let bounds = ctx.resource::<MapBounds>().cloned().unwrap_or_default();
+    let grid = ctx.resource::<NavGrid>();
+    let mut keys = Vec::new();
     if let Some(npc_2) = npc.get_component_mut::<NPC>(){
-        //If it close enough to the target point it will generate a new one
-        if is_close_enough_to_point(npc_2.target_point, (current_position.get_x(), current_position.get_y())){
-            npc_2.set_target_point(generate_random_point());
+        //Remember the last tank seen for a while, to go looking for it once it's out of sight
+        match perception.enemy{
+            Some(enemy) => {
+                npc_2.last_seen = Some(enemy);
+                npc_2.seen_ago = 0.0;
+            }
+            None => npc_2.seen_ago += delta_time
         }
-        //dir_vec is the vector of our desired
-        let dir_vec: (f32, f32) = (npc_2.target_point.0 - current_position.get_x(), npc_2.target_point.1 - current_position.get_y());
-        let mut dir_vec_angle = dir_vec.1.atan2(dir_vec.0).to_degrees();
-        let mut dir_vec_angle = change_range(dir_vec_angle, 0.0, 360.0);
-        let mut current_angle_normalized = normalize_periodicity(current_position.get_angle());
+        if npc_2.seen_ago > MEMORY_TIME{
+            npc_2.last_seen = None;
+        }
+        perception.remembered = npc_2.last_seen;
 
-        if right_or_left(current_angle_normalized, dir_vec_angle)== -1{ctx.insert_pressed_key(2, KeyCode::Left)}
-        else{ctx.insert_pressed_key(2, KeyCode::Right)}
+        //Projectiles which aren't coming anymore are forgotten. Whether the NPC notices the others
+        //is up to chance, and even then it takes a moment before it reacts
+        npc_2.incoming.retain(|projectile, _| incoming.iter().any(|threat| threat.handle == *projectile));
+        for threat in &incoming{
+            let (coming_for, _) = npc_2.incoming.entry(threat.handle).or_insert_with(|| (0.0, rng.gen::<f32>() < difficulty.dodge_chance));
+            *coming_for += delta_time;
+        }
+        perception.threat = incoming
+            .iter()
+            .filter(|threat| npc_2.incoming.get(&threat.handle).is_some_and(|(coming_for, noticed)| *noticed && *coming_for >= difficulty.dodge_reaction))
+            .min_by(|a, b| a.time_to_impact.total_cmp(&b.time_to_impact))
+            .copied();
 
-        //Shoot every 50th update and move forward every 2nd update
-        let mut rng = rand::thread_rng();
-        let mut x : i32 = rng.gen_range(0..50);
-        if x == 1{ctx.insert_pressed_key(2, KeyCode::Space);}
-        let mut y : i32 = rng.gen_range(0..2);
-        if y == 1{ctx.insert_pressed_key(2, KeyCode::Up);}
+        let state = decide(&perception, &difficulty);
+        if state != npc_2.state{
+            //Give the NPC a moment to react to what it's seeing, and a fresh plan to get anywhere
+            npc_2.until_shot = difficulty.reaction_time;
+            npc_2.aim_offset = roll_aim_offset(&difficulty, rng);
+            npc_2.waypoints.clear();
+            if state == AiState::Patrol{
+                let point = random_point(grid, bounds, rng);
+                npc_2.set_target_point(point);
+            }
+            npc_2.state = state;
+        }
+        if state != AiState::Retreat{
+            npc_2.use_held = false;
+        }
+        if !matches!(state, AiState::Attack(..)){
+            npc_2.trigger_held = false;
+        }
 
+        match state{
+            AiState::Patrol => {
+                //Off somewhere else once it's got where it was going
+                let mut destination = npc_2.target_point;
+                if distance(here, destination) <= WAYPOINT_RADIUS{
+                    destination = random_point(grid, bounds, rng);
+                }
+                navigate(npc_2, &current_position, speed, destination, grid, delta_time, &mut keys);
+            }
+            AiState::Chase(_) => {
+                let target = perception.enemy.or(perception.remembered).map(|enemy| enemy.position).unwrap_or(npc_2.target_point);
+                //Nothing where it was last seen, give up on it
+                if perception.enemy.is_none() && distance(here, target) <= WAYPOINT_RADIUS{
+                    npc_2.last_seen = None;
+                }
+                navigate(npc_2, &current_position, speed, target, grid, delta_time, &mut keys);
+            }
+            AiState::Attack(_) => {
+                if let Some(enemy) = perception.enemy{
+                    attack(npc_2, &current_position, enemy, projectile_speed, delta_time, rng, &mut keys);
+                }
+            }
+            AiState::Retreat => match (perception.heal_slot, perception.heal_pickup){
+                (Some(slot), _) => {
+                    //Pick the slot and use what's in it, letting go of the key in between uses
+                    keys.push(SLOT_KEYS[slot.min(SLOT_KEYS.len() - 1)]);
+                    if !npc_2.use_held{keys.push(KeyCode::I)}
+                    npc_2.use_held = !npc_2.use_held;
+                }
+                (None, Some((_, there))) => navigate(npc_2, &current_position, speed, there, grid, delta_time, &mut keys),
+                (None, None) => {}
+            },
+            AiState::Evade(_) => {
+                if let Some(threat) = perception.threat{
+                    evade(&current_position, threat, &mut keys);
+                }
+            }
+            AiState::Collect(_) => {
+                if let Some((_, there)) = perception.pickup{
+                    navigate(npc_2, &current_position, speed, there, grid, delta_time, &mut keys);
+                }
+            }
+        }
+    }
+    for key in keys{
+        ctx.insert_pressed_key(input_device_index, key);
     }
 }
 
@@ -160,11 +523,26 @@ pub struct NpcSystem;
 
 impl System for NpcSystem{
     fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        //Everyone's looked at before any NPC acts, so they all see the same thing
+        let tanks: Vec<TankSnapshot> = entities.iter().filter_map(snapshot_tank).collect();
+        let projectiles: Vec<ProjectileSnapshot> = entities.iter().filter_map(snapshot_projectile).collect();
+        let pickups: Vec<PickupSnapshot> = entities.iter().filter_map(snapshot_pickup).collect();
+        //Taken out for the while, the NPCs look at other resources while rolling the dice
+        let mut rng = ctx.take_resource::<GameRng>().unwrap_or_else(GameRng::from_entropy);
         for entity in entities {
-            let handle = entity.get_handle();
-            if let Some(NPC) = entity.get_component_mut::<NPC>(){
-                update(entity, ctx, ctx.delta_time());
+            if entity.has_component::<NPC>() && !entity.deleted(){
+                update(entity, &tanks, &projectiles, &pickups, ctx, &mut rng, ctx.delta_time());
             }
         }
+        ctx.insert_resource(rng);
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Input
+    }
+
+    // NPCs press keys on their input devices, which are read by the ControlSystem
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<ControlSystem>()]
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,853 @@
+use crate::game::ecs::{
+    CollisionClass, CollisionComponent, CollisionFilter, CollisionScript, ColorComponent, ControlComponent, ControlScript,
+    Armor, DeathComponent, DeathScript, Entity, Health, Hitbox, Invulnerable, InventoryComponent, Item, ItemUseScript, PickUpComponent,
+    Position, Replicated, Scale, ScriptDescription, Shape, ScriptLibrary, TimeToLive, Velocity, Physics, NPC, Difficulty,
+    TriggerZone, CapturePoint, Progress, ZoneScript, Weapon, WeaponStats, Effect, EffectKind, StatusEffects,
+    ItemSpawner, Placement, Owner, Arming, Ricochet, RicochetRule,
+    ALL_LAYERS, BULLET_LAYER, ITEM_LAYER, TANK_LAYER, WALL_LAYER,
+};
+use crate::game::graphics::MeshType;
+use crate::misc::constants::ALL_MESH_TYPES;
+use crate::net::Handle;
+use std::str::FromStr;
+
+/// A component which can be written to and read back from a file.
+/// Each component is a single line consisting of its name followed by whitespace separated fields
+pub trait Persistent: Sized + 'static {
+    /// The name a component goes by in files
+    const NAME: &'static str;
+    /// Returns the fields of the component, None if it can't be saved (i.e its script has no name)
+    fn save(&self) -> Option<Vec<String>>;
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self>;
+}
+
+/// Saves a component of an entity, if it has one. Err if the component can't be saved
+type ComponentSaver = fn(&Entity) -> Option<Result<String, String>>;
+/// Reads fields into a component and puts it into the entity, None if the fields are invalid
+type ComponentLoader = fn(&mut Entity, &[&str], &ScriptLibrary) -> Option<()>;
+
+struct ComponentEntry {
+    name: &'static str,
+    save: ComponentSaver,
+    load: ComponentLoader,
+}
+
+fn save_component<C: Persistent>(entity: &Entity) -> Option<Result<String, String>> {
+    let component = entity.get_component::<C>()?;
+    Some(match component.save() {
+        Some(fields) if fields.is_empty() => Ok(C::NAME.to_string()),
+        Some(fields) => Ok(format!("{} {}", C::NAME, fields.join(" "))),
+        None => Err(format!("{} of entity {} can't be saved", C::NAME, entity.get_handle())),
+    })
+}
+
+fn load_component<C: Persistent>(entity: &mut Entity, fields: &[&str], scripts: &ScriptLibrary) -> Option<()> {
+    entity.put_component(C::load(fields, scripts)?);
+    Some(())
+}
+
+fn load_replicated<C: Persistent + Replicated>(entity: &mut Entity, fields: &[&str], scripts: &ScriptLibrary) -> Option<()> {
+    entity.put_replicated(C::load(fields, scripts)?);
+    Some(())
+}
+
+/// Knows how to save and load every persistent component by name
+#[derive(Default)]
+pub struct ComponentRegistry {
+    entries: Vec<ComponentEntry>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<C: Persistent>(mut self) -> Self {
+        self.entries.push(ComponentEntry {
+            name: C::NAME,
+            save: save_component::<C>,
+            load: load_component::<C>,
+        });
+        self
+    }
+
+    /// Registers a component which is put into entities using Entity::put_replicated when loaded
+    pub fn register_replicated<C: Persistent + Replicated>(mut self) -> Self {
+        self.entries.push(ComponentEntry {
+            name: C::NAME,
+            save: save_component::<C>,
+            load: load_replicated::<C>,
+        });
+        self
+    }
+
+    /// Writes all persistent components of an entity, one per line
+    pub fn save_components(&self, entity: &Entity) -> Result<Vec<String>, String> {
+        self.entries
+            .iter()
+            .filter_map(|entry| (entry.save)(entity))
+            .collect()
+    }
+
+    /// Reads a single component line into an entity
+    pub fn load_component(&self, entity: &mut Entity, line: &str, scripts: &ScriptLibrary) -> Result<(), String> {
+        let mut fields = line.split_whitespace();
+        let name = fields.next().unwrap_or_default();
+        let fields: Vec<&str> = fields.collect();
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| format!("Unknown component {}", name))?;
+        (entry.load)(entity, &fields, scripts).ok_or_else(|| format!("Invalid {}: {}", name, line))
+    }
+
+    /// Writes a bunch of entities as text, looking like:
+    /// entity 3
+    /// Position 200 250 0
+    /// ...
+    /// end
+    pub fn save_entities(&self, entities: &[Entity]) -> Result<String, String> {
+        let mut text = String::new();
+        for entity in entities {
+            text.push_str(&format!("entity {}\n", entity.get_handle()));
+            for line in self.save_components(entity)? {
+                text.push_str(&line);
+                text.push('\n');
+            }
+            text.push_str("end\n");
+        }
+        Ok(text)
+    }
+
+    /// Reads entities from text written by save_entities. Empty lines and lines starting with #
+    /// are ignored, as are any lines outside of entities (so they can be used for other things)
+    pub fn load_entities(&self, text: &str, scripts: &ScriptLibrary) -> Result<Vec<Entity>, String> {
+        let mut entities = Vec::new();
+        let mut current: Option<Entity> = None;
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("Line {}: {}", line_index + 1, message);
+            match current.as_mut() {
+                None if line.starts_with("entity") => {
+                    let handle = line
+                        .split_whitespace()
+                        .nth(1)
+                        .and_then(|handle| handle.parse::<Handle>().ok())
+                        .ok_or_else(|| error("Invalid entity handle".to_string()))?;
+                    current = Some(Entity::new(handle));
+                }
+                None => (),
+                Some(_) if line == "end" => entities.push(current.take().unwrap()),
+                Some(entity) => self.load_component(entity, line, scripts).map_err(error)?,
+            }
+        }
+        match current {
+            Some(entity) => Err(format!("Entity {} is missing an end", entity.get_handle())),
+            None => Ok(entities),
+        }
+    }
+}
+
+impl ComponentRegistry {
+    /// All persistent components of the game
+    pub fn with_game_components() -> Self {
+        Self::new()
+            .register::<MeshType>()
+            .register_replicated::<Position>()
+            .register::<Velocity>()
+            .register::<Physics>()
+            .register_replicated::<Health>()
+            .register::<Armor>()
+            .register::<Invulnerable>()
+            .register::<Owner>()
+            .register::<Arming>()
+            .register_replicated::<ColorComponent>()
+            .register_replicated::<Scale>()
+            .register_replicated::<InventoryComponent>()
+            .register::<TimeToLive>()
+            .register::<Ricochet>()
+            .register::<RicochetRule>()
+            .register_replicated::<Hitbox>()
+            .register::<NPC>()
+            .register::<CollisionComponent>()
+            .register::<ControlComponent>()
+            .register::<DeathComponent>()
+            .register::<PickUpComponent>()
+            .register::<TriggerZone>()
+            .register::<CapturePoint>()
+            .register_replicated::<Progress>()
+            .register_replicated::<Weapon>()
+            .register_replicated::<StatusEffects>()
+            .register_replicated::<ItemSpawner>()
+    }
+}
+
+/* Below are the implementations for each persistent component.
+ * Just like the protocol they're pretty straightforward,
+ * so most won't be commented */
+
+fn parse<T: FromStr>(fields: &[&str], index: usize) -> Option<T> {
+    fields.get(index)?.parse().ok()
+}
+
+fn save_script(description: Option<&ScriptDescription>) -> Option<String> {
+    description.map(|description| description.to_string())
+}
+
+fn load_script<T: ?Sized + 'static>(fields: &[&str], index: usize, scripts: &ScriptLibrary) -> Option<std::rc::Rc<T>> {
+    scripts.construct::<T>(&ScriptDescription::parse(fields.get(index)?)?)
+}
+
+fn mesh_type_from_name(name: &str) -> Option<MeshType> {
+    ALL_MESH_TYPES
+        .iter()
+        .find(|mesh_type| format!("{:?}", mesh_type) == name)
+        .cloned()
+}
+
+fn save_item(item: &Item) -> Option<Vec<String>> {
+    Some(vec![format!("{:?}", item.0), save_script(item.1.description())?])
+}
+
+fn load_item(fields: &[&str], scripts: &ScriptLibrary) -> Option<Item> {
+    let mesh_type = mesh_type_from_name(fields.first()?)?;
+    let script = load_script::<dyn ItemUseScript>(fields, 1, scripts)?;
+    Some((mesh_type, script))
+}
+
+impl Persistent for MeshType {
+    const NAME: &'static str = "Mesh";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![format!("{:?}", self)])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        mesh_type_from_name(fields.first()?)
+    }
+}
+
+impl Persistent for Position {
+    const NAME: &'static str = "Position";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![
+            self.get_x().to_string(),
+            self.get_y().to_string(),
+            self.get_angle().to_string(),
+        ])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Position::new(parse(fields, 0)?, parse(fields, 1)?, parse(fields, 2)?))
+    }
+}
+
+impl Persistent for Velocity {
+    const NAME: &'static str = "Velocity";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![
+            self.get_velocity().0.to_string(),
+            self.get_velocity().1.to_string(),
+            self.get_angular_velocity().to_string(),
+        ])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Velocity::new(parse(fields, 0)?, parse(fields, 1)?, parse(fields, 2)?))
+    }
+}
+
+// Forces are only around for a tick at most, so they aren't worth saving
+impl Persistent for Physics {
+    const NAME: &'static str = "Physics";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_mass().to_string(), self.get_drag().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Physics::new(parse(fields, 0)?, parse(fields, 1)?))
+    }
+}
+
+impl Persistent for Health {
+    const NAME: &'static str = "Health";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_health().to_string(), self.get_max_health().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Health::new(parse(fields, 0)?, parse(fields, 1)?))
+    }
+}
+
+impl Persistent for Armor {
+    const NAME: &'static str = "Armor";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![
+            self.get_points().to_string(),
+            self.get_max_points().to_string(),
+            self.get_absorption().to_string(),
+        ])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Armor::new(parse(fields, 0)?, parse(fields, 1)?, parse(fields, 2)?))
+    }
+}
+
+impl Persistent for Invulnerable {
+    const NAME: &'static str = "Invulnerable";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_time_left().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Invulnerable::new(parse(fields, 0)?))
+    }
+}
+
+impl Persistent for ColorComponent {
+    const NAME: &'static str = "Color";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![
+            self.get_red().to_string(),
+            self.get_green().to_string(),
+            self.get_blue().to_string(),
+            self.get_alpha().to_string(),
+        ])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(ColorComponent::new(
+            parse(fields, 0)?,
+            parse(fields, 1)?,
+            parse(fields, 2)?,
+            parse(fields, 3)?,
+        ))
+    }
+}
+
+impl Persistent for Scale {
+    const NAME: &'static str = "Scale";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_width().to_string(), self.get_height().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Scale::new(parse(fields, 0)?, parse(fields, 1)?))
+    }
+}
+
+// As many slots as there are number keys to select them with, inventories are relayed whole
+const MAX_INVENTORY_SLOTS: usize = 9;
+
+impl Persistent for InventoryComponent {
+    const NAME: &'static str = "Inventory";
+
+    // The number of slots and the selected slot, followed by the slot, count, mesh type and
+    // script of each stack, e.g Inventory 4 0 2 3 Heal heal_item(0.5).
+    // No fields at all is an empty inventory
+    fn save(&self) -> Option<Vec<String>> {
+        let mut fields = vec![self.slot_count().to_string(), self.get_selected().to_string()];
+        for slot in 0..self.slot_count() {
+            if let Some((item, count)) = self.peek_slot(slot) {
+                fields.push(slot.to_string());
+                fields.push(count.to_string());
+                fields.append(&mut save_item(item)?);
+            }
+        }
+        Some(fields)
+    }
+
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self> {
+        let mut inventory = InventoryComponent::empty();
+        if fields.is_empty() {
+            return Some(inventory);
+        }
+        let slot_count: usize = parse(fields, 0)?;
+        if slot_count > MAX_INVENTORY_SLOTS {
+            return None;
+        }
+        inventory = InventoryComponent::with_slots(slot_count);
+        inventory.select(parse(fields, 1)?);
+        for stack in fields[2..].chunks(4) {
+            let item = load_item(stack.get(2..)?, scripts)?;
+            if !inventory.put_stack(parse(stack, 0)?, item, parse(stack, 1)?) {
+                return None;
+            }
+        }
+        Some(inventory)
+    }
+}
+
+impl Persistent for TimeToLive {
+    const NAME: &'static str = "TimeToLive";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_time_to_live().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(TimeToLive::new(parse(fields, 0)?))
+    }
+}
+
+impl Persistent for NPC {
+    const NAME: &'static str = "NPC";
+
+    fn save(&self) -> Option<Vec<String>> {
+        let (x, y) = self.get_target_point();
+        Some(vec![x.to_string(), y.to_string(), self.get_difficulty().name.clone()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        let difficulty = Difficulty::by_name(fields.get(2)?)?;
+        Some(NPC::with_difficulty((parse(fields, 0)?, parse(fields, 1)?), difficulty))
+    }
+}
+
+impl Persistent for CollisionComponent {
+    const NAME: &'static str = "Collision";
+
+    // The class, any parameters of the class, the script and lastly anything about the filter
+    // which differs from the class' usual one, e.g: bullet 1 bullet_collision(1) team=2 ignore_team=true
+    fn save(&self) -> Option<Vec<String>> {
+        let mut fields = match self.get_collision_class() {
+            CollisionClass::Tank(owner) => vec!["tank".to_string(), owner.to_string()],
+            CollisionClass::Bullet(shooter) => vec!["bullet".to_string(), shooter.to_string()],
+            CollisionClass::Wall(width, height) => vec!["wall".to_string(), width.to_string(), height.to_string()],
+            CollisionClass::Item => vec!["item".to_string()],
+        };
+        fields.push(save_script(self.get_script().description())?);
+        let usual = CollisionFilter::from_class(self.get_collision_class());
+        let filter = self.get_filter();
+        if filter.layers != usual.layers {
+            fields.push(format!("layers={}", save_layers(filter.layers)));
+        }
+        if filter.mask != usual.mask {
+            fields.push(format!("mask={}", save_layers(filter.mask)));
+        }
+        if filter.owner != usual.owner {
+            fields.push(format!("owner={}", filter.owner));
+        }
+        if filter.ignore_owner != usual.ignore_owner {
+            fields.push(format!("ignore_owner={}", filter.ignore_owner));
+        }
+        if filter.team != usual.team {
+            fields.push(format!("team={}", filter.team));
+        }
+        if filter.ignore_team != usual.ignore_team {
+            fields.push(format!("ignore_team={}", filter.ignore_team));
+        }
+        Some(fields)
+    }
+
+    // The handle is fixed by the CollisionSystem, so any will do here
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self> {
+        let (class, script_index) = match *fields.first()? {
+            "tank" => (CollisionClass::Tank(parse(fields, 1)?), 2),
+            "bullet" => (CollisionClass::Bullet(parse(fields, 1)?), 2),
+            "wall" => (CollisionClass::Wall(parse(fields, 1)?, parse(fields, 2)?), 3),
+            "item" => (CollisionClass::Item, 1),
+            _ => return None,
+        };
+        let script = load_script::<dyn CollisionScript>(fields, script_index, scripts)?;
+        let mut filter = CollisionFilter::from_class(class);
+        for field in &fields[script_index + 1..] {
+            let mut split = field.splitn(2, '=');
+            match (split.next()?, split.next()?) {
+                ("layers", value) => filter.layers = load_layers(value)?,
+                ("mask", value) => filter.mask = load_layers(value)?,
+                ("owner", value) => filter.owner = value.parse().ok()?,
+                ("ignore_owner", value) => filter.ignore_owner = value.parse().ok()?,
+                ("team", value) => filter.team = value.parse().ok()?,
+                ("ignore_team", value) => filter.ignore_team = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(CollisionComponent::from_shared(0, class, script).with_filter(filter))
+    }
+}
+
+impl Persistent for TriggerZone {
+    const NAME: &'static str = "Zone";
+
+    // The layers setting it off, the seconds between Stay events and the script, e.g: Zone tank 1 heal_pad(5)
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![
+            save_layers(self.get_mask()),
+            self.get_interval().to_string(),
+            save_script(self.get_script().description())?,
+        ])
+    }
+
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self> {
+        let script = load_script::<dyn ZoneScript>(fields, 2, scripts)?;
+        Some(TriggerZone::from_shared(load_layers(fields.first()?)?, parse(fields, 1)?, script))
+    }
+}
+
+impl Persistent for CapturePoint {
+    const NAME: &'static str = "Capture";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_owner().to_string(), self.get_claimant().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(CapturePoint::new(parse(fields, 0)?, parse(fields, 1)?))
+    }
+}
+
+impl Persistent for Progress {
+    const NAME: &'static str = "Progress";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.0.to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Progress(parse(fields, 0)?))
+    }
+}
+
+// Collision layers by name, anything else is written as the bits themselves
+const LAYER_NAMES: [(&str, u32); 4] = [
+    ("tank", TANK_LAYER),
+    ("bullet", BULLET_LAYER),
+    ("wall", WALL_LAYER),
+    ("item", ITEM_LAYER),
+];
+
+/// Writes layers like tank+bullet, all, all-wall or none
+fn save_layers(layers: u32) -> String {
+    if layers == 0 {
+        return "none".to_string();
+    }
+    let (prefix, layers) = if layers.count_ones() > 16 {
+        // Mostly everything, so it's shorter to say what's missing
+        ("all", !layers)
+    } else {
+        ("", layers)
+    };
+    let mut names: Vec<String> = LAYER_NAMES
+        .iter()
+        .filter(|(_, layer)| layers & layer != 0)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let unnamed = LAYER_NAMES.iter().fold(layers, |layers, (_, layer)| layers & !layer);
+    if unnamed != 0 {
+        names.push(unnamed.to_string());
+    }
+    if prefix.is_empty() {
+        names.join("+")
+    } else if names.is_empty() {
+        prefix.to_string()
+    } else {
+        format!("{}-{}", prefix, names.join("-"))
+    }
+}
+
+fn load_layers(text: &str) -> Option<u32> {
+    let layer = |name: &str| match LAYER_NAMES.iter().find(|(layer_name, _)| *layer_name == name) {
+        Some((_, layer)) => Some(*layer),
+        None => name.parse().ok(),
+    };
+    if text == "none" {
+        Some(0)
+    } else if text == "all" || text.starts_with("all-") {
+        text.split('-').skip(1).try_fold(ALL_LAYERS, |layers, name| Some(layers & !layer(name)?))
+    } else {
+        text.split('+').try_fold(0, |layers, name| Some(layers | layer(name)?))
+    }
+}
+
+impl Persistent for ControlComponent {
+    const NAME: &'static str = "Control";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![
+            self.get_input_device_index().to_string(),
+            save_script(self.get_script().description())?,
+        ])
+    }
+
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self> {
+        let script = load_script::<dyn ControlScript>(fields, 1, scripts)?;
+        Some(ControlComponent::from_shared(parse(fields, 0)?, script))
+    }
+}
+
+impl Persistent for DeathComponent {
+    const NAME: &'static str = "Death";
+
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![save_script(self.get_script().description())?])
+    }
+
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self> {
+        Some(DeathComponent::from_shared(load_script::<dyn DeathScript>(fields, 0, scripts)?))
+    }
+}
+
+impl Persistent for PickUpComponent {
+    const NAME: &'static str = "PickUp";
+
+    fn save(&self) -> Option<Vec<String>> {
+        save_item(&self.get_item())
+    }
+
+    fn load(fields: &[&str], scripts: &ScriptLibrary) -> Option<Self> {
+        Some(PickUpComponent::from_item(load_item(fields, scripts)?))
+    }
+}
+
+// More points than any sensible hitbox has, so a broken save can't make us count to the moon
+const MAX_POLYGON_POINTS: usize = 64;
+
+impl Persistent for Hitbox {
+    const NAME: &'static str = "Hitbox";
+
+    // Any number of shapes, each starting with its kind, e.g circle 0 0 25 rect 0 -15 30 30.
+    // Polygons say how many points they have, e.g polygon 3 0 0 10 0 0 10
+    fn save(&self) -> Option<Vec<String>> {
+        let mut fields = Vec::new();
+        for shape in self.get_shapes() {
+            let (kind, numbers) = match shape {
+                Shape::Circle(x, y, radius) => ("circle", vec![*x, *y, *radius]),
+                Shape::Rect(x, y, width, height) => ("rect", vec![*x, *y, *width, *height]),
+                Shape::Polygon(points) => {
+                    fields.push("polygon".to_string());
+                    fields.push(points.len().to_string());
+                    let numbers = points.iter().flat_map(|(x, y)| vec![*x, *y]).collect();
+                    fields.extend(numbers_to_fields(numbers));
+                    continue;
+                }
+            };
+            fields.push(kind.to_string());
+            fields.extend(numbers_to_fields(numbers));
+        }
+        Some(fields)
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        let mut shapes = Vec::new();
+        let mut i = 0;
+        while i < fields.len() {
+            let numbers = |from: usize, count: usize| -> Option<Vec<f32>> {
+                (from..from + count).map(|j| parse(fields, j)).collect()
+            };
+            let (shape, length) = match fields[i] {
+                "circle" => {
+                    let n = numbers(i + 1, 3)?;
+                    (Shape::Circle(n[0], n[1], n[2]), 4)
+                }
+                "rect" => {
+                    let n = numbers(i + 1, 4)?;
+                    (Shape::Rect(n[0], n[1], n[2], n[3]), 5)
+                }
+                "polygon" => {
+                    let point_count: usize = parse(fields, i + 1)?;
+                    if point_count > MAX_POLYGON_POINTS {
+                        return None;
+                    }
+                    let number_count = point_count.checked_mul(2)?;
+                    let n = numbers(i + 2, number_count)?;
+                    let points = n.chunks(2).map(|point| (point[0], point[1])).collect();
+                    (Shape::Polygon(points), 2 + number_count)
+                }
+                _ => return None,
+            };
+            shapes.push(shape);
+            i += length;
+        }
+        Some(Hitbox::new(shapes))
+    }
+}
+
+fn numbers_to_fields(numbers: Vec<f32>) -> impl Iterator<Item = String> {
+    numbers.into_iter().map(|number| number.to_string())
+}
+
+impl Persistent for Weapon {
+    const NAME: &'static str = "Weapon";
+
+    // The projectile prefab, its speed and damage, the cooldown, spread and projectiles per shot,
+    // the magazine and reload time, whether it's automatic and lastly the ammo if it isn't full,
+    // e.g: Weapon bullet 800 1 0.8 30 5 6 2 false 4
+    fn save(&self) -> Option<Vec<String>> {
+        let stats = self.get_stats();
+        let mut fields = vec![
+            stats.projectile.clone(),
+            stats.speed.to_string(),
+            stats.damage.to_string(),
+            stats.cooldown.to_string(),
+            stats.spread.to_string(),
+            stats.count.to_string(),
+            stats.magazine.to_string(),
+            stats.reload_time.to_string(),
+            stats.automatic.to_string(),
+        ];
+        if self.get_ammo() != stats.magazine {
+            fields.push(self.get_ammo().to_string());
+        }
+        Some(fields)
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        let stats = WeaponStats {
+            projectile: fields.first()?.to_string(),
+            speed: parse(fields, 1)?,
+            damage: parse(fields, 2)?,
+            cooldown: parse(fields, 3)?,
+            spread: parse(fields, 4)?,
+            count: parse(fields, 5)?,
+            magazine: parse(fields, 6)?,
+            reload_time: parse(fields, 7)?,
+            automatic: parse(fields, 8)?,
+        };
+        let ammo = match fields.get(9) {
+            Some(ammo) => ammo.parse().ok()?,
+            None => stats.magazine,
+        };
+        Some(Weapon::with_ammo(stats, ammo))
+    }
+}
+
+impl Persistent for StatusEffects {
+    const NAME: &'static str = "Effects";
+
+    // The kind, strength, seconds left and duration of each effect, e.g Effects shield 0.5 2 8
+    fn save(&self) -> Option<Vec<String>> {
+        let mut fields = Vec::new();
+        for effect in self.get_effects() {
+            fields.push(effect.kind.name().to_string());
+            fields.push(effect.strength.to_string());
+            fields.push(effect.remaining.to_string());
+            fields.push(effect.duration.to_string());
+        }
+        Some(fields)
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        let mut effects = StatusEffects::new();
+        for effect in fields.chunks(4) {
+            let mut loaded = Effect::new(EffectKind::from_name(effect[0])?, parse(effect, 1)?, parse(effect, 3)?);
+            loaded.remaining = parse(effect, 2)?;
+            effects.apply(loaded);
+        }
+        Some(effects)
+    }
+}
+
+impl Persistent for ItemSpawner {
+    const NAME: &'static str = "Spawner";
+
+    // The cooldown, seconds until the next item, the item that's out there, where items go and the weighted prefabs,
+    // e.g Spawner 10 0 0 area 100 100 200 50 heal_item 3 shield_item 1
+    fn save(&self) -> Option<Vec<String>> {
+        let mut fields = vec![
+            self.get_cooldown().to_string(),
+            self.get_until_spawn().to_string(),
+            self.get_spawned().to_string(),
+        ];
+        match self.get_placement() {
+            Placement::Fixed => fields.push("fixed".to_string()),
+            Placement::Area(x, y, width, height) => {
+                fields.push("area".to_string());
+                fields.extend([x, y, width, height].iter().map(f32::to_string));
+            }
+        }
+        for (prefab, weight) in self.get_table() {
+            fields.push(prefab.clone());
+            fields.push(weight.to_string());
+        }
+        Some(fields)
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        let (placement, table_start) = match *fields.get(3)? {
+            "fixed" => (Placement::Fixed, 4),
+            "area" => (Placement::Area(parse(fields, 4)?, parse(fields, 5)?, parse(fields, 6)?, parse(fields, 7)?), 8),
+            _ => return None,
+        };
+        let mut table = Vec::new();
+        for entry in fields[table_start..].chunks(2) {
+            table.push((entry[0].to_string(), parse(entry, 1)?));
+        }
+        let spawner = ItemSpawner::new(table, parse(fields, 0)?, placement);
+        Some(spawner.with_state(parse(fields, 1)?, parse(fields, 2)?))
+    }
+}
+
+impl Persistent for Owner {
+    const NAME: &'static str = "Owner";
+
+    // The handle of the owner and their team, e.g Owner 1 2
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_handle().to_string(), self.get_team().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Owner::new(parse(fields, 0)?, parse(fields, 1)?))
+    }
+}
+
+impl Persistent for Arming {
+    const NAME: &'static str = "Arming";
+
+    // Seconds until it's armed
+    fn save(&self) -> Option<Vec<String>> {
+        Some(vec![self.get_time_left().to_string()])
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(Arming::new(parse(fields, 0)?))
+    }
+}
+
+fn save_ricochet(ricochet: &Ricochet) -> Vec<String> {
+    vec![ricochet.get_bounces_left().to_string(), ricochet.get_speed_loss().to_string()]
+}
+
+fn load_ricochet(fields: &[&str]) -> Option<Ricochet> {
+    Some(Ricochet::new(parse(fields, 0)?, parse(fields, 1)?))
+}
+
+impl Persistent for Ricochet {
+    const NAME: &'static str = "Ricochet";
+
+    // The bounces left and the fraction of speed lost each bounce, e.g Ricochet 5 0.1
+    fn save(&self) -> Option<Vec<String>> {
+        Some(save_ricochet(self))
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        load_ricochet(fields)
+    }
+}
+
+impl Persistent for RicochetRule {
+    const NAME: &'static str = "RicochetRule";
+
+    // Like a Ricochet, given to every projectile which doesn't have one
+    fn save(&self) -> Option<Vec<String>> {
+        Some(save_ricochet(&self.0))
+    }
+
+    fn load(fields: &[&str], _scripts: &ScriptLibrary) -> Option<Self> {
+        Some(RicochetRule(load_ricochet(fields)?))
+    }
+}
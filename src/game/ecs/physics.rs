@@ -0,0 +1,78 @@
+use crate::game::ecs::Velocity;
+
+/// Gives an entity mass and drag, so it can be pushed around by forces and impulses instead of
+/// having its Velocity set directly. Whatever's applied during a tick piles up here until the
+/// VelocitySystem turns it into velocity
+pub struct Physics {
+    mass: f32,
+    // The fraction of velocity lost per second, both the linear and the angular kind
+    drag: f32,
+    // In mass px/s², applied over the whole tick
+    force: (f32, f32),
+    // In mass degrees/s², applied over the whole tick
+    torque: f32,
+    // In mass px/s, applied all at once
+    impulse: (f32, f32),
+}
+
+impl Physics {
+    pub fn new(mass: f32, drag: f32) -> Self {
+        Self {
+            mass,
+            drag,
+            force: (0.0, 0.0),
+            torque: 0.0,
+            impulse: (0.0, 0.0),
+        }
+    }
+    pub fn get_mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn get_drag(&self) -> f32 {
+        self.drag
+    }
+    /// Pushes steadily, like an engine. Heavier things accelerate slower
+    pub fn apply_force(&mut self, x: f32, y: f32) {
+        self.force.0 += x;
+        self.force.1 += y;
+    }
+    /// Turns steadily, like an engine but for spinning
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.torque += torque;
+    }
+    /// A sudden push, like a hit or a recoil. Doesn't depend on how long the tick is
+    pub fn apply_impulse(&mut self, x: f32, y: f32) {
+        self.impulse.0 += x;
+        self.impulse.1 += y;
+    }
+
+    /// Changes a velocity by everything applied since the last time, then forgets about it
+    pub fn accelerate(&mut self, velocity: &mut Velocity, delta_time: f32) {
+        // Weightless things would fly off to infinity, so just leave them be
+        if self.mass > 0.0 {
+            let (v_x, v_y) = velocity.get_velocity();
+            velocity.set_velocity(
+                v_x + (self.force.0 * delta_time + self.impulse.0) / self.mass,
+                v_y + (self.force.1 * delta_time + self.impulse.1) / self.mass,
+            );
+            let angular_velocity = velocity.get_angular_velocity();
+            velocity.set_angular_velocity(angular_velocity + self.torque * delta_time / self.mass);
+        }
+        self.force = (0.0, 0.0);
+        self.torque = 0.0;
+        self.impulse = (0.0, 0.0);
+    }
+
+    /// Slows a velocity down by the drag. This results in a terminal velocity, so we don't have
+    /// to worry about accumulating too much from forces
+    pub fn slow_down(&self, velocity: &mut Velocity, delta_time: f32) {
+        // Anything slower than this has stopped, otherwise we'd be creeping forever
+        const VELOCITY_THRESHOLD: f32 = 0.01;
+        // We're multiplying here, so the delta_time is accounted for with a power
+        let remaining = (1.0 - self.drag).max(0.0).powf(delta_time);
+        let stop = |v: f32| if v.abs() <= VELOCITY_THRESHOLD { 0.0 } else { v };
+        let (v_x, v_y) = velocity.get_velocity();
+        velocity.set_velocity(stop(v_x * remaining), stop(v_y * remaining));
+        velocity.set_angular_velocity(stop(velocity.get_angular_velocity() * remaining));
+    }
+}
@@ -1,22 +1,24 @@
-use crate::game::ecs::{ColorComponent, Entity, Position, TimeToLive, Velocity, CollisionComponent};
-use crate::game::graphics::MeshType;
-use crate::net::Handle;
-use ggez::graphics::Color;
+use crate::game::ecs::{Entity, CollisionComponent, CollisionClass, CollisionScript, deal_damage, ricochet, DamageType, Contact, Described, ScriptDescription};
+use crate::net::NULL_HANDLE;
 use crate::game::ServerContext;
+use std::rc::Rc;
 
-fn bullet_collision_script(me: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {
-    // Bullets that hit stuff go bye bye
-    entities[me].delete();
+/// Bullets that hit stuff go bye bye, dealing some damage to whatever they hit on the way out.
+/// Bullets that can ricochet bounce off walls instead, still chipping away at any that can be destroyed
+pub(crate) fn bullet_collision_script(damage: u16) -> impl CollisionScript {
+    Described::new("bullet_collision", &[damage as f32], move |me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext| {
+        let shooter = match entities[me].get_component::<CollisionComponent>().map(|c| c.get_collision_class()) {
+            Some(CollisionClass::Bullet(shooter)) => shooter,
+            _ => NULL_HANDLE,
+        };
+        deal_damage(&mut entities[other], shooter, damage, DamageType::Bullet, ctx);
+        if !ricochet(me, other, contact, entities) {
+            entities[me].delete();
+        }
+    })
 }
 
-/// Generates a bullet which will live for at most 2 seconds
-pub fn bullet(handle: Handle, shooter_handle: Handle, x: f32, y: f32, angle: f32, velocity: f32, color: Color) -> Entity {
-    let mut bullet = Entity::new(handle);
-    bullet.put_component(Position::new(x, y, angle));
-    bullet.put_component(Velocity::new(velocity, 0.0));
-    bullet.put_component(TimeToLive::new(2.0));
-    bullet.put_component(MeshType::Bullet);
-    bullet.put_component(ColorComponent::from_color(color));
-    bullet.put_component(CollisionComponent::new_bullet(handle, shooter_handle, bullet_collision_script));
-    bullet
+/// Reconstructs a bullet_collision script from its description, e.g bullet_collision(1)
+pub(crate) fn load_bullet_collision_script(description: &ScriptDescription) -> Option<Rc<dyn CollisionScript>> {
+    Some(Rc::new(bullet_collision_script(description.param(0)? as u16)))
 }
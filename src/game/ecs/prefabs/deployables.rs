@@ -0,0 +1,122 @@
+use crate::game::ecs::prefabs::{spawn_prefab, PrefabArgs};
+use crate::game::ecs::{
+    deal_damage, Arming, ColorComponent, DamageType, Described, Entity, ItemUseScript, Owner, Position, ScriptDescription,
+    ZoneEvent, ZoneScript, NO_TEAM,
+};
+use crate::game::graphics::ITEM_SIZE;
+use crate::game::ServerContext;
+use crate::misc::constants::DEFAULT_COLOR;
+use crate::net::NULL_HANDLE;
+use ggez::graphics::Color;
+use std::rc::Rc;
+
+/// How far behind the center of a tank its mines are laid, in px
+const MINE_DISTANCE: f32 = 45.0;
+/// How far in front of the center of a tank its barriers go up, in px
+const BARRIER_DISTANCE: f32 = 60.0;
+pub const BARRIER_LENGTH: f32 = 80.0;
+pub const BARRIER_THICKNESS: f32 = 10.0;
+
+/// Blows up under the first tank that isn't the owner's or on their team, once it's armed
+pub fn mine_script(damage: u16) -> impl ZoneScript {
+    Described::new("mine", &[damage as f32], move |event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext| {
+        // The zone checks every tick, so whoever's been sitting on it gets it as soon as it's armed
+        if event == ZoneEvent::Exit || !entities[zone].get_component::<Arming>().is_none_or(Arming::is_armed) {
+            return;
+        }
+        let owner = entities[zone].get_component::<Owner>().cloned().unwrap_or(Owner::new(NULL_HANDLE, NO_TEAM));
+        if owner.is_friendly(&entities[other]) {
+            return;
+        }
+        deal_damage(&mut entities[other], owner.get_handle(), damage, DamageType::Explosion, ctx);
+        entities[zone].delete();
+    })
+}
+
+pub(crate) fn load_mine_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(mine_script(description.param(0)? as u16)))
+}
+
+/// Where something's being deployed from, on whose behalf and in what color
+struct Deployer {
+    owner: Owner,
+    x: f32,
+    y: f32,
+    // In radians
+    angle: f32,
+    color: Color,
+}
+
+impl Deployer {
+    fn of(user: &Entity) -> Option<Self> {
+        let position = user.get_component::<Position>()?;
+        let color = user.get_component::<ColorComponent>().map(|color| color.get_color()).unwrap_or(DEFAULT_COLOR);
+        Some(Self {
+            owner: Owner::of(user),
+            x: position.get_x(),
+            y: position.get_y(),
+            angle: position.get_angle().to_radians(),
+            color,
+        })
+    }
+
+    /// The prefab arguments every deployable takes
+    fn args(&self) -> PrefabArgs {
+        PrefabArgs::new()
+            .with("owner", self.owner.get_handle())
+            .with("team", self.owner.get_team())
+            .with_color(self.color)
+    }
+}
+
+/// Lays a mine behind the user, arming after some seconds, e.g lay_mine(15,1.5)
+pub(crate) fn lay_mine_script(damage: u16, delay: f32) -> impl ItemUseScript {
+    Described::new("lay_mine", &[damage as f32, delay], move |user: &mut Entity, ctx: &mut ServerContext| {
+        let deployer = match Deployer::of(user) {
+            Some(deployer) => deployer,
+            None => return,
+        };
+        // Mines are anchored at their top left corner like items, center them on the spot
+        let x = deployer.x - deployer.angle.cos() * MINE_DISTANCE - 0.5 * ITEM_SIZE;
+        let y = deployer.y - deployer.angle.sin() * MINE_DISTANCE - 0.5 * ITEM_SIZE;
+        let args = deployer.args().with("x", x).with("y", y).with("damage", damage).with("delay", delay);
+        spawn_prefab("mine", &args, ctx);
+    })
+}
+
+pub(crate) fn load_lay_mine_script(description: &ScriptDescription) -> Option<Rc<dyn ItemUseScript>> {
+    Some(Rc::new(lay_mine_script(description.param(0)? as u16, description.param(1)?)))
+}
+
+/// Puts up a barrier in front of the user with some health, standing for some seconds,
+/// e.g place_barrier(20,10). It's turned across whichever axis the user is facing along the most
+pub(crate) fn place_barrier_script(health: u16, seconds: f32) -> impl ItemUseScript {
+    Described::new("place_barrier", &[health as f32, seconds], move |user: &mut Entity, ctx: &mut ServerContext| {
+        let deployer = match Deployer::of(user) {
+            Some(deployer) => deployer,
+            None => return,
+        };
+        let (cos, sin) = (deployer.angle.cos(), deployer.angle.sin());
+        let (w, h) = if cos.abs() >= sin.abs() {
+            (BARRIER_THICKNESS, BARRIER_LENGTH)
+        } else {
+            (BARRIER_LENGTH, BARRIER_THICKNESS)
+        };
+        // Walls are anchored at their top left corner too
+        let x = deployer.x + cos * BARRIER_DISTANCE - 0.5 * w;
+        let y = deployer.y + sin * BARRIER_DISTANCE - 0.5 * h;
+        let args = deployer
+            .args()
+            .with("x", x)
+            .with("y", y)
+            .with("w", w)
+            .with("h", h)
+            .with("health", health)
+            .with("seconds", seconds);
+        spawn_prefab("barrier", &args, ctx);
+    })
+}
+
+pub(crate) fn load_place_barrier_script(description: &ScriptDescription) -> Option<Rc<dyn ItemUseScript>> {
+    Some(Rc::new(place_barrier_script(description.param(0)? as u16, description.param(1)?)))
+}
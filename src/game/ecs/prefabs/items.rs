@@ -1,37 +1,106 @@
-use crate::game::ecs::{Entity, PickUpComponent, CollisionComponent, InventoryComponent, Position, Health, ColorComponent};
-use crate::net::Handle;
-use crate::game::graphics::MeshType;
+use crate::game::ecs::{apply_effect, Contact, Effect, EffectKind, Entity, Hitbox, Item, PickUpComponent, CollisionComponent, InventoryComponent, Position, Health, ColorComponent, GameEvent, ItemUseScript, CollisionScript, Described, ScriptDescription, Armor};
 use crate::game::ServerContext;
+use crate::net::{Handle, NULL_HANDLE};
+use crate::game::graphics::{item_color, MeshType, ITEM_SIZE};
+use std::rc::Rc;
 
-/// Inserts an item (entities[me]) into the inventory of other, if any
-fn pickup_script(me: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {
+/// Inserts an item (entities[me]) into the inventory of other, if there's any room in it
+fn pickup_script(me: usize, other: usize, _contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext) {
     let item = if let Some(item) = entities[me].get_component::<PickUpComponent>() {
         item.get_item()
     } else {
         unreachable!()
     };
-    if let Some(inventory) = entities[other].get_component_mut::<InventoryComponent>() {
-        if !inventory.has_item() {
-            inventory.put_item(item);
+    let picker = entities[other].get_handle();
+    // Full inventories keep sitting on items, which mustn't relay them every tick
+    if let Some(state) = entities[other].get_replicated_state::<InventoryComponent>() {
+        let mesh_type = item.0;
+        if state.untracked().put_item(item) {
+            state.invalidate();
             entities[me].delete();
+            ctx.publish(GameEvent::PickedUp(picker, mesh_type));
         }
     }
 }
 
-fn heal_item_script(user: &mut Entity) {
-    if let Some(health) = user.get_component_mut::<Health>() {
-        // Heal by half of the
-        health.set_health((50 - health.get_health()) / 2 + health.get_health());
-    }
+/// Heals a fraction of the user's lost health
+fn heal_item_script(fraction: f32) -> impl ItemUseScript {
+    Described::new("heal_item", &[fraction], move |user: &mut Entity, _ctx: &mut ServerContext| {
+        if let Some(health) = user.get_component_mut::<Health>() {
+            let lost_health = health.get_max_health() - health.get_health();
+            let healed = (lost_health as f32 * fraction) as u16;
+            health.set_health(health.get_health() + healed);
+        }
+    })
+}
+
+/// Puts the user under an effect of some strength for some seconds, e.g speed_boost(1.5,5)
+fn effect_item_script(kind: EffectKind, strength: f32, duration: f32) -> impl ItemUseScript {
+    Described::new(kind.name(), &[strength, duration], move |user: &mut Entity, _ctx: &mut ServerContext| {
+        apply_effect(user, Effect::new(kind, strength, duration));
+    })
+}
+
+/// Puts fresh armor of some points on the user, replacing whatever was left of its old armor
+fn armor_item_script(points: u16, absorption: f32) -> impl ItemUseScript {
+    Described::new("armor", &[points as f32, absorption], move |user: &mut Entity, _ctx: &mut ServerContext| {
+        user.put_component(Armor::new(points, points, absorption));
+    })
+}
+
+pub(crate) fn load_pickup_script(_: &ScriptDescription) -> Option<Rc<dyn CollisionScript>> {
+    Some(Rc::new(Described::new("pickup", &[], pickup_script)))
+}
+
+/// Reconstructs a heal_item script from its description, e.g heal_item(0.5)
+pub(crate) fn load_heal_item_script(description: &ScriptDescription) -> Option<Rc<dyn ItemUseScript>> {
+    Some(Rc::new(heal_item_script(description.param(0)?)))
 }
 
-/// Creates a regular ol' healing item. Heals 50% of lost health
-pub fn heal_item(handle: Handle, x: f32, y: f32) -> Entity {
-    let mut heal = Entity::new(handle);
-    heal.put_component(MeshType::Heal);
-    heal.put_component(Position::new(x, y, 0.0));
-    heal.put_component(ColorComponent::new(1.0, 0.0, 0.0, 1.0));
-    heal.put_component(CollisionComponent::new_item(handle, pickup_script));
-    heal.put_component(PickUpComponent::new((MeshType::Heal, heal_item_script)));
-    heal
-}
\ No newline at end of file
+/// Reconstructs an armor script from its description, e.g armor(25,0.5)
+pub(crate) fn load_armor_item_script(description: &ScriptDescription) -> Option<Rc<dyn ItemUseScript>> {
+    Some(Rc::new(armor_item_script(description.param(0)? as u16, description.param(1)?)))
+}
+
+/// Reconstructs an effect item script from its description, the name says which effect it is
+pub(crate) fn load_effect_item_script(description: &ScriptDescription) -> Option<Rc<dyn ItemUseScript>> {
+    let kind = EffectKind::from_name(description.get_name())?;
+    Some(Rc::new(effect_item_script(kind, description.param(0)?, description.param(1)?)))
+}
+
+/// How far behind a tank the items it drops land in px, far enough that it doesn't pick them
+/// right back up unless it backs up over them
+const DROP_DISTANCE: f32 = 60.0;
+
+/// Drops an item back into the world behind whoever dropped it, for anyone to pick up
+pub fn drop_item(dropper: &Entity, item: Item, ctx: &mut ServerContext) {
+    let (x, y, angle) = match dropper.get_component::<Position>() {
+        Some(position) => (position.get_x(), position.get_y(), position.get_angle().to_radians()),
+        None => return,
+    };
+    // Items are anchored at their top left corner, center them on the spot behind the dropper
+    let x = x - angle.cos() * DROP_DISTANCE - 0.5 * ITEM_SIZE;
+    let y = y - angle.sin() * DROP_DISTANCE - 0.5 * ITEM_SIZE;
+    let mesh_type = item.0;
+    ctx.spawn(dropped_item(NULL_HANDLE, x, y, item));
+    ctx.publish(GameEvent::Dropped(dropper.get_handle(), mesh_type));
+}
+
+/// An item lying around after being dropped, picked up again like any other
+pub fn dropped_item(handle: Handle, x: f32, y: f32, item: Item) -> Entity {
+    let mesh_type = item.0;
+    let hitbox = match mesh_type {
+        MeshType::Heal => Hitbox::heal_item(),
+        MeshType::SpeedBoost | MeshType::RapidFire | MeshType::Shield => Hitbox::power_up(),
+        MeshType::Mine => Hitbox::mine(),
+        _ => Hitbox::weapon_item(),
+    };
+    let mut dropped = Entity::new(handle);
+    dropped.put_component(mesh_type);
+    dropped.put_replicated(Position::new(x, y, 0.0));
+    dropped.put_replicated(ColorComponent::from_color(item_color(mesh_type)));
+    dropped.put_replicated(hitbox);
+    dropped.put_component(CollisionComponent::new_item(handle, Described::new("pickup", &[], pickup_script)));
+    dropped.put_component(PickUpComponent::from_item(item));
+    dropped
+}
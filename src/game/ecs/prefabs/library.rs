@@ -0,0 +1,292 @@
+use crate::game::ecs::{prefabs, ComponentRegistry, Entity, ScriptLibrary, Stage, System};
+use crate::game::ServerContext;
+use crate::misc::constants::PREFAB_DIRECTORY;
+use crate::net::{Handle, NULL_HANDLE};
+use ggez::graphics::Color;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Arguments passed to a prefab when it's instantiated, filling in its $parameters
+#[derive(Default, Clone, Debug)]
+pub struct PrefabArgs {
+    values: HashMap<String, String>,
+}
+
+impl PrefabArgs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Sets the r, g, b and a parameters
+    pub fn with_color(self, color: Color) -> Self {
+        self.with("r", color.r)
+            .with("g", color.g)
+            .with("b", color.b)
+            .with("a", color.a)
+    }
+
+    /// Reads arguments written like x=250 y=100
+    pub fn parse<'a>(words: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut args = Self::new();
+        for word in words {
+            let mut split = word.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(name), Some(value)) if !name.is_empty() => args = args.with(name, value),
+                _ => return Err(format!("Expected name=value, got {}", word)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// A named list of component lines, as written by the ComponentRegistry, which may contain
+/// $parameters. Every prefab has a $handle parameter, other parameters need a default value
+struct Prefab {
+    defaults: HashMap<String, String>,
+    lines: Vec<String>,
+}
+
+/// All prefabs the server knows about, read from the .prefab files in a directory.
+/// A prefab file looks something like this:
+/// prefab heal_item
+/// param x 0
+/// param y 0
+/// Mesh Heal
+/// Position $x $y 0
+/// end
+pub struct PrefabLibrary {
+    prefabs: HashMap<String, Prefab>,
+    registry: ComponentRegistry,
+    scripts: ScriptLibrary,
+}
+
+impl Default for PrefabLibrary {
+    fn default() -> Self {
+        Self {
+            prefabs: HashMap::new(),
+            registry: ComponentRegistry::with_game_components(),
+            scripts: prefabs::script_library(),
+        }
+    }
+}
+
+impl PrefabLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads every .prefab file in a directory
+    pub fn load_directory(directory: &str) -> Result<Self, String> {
+        let mut library = Self::new();
+        for path in prefab_files(directory)? {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+            library
+                .add_prefabs(&text)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(library)
+    }
+
+    /// Adds every prefab in the text of a prefab file, replacing any prefabs of the same names
+    pub fn add_prefabs(&mut self, text: &str) -> Result<(), String> {
+        let mut current: Option<(String, Prefab)> = None;
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| format!("Line {}: {}", line_index + 1, message);
+            let mut words = line.split_whitespace();
+            match (current.as_mut(), words.next()) {
+                (None, Some("prefab")) => {
+                    let name = words.next().ok_or_else(|| error("Prefab without a name"))?;
+                    let prefab = Prefab {
+                        defaults: HashMap::new(),
+                        lines: Vec::new(),
+                    };
+                    current = Some((name.to_string(), prefab));
+                }
+                (None, _) => return Err(error("Expected a prefab")),
+                (Some(_), Some("end")) => {
+                    let (name, prefab) = current.take().unwrap();
+                    self.prefabs.insert(name, prefab);
+                }
+                (Some((_, prefab)), Some("param")) => match (words.next(), words.next()) {
+                    (Some(name), Some(default)) => {
+                        prefab.defaults.insert(name.to_string(), default.to_string());
+                    }
+                    _ => return Err(error("Expected param <name> <default>")),
+                },
+                (Some((_, prefab)), _) => prefab.lines.push(line.to_string()),
+            }
+        }
+        match current {
+            Some((name, _)) => Err(format!("Prefab {} is missing an end", name)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prefabs.keys().map(String::as_str)
+    }
+
+    /// Creates an entity from a prefab, with any parameters not among the args left at their defaults
+    pub fn instantiate(&self, name: &str, handle: Handle, args: &PrefabArgs) -> Result<Entity, String> {
+        let prefab = self
+            .prefabs
+            .get(name)
+            .ok_or_else(|| format!("Unknown prefab {}", name))?;
+        let mut values = prefab.defaults.clone();
+        for (param, value) in &args.values {
+            if !values.contains_key(param) {
+                return Err(format!("Prefab {} has no parameter {}", name, param));
+            }
+            values.insert(param.clone(), value.clone());
+        }
+        values.insert("handle".to_string(), handle.to_string());
+        // Substitute the longest names first, so $xy doesn't get mistaken for $x
+        let mut values: Vec<(String, String)> = values.into_iter().collect();
+        values.sort_by_key(|(param, _)| std::cmp::Reverse(param.len()));
+        let mut entity = Entity::new(handle);
+        for line in &prefab.lines {
+            let mut line = line.clone();
+            for (param, value) in &values {
+                line = line.replace(&format!("${}", param), value);
+            }
+            if line.contains('$') {
+                return Err(format!("Prefab {} uses an undeclared parameter: {}", name, line));
+            }
+            self.registry
+                .load_component(&mut entity, &line, &self.scripts)
+                .map_err(|e| format!("Prefab {}: {}", name, e))?;
+        }
+        Ok(entity)
+    }
+}
+
+/// Spawns a prefab from the server's prefab library.
+/// Returns the handle it's been given, None if it couldn't be made
+pub fn spawn_prefab(name: &str, args: &PrefabArgs, ctx: &mut ServerContext) -> Option<Handle> {
+    let entity = match ctx.resource::<PrefabLibrary>() {
+        Some(library) => library.instantiate(name, NULL_HANDLE, args),
+        None => Err(format!("Couldn't spawn {}: No prefab library", name)),
+    };
+    match entity {
+        Ok(entity) => Some(ctx.spawn(entity)),
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    }
+}
+
+fn prefab_files(directory: &str) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(directory).map_err(|e| format!("Couldn't read {}: {}", directory, e))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map(|extension| extension == "prefab").unwrap_or(false))
+        .collect();
+    // Sort them so that prefabs defined twice always end up the same
+    paths.sort();
+    Ok(paths)
+}
+
+/// The time any prefab file in a directory was last changed, or None if it can't be told
+fn last_modified(directory: &str) -> Option<SystemTime> {
+    prefab_files(directory)
+        .ok()?
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok()?.modified().ok())
+        .max()
+}
+
+/// One entity to spawn on a map, by prefab name
+pub struct MapSpawn {
+    pub prefab: String,
+    pub args: PrefabArgs,
+}
+
+/// Reads a map file, consisting of lines like: spawn wall x=250 y=200 w=10 h=100
+pub fn parse_map(text: &str) -> Result<Vec<MapSpawn>, String> {
+    let mut spawns = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| format!("Line {}: {}", line_index + 1, message);
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("spawn"), Some(prefab)) => spawns.push(MapSpawn {
+                prefab: prefab.to_string(),
+                args: PrefabArgs::parse(words).map_err(error)?,
+            }),
+            _ => return Err(error(format!("Expected spawn <prefab> <args>, got {}", line))),
+        }
+    }
+    Ok(spawns)
+}
+
+/// Reloads the PrefabLibrary resource whenever a prefab file changes, so prefabs can be tuned
+/// while the game is running. Only registered in debug builds
+pub struct PrefabReloadSystem {
+    last_modified: Option<SystemTime>,
+    // Seconds until the files are checked again, no need to hammer the file system every tick
+    until_check: f32,
+}
+
+impl PrefabReloadSystem {
+    pub fn new() -> Self {
+        Self {
+            last_modified: last_modified(PREFAB_DIRECTORY),
+            until_check: 0.0,
+        }
+    }
+}
+
+impl Default for PrefabReloadSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl System for PrefabReloadSystem {
+    fn update(&mut self, _entities: &mut [Entity], ctx: &mut ServerContext) {
+        const CHECK_INTERVAL: f32 = 1.0;
+        self.until_check -= ctx.delta_time();
+        if self.until_check > 0.0 {
+            return;
+        }
+        self.until_check = CHECK_INTERVAL;
+        let modified = last_modified(PREFAB_DIRECTORY);
+        if modified == self.last_modified {
+            return;
+        }
+        self.last_modified = modified;
+        match PrefabLibrary::load_directory(PREFAB_DIRECTORY) {
+            Ok(library) => {
+                ctx.insert_resource(library);
+                println!("Reloaded prefabs from {}", PREFAB_DIRECTORY);
+            }
+            // Keep the old prefabs around until the files are fixed
+            Err(e) => eprintln!("Couldn't reload prefabs: {}", e),
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Input
+    }
+}
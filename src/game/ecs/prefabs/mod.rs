@@ -1,12 +1,53 @@
+//! The scripts which make prefabs behave the way they do, and the library spawning prefabs from
+//! the data files in assets/prefabs. Those files are the one place prefabs are defined, be it
+//! player tanks, bullets, healing items, weapons, mines or walls.
+
 mod bullet;
 pub(crate) mod player;
 mod wall;
 mod items;
-
-/// A collection of prefab functions which generate entities with common component combinations
-/// Examples include player tanks, bullets, healing items and walls
+mod library;
+mod zones;
+mod weapons;
+mod deployables;
 
 pub use bullet::*;
 pub use player::*;
 pub use wall::*;
-pub use items::*;
\ No newline at end of file
+pub use items::*;
+pub use library::*;
+pub use zones::*;
+pub use weapons::*;
+pub use deployables::*;
+
+use crate::game::ecs::{CollisionScript, ControlScript, DeathScript, ItemUseScript, ScriptLibrary, ZoneScript};
+
+/// All the scripts used by the prefabs, by the names they're saved under
+pub fn script_library() -> ScriptLibrary {
+    let mut library = ScriptLibrary::new();
+    library
+        .register::<dyn CollisionScript>("player_collision", load_player_collision_script)
+        .register::<dyn CollisionScript>("bullet_collision", load_bullet_collision_script)
+        .register::<dyn CollisionScript>("wall_collision", load_wall_collision_script)
+        .register::<dyn CollisionScript>("pickup", load_pickup_script)
+        .register::<dyn CollisionScript>("rocket_collision", load_rocket_collision_script)
+        .register::<dyn CollisionScript>("weapon_pickup", load_weapon_pickup_script)
+        .register::<dyn ControlScript>("player_control", load_player_control_script)
+        .register::<dyn DeathScript>("player_death", load_player_death_script)
+        .register::<dyn DeathScript>("crumble", load_crumble_script)
+        .register::<dyn ItemUseScript>("heal_item", load_heal_item_script)
+        .register::<dyn ItemUseScript>("speed_boost", load_effect_item_script)
+        .register::<dyn ItemUseScript>("rapid_fire", load_effect_item_script)
+        .register::<dyn ItemUseScript>("shield", load_effect_item_script)
+        .register::<dyn ItemUseScript>("armor", load_armor_item_script)
+        .register::<dyn ItemUseScript>("lay_mine", load_lay_mine_script)
+        .register::<dyn ItemUseScript>("place_barrier", load_place_barrier_script)
+        .register::<dyn ZoneScript>("heal_pad", load_heal_pad_script)
+        .register::<dyn ZoneScript>("hazard", load_hazard_script)
+        .register::<dyn ZoneScript>("mud", load_mud_script)
+        .register::<dyn ZoneScript>("boost_pad", load_boost_pad_script)
+        .register::<dyn ZoneScript>("teleporter", load_teleporter_script)
+        .register::<dyn ZoneScript>("capture_point", load_capture_point_script)
+        .register::<dyn ZoneScript>("mine", load_mine_script);
+    library
+}
@@ -1,13 +1,10 @@
 use crate::game::ecs::position::Position;
 use crate::game::ecs::velocity::Velocity;
-use crate::game::ecs::{prefabs, ColorComponent, ControlComponent, Entity, Health, DeathComponent, CollisionClass, InventoryComponent};
-use crate::game::graphics::MeshType;
+use crate::game::ecs::{effect_multiplier, operate_inventory, StatusEffects, operate_weapon, prefabs, InventoryAction, Physics, ControlComponent, ControlScript, Entity, CollisionClass, GameEvent, Contact, Described, CollisionScript, DeathScript, ScriptDescription};
 use crate::game::ServerContext;
-use crate::misc::constants::DEFAULT_COLOR;
-use crate::net::{Handle, NULL_HANDLE};
 use ggez::event::KeyCode;
-use ggez::graphics::Color;
 use std::collections::HashSet;
+use std::rc::Rc;
 use crate::game::ecs::collision::CollisionComponent;
 
 pub fn player_death_script(me: &mut Entity, ctx: &mut ServerContext) {
@@ -23,158 +20,151 @@ pub fn player_death_script(me: &mut Entity, ctx: &mut ServerContext) {
     ctx.trigger_game_over();
 }
 
-pub fn player_collision_script(me: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {
+pub fn player_collision_script(me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext) {
     // This is a collision script, so if it the entities don't have CollisionComponents,
     // something mighty weird must be happening
     let collision_class = entities[other].get_component::<CollisionComponent>()
         .expect("Fatal error in player_collision_script").get_collision_class();
     // What have we collided with?
     match collision_class {
-        // A bullet?! Guess I'll die (in 49 more shots)
-        CollisionClass::Bullet(_) => if let Some(health) = entities[me].get_component_mut::<Health>() {
-            // This check is important since there might multiple bullets damaging a player in a single frame
-            // and since health is an unsigned integer we don't want to underflow it and crash the game!
-            if health.get_health() > 0 {
-                let new_health = health.get_health() - 1;
-                health.set_health(new_health);
-                if new_health == 0 {
-                    // o o f - death. One could argue this should be handled by the HealthSystem.
-                    // Buuut it works and you might wanna customize future health events
-                    // and I don't feel like making any more script aliases
-                    entities[me].delete();
-                }
-            }
-        }
+        // Bullets deal their own damage, so there's nothing to do for them here
         CollisionClass::Tank(..) => {
             if let Some(velocity) = entities[me].get_component_mut::<Velocity>() {
                 // Slow down while driving over another tank.
                 // if we slow down to a halt we might accidentally get stuck in a respawning tank,
                 // so just half off
-                velocity.set_velocity(velocity.get_velocity() * 0.5);
+                let (v_x, v_y) = velocity.get_velocity();
+                velocity.set_velocity(v_x * 0.5, v_y * 0.5);
             }
         }
         CollisionClass::Wall(..) => {
-            // Stop things from going through walls.
-            // This will make walls feel kinda sticky since you will stop fully and not be able
-            // to glide against them, but it works. Could be fixed by checking whether the player
-            // is aimed straight at the wall or not, then doing some vector math / trig
-            // Todo: add better stopping (i.e more selective in regards to vertical/horizontal velocity)
+            // Stop things from going through walls, but only the part of the velocity going into
+            // the wall, so we glide along it rather than sticking to it
+            let (n_x, n_y) = contact.normal;
             if let Some(velocity) = entities[me].get_component_mut::<Velocity>() {
-                velocity.set_velocity(0.0);
+                let (v_x, v_y) = velocity.get_velocity();
+                let into_wall = v_x * n_x + v_y * n_y;
+                if into_wall > 0.0 {
+                    velocity.set_velocity(v_x - into_wall * n_x, v_y - into_wall * n_y);
+                }
+            }
+            // If we're already stuck in the wall, get out of there
+            if contact.depth > 0.0 {
+                if let Some(position) = entities[me].get_component_mut::<Position>() {
+                    position.set_x(position.get_x() - contact.depth * n_x);
+                    position.set_y(position.get_y() - contact.depth * n_y);
+                }
             }
         }
         _ => ()
     }
 }
 
-/// This enables a client to control a player tank
-pub fn player_control_script(
+/// The player collision script, described so it can be saved
+pub(crate) fn player_collision() -> impl CollisionScript {
+    Described::new("player_collision", &[], player_collision_script)
+}
+
+/// This enables a client to control a player tank, driving with the given parameters and
+/// shooting with whatever Weapon the tank has
+pub fn player_control_script(acceleration: f32, turn_rate: f32) -> impl ControlScript {
+    Described::new("player_control", &[acceleration, turn_rate], move |player: &mut Entity, ctx: &mut ServerContext, keys: HashSet<KeyCode>, delta_time: f32| {
+        control_tank(player, ctx, keys, delta_time, acceleration, turn_rate)
+    })
+}
+
+/// Reconstructs a player_control script from its description, e.g player_control(1000,180)
+pub(crate) fn load_player_control_script(description: &ScriptDescription) -> Option<Rc<dyn ControlScript>> {
+    Some(Rc::new(player_control_script(description.param(0)?, description.param(1)?)))
+}
+
+pub(crate) fn load_player_collision_script(_: &ScriptDescription) -> Option<Rc<dyn CollisionScript>> {
+    Some(Rc::new(player_collision()))
+}
+
+pub(crate) fn load_player_death_script(_: &ScriptDescription) -> Option<Rc<dyn DeathScript>> {
+    Some(Rc::new(Described::new("player_death", &[], player_death_script)))
+}
+
+fn control_tank(
     player: &mut Entity,
     ctx: &mut ServerContext,
     // The keys currently held down on the client's keyboard
     keys: HashSet<KeyCode>,
     delta_time: f32,
+    acceleration: f32,
+    turn_rate: f32,
 ) {
-    const VELOCITY_THRESHOLD: f32 = 0.01;
-    let (mut speed_change, mut angle_change): (f32, f32) = (0.0, 0.0);
-    // Accelerate forwards (1000 px/s, or one 3600 kilopixels per hour, wew!)
+    // How much of a tank's sideways sliding its tracks stop each second, so it doesn't drift
+    // around like it's on ice, while still getting pushed a bit by bullets and other tanks
+    const TRACK_GRIP: f32 = 10.0;
+    // Speed boosts and slows make the tank accelerate and turn faster or slower than usual
+    let speed = effect_multiplier(player, StatusEffects::speed_multiplier);
+    let (acceleration, turn_rate) = (acceleration * speed, turn_rate * speed);
+    let (mut thrust, mut turning): (f32, f32) = (0.0, 0.0);
+    // Accelerate forwards
     if keys.contains(&KeyCode::Up) {
-        speed_change += 1000.0 * delta_time
+        thrust += acceleration
     }
     // Accelerate backwards
     if keys.contains(&KeyCode::Down) {
-        speed_change -= 1000.0 * delta_time
+        thrust -= acceleration
     }
-    // Turn at an angular velocity of 180 degrees per second
+    // Turn at a constant angular velocity
     if keys.contains(&KeyCode::Right) {
-        angle_change += 180.0 * delta_time
+        turning += turn_rate
     }
     if keys.contains(&KeyCode::Left) {
-        angle_change -= 180.0 * delta_time
+        turning -= turn_rate
     }
-    // The percentage of speed to decrease per second.
-    // This results in a terminal velocity, so we don't have to worry about accumulating too much
-    // from the acceleration. Real clever
-    const FRICTION_CONSTANT: f32 = 0.90;
-    // We're multiplying here, so the delta_time will be accounted for using delta_time
-    // The algebra checks out ...I think.
-    let friction = (1.0 - FRICTION_CONSTANT).powf(delta_time);
-    let velocity = if let Some(velocity) = player.get_component_mut::<Velocity>() {
-        velocity.set_velocity(
-            // speed_change and friction
-            (velocity.get_velocity() + speed_change) * friction,
-        );
-        if velocity.get_velocity().abs() <= VELOCITY_THRESHOLD {
-            velocity.set_velocity(0.0);
-        }
-        velocity.get_velocity()
-    } else {
-        0.0
-    };
-    let (x, y, angle) = if let Some(position) = player.get_component_mut::<Position>() {
-        position.set_angle(position.get_angle() + angle_change);
-        (position.get_x(), position.get_y(), position.get_angle())
-    } else {
-        // If the player doesn't have a position, that's serious cause for concern.
-        // Fortunately, it won't happen. All players are spawned with positions ready.
-        unreachable!();
-    };
-    // We need to check the color of the player to color their bullets the same color
-    // If we don't, it'll be ugly and people will notice how bullets spawn on top of tanks.
-    let color = if let Some(color) = player.get_component::<ColorComponent>() {
-        color.get_color()
-    } else {
-        DEFAULT_COLOR
+    let facing = match player.get_component::<Position>() {
+        Some(position) => position.get_angle().to_radians(),
+        None => 0.0,
     };
-    // The bool component in the entity says whether the tank recently fired. We don't want any bullet spam
-    if let Some(firing) = player.get_component_mut::<bool>() {
-        if keys.contains(&KeyCode::Space) {
-            if !*firing {
-                *firing = true;
-                ctx.spawn(prefabs::bullet(NULL_HANDLE, player.get_handle(), x, y, angle, 1000.0, color));
-            }
-        } else {
-            *firing = false;
+    let (facing_x, facing_y) = (facing.cos(), facing.sin());
+    let (v_x, v_y) = match player.get_component_mut::<Velocity>() {
+        Some(velocity) => {
+            // The tracks don't spin up, they just turn the tank
+            velocity.set_angular_velocity(turning);
+            velocity.get_velocity()
         }
+        None => (0.0, 0.0),
+    };
+    if let Some(physics) = player.get_component_mut::<Physics>() {
+        let mass = physics.get_mass();
+        physics.apply_force(facing_x * thrust * mass, facing_y * thrust * mass);
+        // Whatever part of the velocity isn't along the way we're facing is sliding sideways
+        let forward = v_x * facing_x + v_y * facing_y;
+        let (slide_x, slide_y) = (v_x - forward * facing_x, v_y - forward * facing_y);
+        let grip = (TRACK_GRIP * delta_time).min(1.0) * mass;
+        physics.apply_impulse(-slide_x * grip, -slide_y * grip);
     }
-    // If the player presses the key I, that means they want to use whatever's in their inventory
-    if keys.contains(&KeyCode::I) {
-        if let Some(inventory) = player.get_component_mut::<InventoryComponent>() {
-            if inventory.has_item() {
-                let item = inventory.remove_item().unwrap();
-                // Trigger the item's use script
-                (item.1)(player);
-            }
+    // Space pulls the trigger and R reloads, the weapon decides whether that makes it fire
+    if let Some(stats) = operate_weapon(player, keys.contains(&KeyCode::Space), keys.contains(&KeyCode::R)) {
+        prefabs::fire(player, &stats, ctx);
+    }
+    // Number keys select an inventory slot, I uses a single item of that slot and Q drops one
+    let slot = SLOT_KEYS.iter().position(|key| keys.contains(key));
+    match operate_inventory(player, slot, keys.contains(&KeyCode::I), keys.contains(&KeyCode::Q)) {
+        Some(InventoryAction::Use(item)) => {
+            // Trigger the item's use script
+            item.1.on_use(player, ctx);
+            ctx.publish(GameEvent::ItemUsed(player.get_handle(), item.0));
         }
+        Some(InventoryAction::Drop(item)) => prefabs::drop_item(player, item, ctx),
+        None => {}
     }
 }
 
-// It's just more concise this way
-fn player_control_component(input_device_index: usize) -> ControlComponent {
-    ControlComponent::new(input_device_index, player_control_script)
-}
-
-/// A player is complicated, so it's got lots of components, but don't fret.
-pub fn player(
-    handle: Handle,
-    // This number indicates which client controls the player
-    input_device_index: usize,
-    x: f32,
-    y: f32,
-    angle: f32,
-    color: Color,
-) -> Entity {
-    let mut player = Entity::new(handle);
-    player.put_component(Position::new(x, y, angle));
-    player.put_component(Velocity::new(0.0, 0.0));
-    player.put_component(player_control_component(input_device_index));
-    player.put_component(MeshType::Tank);
-    player.put_component(Health::new(50, 50));
-    player.put_component(ColorComponent::from_color(color));
-    player.put_component(CollisionComponent::new_tank(handle, player_collision_script));
-    player.put_component(DeathComponent::new(player_death_script));
-    player.put_component(InventoryComponent::empty());
-    // firing status
-    player.put_component(false);
-    player
-}
+/// The keys selecting each inventory slot, in order
+const SLOT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
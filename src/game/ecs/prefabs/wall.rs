@@ -1,18 +1,28 @@
-use crate::game::ecs::{Entity, Position, CollisionComponent, Scale, ColorComponent};
-use crate::net::Handle;
+use crate::game::ecs::prefabs::{spawn_prefab, PrefabArgs};
+use crate::game::ecs::{Contact, Entity, Position, Scale, ColorComponent, CollisionScript, Described, ScriptDescription, DeathScript};
 use crate::game::ServerContext;
-use crate::game::graphics::MeshType;
-use ggez::graphics::Color;
+use crate::misc::constants::DEFAULT_COLOR;
+use std::rc::Rc;
 
 // Walls don't stop tanks or bullets, bullets and tanks stop themselves when hitting walls, as not to be rude.
-fn wall_collision_script(me: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {}
+fn wall_collision_script(me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext) {}
 
-pub fn wall(handle: Handle, x: f32, y: f32, w: f32, h: f32, color: Color) -> Entity {
-    let mut wall = Entity::new(handle);
-    wall.put_component(Position::new(x, y, 0.0));
-    wall.put_component(Scale::new(w, h));
-    wall.put_component(ColorComponent::from_color(color));
-    wall.put_component(CollisionComponent::new_wall(handle, wall_collision_script, w, h));
-    wall.put_component(MeshType::Wall);
-    wall
-}
\ No newline at end of file
+pub(crate) fn load_wall_collision_script(_: &ScriptDescription) -> Option<Rc<dyn CollisionScript>> {
+    Some(Rc::new(Described::new("wall_collision", &[], wall_collision_script)))
+}
+
+/// Leaves some rubble behind where the wall stood, in the same color
+fn crumble_script(me: &mut Entity, ctx: &mut ServerContext) {
+    let (x, y) = match me.get_component::<Position>() {
+        Some(position) => (position.get_x(), position.get_y()),
+        None => return,
+    };
+    let (w, h) = me.get_component::<Scale>().map(|scale| (scale.get_width(), scale.get_height())).unwrap_or((1.0, 1.0));
+    let color = me.get_component::<ColorComponent>().map(|color| color.get_color()).unwrap_or(DEFAULT_COLOR);
+    let args = PrefabArgs::new().with("x", x).with("y", y).with("w", w).with("h", h).with_color(color);
+    spawn_prefab("debris", &args, ctx);
+}
+
+pub(crate) fn load_crumble_script(_: &ScriptDescription) -> Option<Rc<dyn DeathScript>> {
+    Some(Rc::new(Described::new("crumble", &[], crumble_script)))
+}
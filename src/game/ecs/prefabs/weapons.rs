@@ -0,0 +1,119 @@
+use crate::game::ecs::{
+    area_damage, hitscan, ricochet, CollisionClass, CollisionComponent, CollisionScript, ColorComponent, Contact, Described,
+    Entity, GameEvent, Position, ScriptDescription, Weapon, WeaponStats, NO_TEAM, TANK_LAYER, WALL_LAYER,
+};
+use crate::game::ecs::prefabs::{spawn_prefab, PrefabArgs, PrefabLibrary};
+use crate::game::graphics::MeshType;
+use crate::game::ServerContext;
+use crate::misc::constants::DEFAULT_COLOR;
+use crate::net::{Handle, NULL_HANDLE};
+use std::rc::Rc;
+
+/// Fires a shot of a weapon from an entity, in the direction it's facing and in its color,
+/// spawning the weapon's projectile prefab for each projectile of the shot.
+/// Hitscan weapons hit whatever's in line right away instead, leaving a tracer behind
+pub fn fire(shooter: &Entity, stats: &WeaponStats, ctx: &mut ServerContext) {
+    let (x, y, aim) = match shooter.get_component::<Position>() {
+        Some(position) => (position.get_x(), position.get_y(), position.get_angle()),
+        None => return,
+    };
+    // We need to check the color of the shooter to color their projectiles the same color
+    // If we don't, it'll be ugly and people will notice how bullets spawn on top of tanks.
+    let color = match shooter.get_component::<ColorComponent>() {
+        Some(color) => color.get_color(),
+        None => DEFAULT_COLOR,
+    };
+    let team = match shooter.get_component::<CollisionComponent>() {
+        Some(collision) => collision.get_filter().team,
+        None => NO_TEAM,
+    };
+    let handle = shooter.get_handle();
+    let angles = stats.shot_angles(aim, ctx.rng());
+    for angle in angles {
+        if stats.is_hitscan() {
+            let hit = hitscan(ctx, handle, (x, y), angle, stats.speed, stats.damage, TANK_LAYER | WALL_LAYER);
+            // The tracer ends where the shot did, so players can tell what got hit
+            let length = hit.map(|hit| hit.distance).unwrap_or(stats.speed);
+            let args = PrefabArgs::new()
+                .with("x", x)
+                .with("y", y)
+                .with("angle", angle)
+                .with("length", length)
+                .with_color(color);
+            spawn_prefab("tracer", &args, ctx);
+            continue;
+        }
+        let args = PrefabArgs::new()
+            .with("shooter", handle)
+            .with("x", x)
+            .with("y", y)
+            .with("angle", angle)
+            .with("vx", angle.to_radians().cos() * stats.speed)
+            .with("vy", angle.to_radians().sin() * stats.speed)
+            .with("damage", stats.damage)
+            .with_color(color);
+        let projectile = match ctx.resource::<PrefabLibrary>() {
+            Some(library) => library.instantiate(&stats.projectile, NULL_HANDLE, &args),
+            None => Err(format!("Couldn't fire {}: No prefab library", stats.projectile)),
+        };
+        match projectile {
+            Ok(mut projectile) => {
+                // Projectiles fight for the same team as whoever shot them
+                if let Some(collision) = projectile.get_component_mut::<CollisionComponent>() {
+                    collision.set_filter(collision.get_filter().with_team(team, true));
+                }
+                ctx.spawn(projectile);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+fn shooter_of(entity: &Entity) -> Handle {
+    match entity.get_component::<CollisionComponent>().map(|collision| collision.get_collision_class()) {
+        Some(CollisionClass::Bullet(shooter)) => shooter,
+        _ => NULL_HANDLE,
+    }
+}
+
+/// Rockets blow up on whatever they hit, damaging every tank and destructible wall within the radius
+/// of the blast. That includes whoever fired it, so better keep some distance.
+/// Rockets that can ricochet only blow up on walls once they're out of bounces
+fn rocket_collision_script(damage: u16, radius: f32) -> impl CollisionScript {
+    Described::new("rocket_collision", &[damage as f32, radius], move |me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext| {
+        if ricochet(me, other, contact, entities) {
+            return;
+        }
+        let center = match entities[me].get_component::<Position>() {
+            Some(position) => (position.get_x(), position.get_y()),
+            None => return,
+        };
+        area_damage(ctx, shooter_of(&entities[me]), center, radius, damage, TANK_LAYER | WALL_LAYER);
+        entities[me].delete();
+    })
+}
+
+/// Hands its weapon over to whichever tank drives over it, replacing the one it had
+fn weapon_pickup_script(me: usize, other: usize, _contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext) {
+    if !entities[other].has_component::<Weapon>() {
+        return;
+    }
+    let stats = match entities[me].get_component::<Weapon>() {
+        Some(weapon) => weapon.get_stats().clone(),
+        None => return,
+    };
+    if let Some(weapon) = entities[other].get_component_mut::<Weapon>() {
+        *weapon = Weapon::new(stats);
+    }
+    entities[me].delete();
+    ctx.publish(GameEvent::PickedUp(entities[other].get_handle(), MeshType::Weapon));
+}
+
+/// Reconstructs a rocket_collision script from its description, e.g rocket_collision(5,80)
+pub(crate) fn load_rocket_collision_script(description: &ScriptDescription) -> Option<Rc<dyn CollisionScript>> {
+    Some(Rc::new(rocket_collision_script(description.param(0)? as u16, description.param(1)?)))
+}
+
+pub(crate) fn load_weapon_pickup_script(_: &ScriptDescription) -> Option<Rc<dyn CollisionScript>> {
+    Some(Rc::new(Described::new("weapon_pickup", &[], weapon_pickup_script)))
+}
@@ -0,0 +1,147 @@
+use crate::game::ecs::{apply_effect, deal_damage, Effect, EffectKind, CapturePoint, ColorComponent, DamageType, Described, Entity, GameEvent, Health, Physics, Position, Progress, ScriptDescription, TriggerZone, Velocity, ZoneEvent, ZoneScript};
+use crate::game::ServerContext;
+use crate::net::NULL_HANDLE;
+use std::rc::Rc;
+
+/// Heals whoever stays on the pad by an amount every interval of the zone
+pub fn heal_pad_script(amount: u16) -> impl ZoneScript {
+    Described::new("heal_pad", &[amount as f32], move |event: ZoneEvent, _zone: usize, other: usize, entities: &mut [Entity], _ctx: &mut ServerContext| {
+        if event != ZoneEvent::Stay {
+            return;
+        }
+        if let Some(health) = entities[other].get_component_mut::<Health>() {
+            // Only touch the health if it changes, otherwise it's relayed needlessly
+            if health.get_health() < health.get_max_health() {
+                let healed = health.get_health().saturating_add(amount).min(health.get_max_health());
+                health.set_health(healed);
+            }
+        }
+    })
+}
+
+/// Damages whoever stays in the area every interval of the zone, and whoever enters it right away
+pub fn hazard_script(damage: u16) -> impl ZoneScript {
+    Described::new("hazard", &[damage as f32], move |event: ZoneEvent, _zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext| {
+        if event != ZoneEvent::Exit {
+            // Dying to a hazard isn't anyone's kill, it's just bad driving
+            deal_damage(&mut entities[other], NULL_HANDLE, damage, DamageType::Environment, ctx);
+        }
+    })
+}
+
+/// Slows down whoever's in the area to some fraction of their speed, until a second after they leave
+pub fn mud_script(strength: f32) -> impl ZoneScript {
+    const LINGER: f32 = 1.0;
+    Described::new("mud", &[strength], move |event: ZoneEvent, _zone: usize, other: usize, entities: &mut [Entity], _ctx: &mut ServerContext| {
+        if event != ZoneEvent::Exit {
+            apply_effect(&mut entities[other], Effect::new(EffectKind::Slow, strength, LINGER));
+        }
+    })
+}
+
+/// Gives whoever enters the pad a push in the direction the pad is facing, changing their
+/// velocity by some px/s no matter how heavy they are
+pub fn boost_pad_script(strength: f32) -> impl ZoneScript {
+    Described::new("boost_pad", &[strength], move |event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], _ctx: &mut ServerContext| {
+        if event != ZoneEvent::Enter {
+            return;
+        }
+        let angle = match entities[zone].get_component::<Position>() {
+            Some(position) => position.get_angle().to_radians(),
+            None => return,
+        };
+        let (boost_x, boost_y) = (angle.cos() * strength, angle.sin() * strength);
+        if let Some(physics) = entities[other].get_component_mut::<Physics>() {
+            let mass = physics.get_mass();
+            physics.apply_impulse(boost_x * mass, boost_y * mass);
+        } else if let Some(velocity) = entities[other].get_component_mut::<Velocity>() {
+            // Things without physics, like bullets, just get faster
+            let (v_x, v_y) = velocity.get_velocity();
+            velocity.set_velocity(v_x + boost_x, v_y + boost_y);
+        }
+    })
+}
+
+/// Moves whoever enters it somewhere else. Better not put that somewhere else in another teleporter
+pub fn teleporter_script(x: f32, y: f32) -> impl ZoneScript {
+    Described::new("teleporter", &[x, y], move |event: ZoneEvent, _zone: usize, other: usize, entities: &mut [Entity], _ctx: &mut ServerContext| {
+        if event != ZoneEvent::Enter {
+            return;
+        }
+        if let Some(position) = entities[other].get_component_mut::<Position>() {
+            position.set_x(x);
+            position.set_y(y);
+        }
+    })
+}
+
+/// Whoever stays on the point long enough on their own captures it, taking on their color.
+/// Anyone else on the point at the same time pushes the progress back down
+pub fn capture_point_script(seconds: f32) -> impl ZoneScript {
+    Described::new("capture_point", &[seconds], move |event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext| {
+        if event != ZoneEvent::Stay {
+            return;
+        }
+        let step = match entities[zone].get_component::<TriggerZone>() {
+            Some(trigger) => trigger.get_interval() / seconds.max(f32::EPSILON),
+            None => return,
+        };
+        let (owner, claimant) = match entities[zone].get_component::<CapturePoint>() {
+            Some(point) => (point.get_owner(), point.get_claimant()),
+            None => return,
+        };
+        let progress = entities[zone].get_component::<Progress>().map(|progress| progress.0).unwrap_or(0.0);
+        let candidate = entities[other].get_handle();
+        // The owner has nothing left to take, unless someone's been taking it from them
+        if candidate == owner && claimant == owner && progress >= 1.0 {
+            return;
+        }
+        let (claimant, progress) = if candidate == claimant || progress <= 0.0 {
+            (candidate, (progress + step).min(1.0))
+        } else {
+            (claimant, (progress - step).max(0.0))
+        };
+        let captured = progress >= 1.0 && claimant != owner;
+        if let Some(point) = entities[zone].get_component_mut::<CapturePoint>() {
+            point.set_claimant(claimant);
+            if captured {
+                point.set_owner(claimant);
+            }
+        }
+        if let Some(old_progress) = entities[zone].get_component_mut::<Progress>() {
+            old_progress.0 = progress;
+        }
+        if captured {
+            // Tint the point in the colors of its new owner, keeping it see-through
+            let color = entities[other].get_component::<ColorComponent>().map(|color| color.get_color());
+            if let (Some(color), Some(tint)) = (color, entities[zone].get_component_mut::<ColorComponent>()) {
+                *tint = ColorComponent::new(color.r, color.g, color.b, tint.get_alpha());
+            }
+            ctx.publish(GameEvent::Captured(entities[zone].get_handle(), claimant));
+        }
+    })
+}
+
+pub(crate) fn load_heal_pad_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(heal_pad_script(description.param(0)? as u16)))
+}
+
+pub(crate) fn load_hazard_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(hazard_script(description.param(0)? as u16)))
+}
+
+pub(crate) fn load_mud_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(mud_script(description.param(0)?)))
+}
+
+pub(crate) fn load_boost_pad_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(boost_pad_script(description.param(0)?)))
+}
+
+pub(crate) fn load_teleporter_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(teleporter_script(description.param(0)?, description.param(1)?)))
+}
+
+pub(crate) fn load_capture_point_script(description: &ScriptDescription) -> Option<Rc<dyn ZoneScript>> {
+    Some(Rc::new(capture_point_script(description.param(0)?)))
+}
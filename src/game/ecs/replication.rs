@@ -0,0 +1,76 @@
+use crate::game::ecs::{ColorComponent, Entity, Health, Hitbox, InventoryComponent, ItemSpawner, Position, Progress, Scale, Stage, StatusEffects, System, Weapon};
+use crate::game::ServerContext;
+use crate::net::{Event, Handle};
+
+/// A component which the clients need to know about. Implementors map themselves to the network
+/// event describing their current state, and are stored via Entity::put_replicated so that any
+/// mutable access gets picked up by the ReplicationSystem
+pub trait Replicated: Sized + 'static {
+    fn to_event(&self, handle: Handle) -> Event;
+}
+
+/// Checks a single kind of replicated component for changes, returning the event to relay if any
+type Replicator = fn(&mut Entity) -> Option<Event>;
+
+fn replicate<C: Replicated>(entity: &mut Entity) -> Option<Event> {
+    let handle = entity.get_handle();
+    let state = entity.get_replicated_state::<C>()?;
+    if state.invalidated_since() {
+        Some(state.to_event(handle))
+    } else {
+        None
+    }
+}
+
+/// A system which relays any change to a replicated component to the clients.
+/// Replaces the bunch of watcher systems we used to have, one per component
+pub struct ReplicationSystem {
+    replicators: Vec<Replicator>,
+}
+
+impl ReplicationSystem {
+    pub fn new() -> Self {
+        Self {
+            replicators: Vec::new(),
+        }
+    }
+
+    /// Start relaying changes of another kind of component
+    pub fn with<C: Replicated>(mut self) -> Self {
+        self.replicators.push(replicate::<C>);
+        self
+    }
+}
+
+impl Default for ReplicationSystem {
+    /// All the replicated components of the game
+    fn default() -> Self {
+        Self::new()
+            .with::<Position>()
+            .with::<Scale>()
+            .with::<ColorComponent>()
+            .with::<Health>()
+            .with::<InventoryComponent>()
+            .with::<Hitbox>()
+            .with::<Progress>()
+            .with::<Weapon>()
+            .with::<StatusEffects>()
+            .with::<ItemSpawner>()
+    }
+}
+
+impl System for ReplicationSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        for entity in entities {
+            for replicator in &self.replicators {
+                if let Some(event) = (replicator)(entity) {
+                    ctx.push_event(event);
+                }
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Replication
+    }
+}
@@ -0,0 +1,99 @@
+use crate::game::ecs::{system_id, CollisionClass, CollisionComponent, CollisionSystem, Contact, Entity, Position, Stage, System, SystemId, Velocity};
+use crate::game::ServerContext;
+
+/// Makes a projectile bounce off walls instead of stopping at them, a number of times at most.
+/// Each bounce takes some of its speed away
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ricochet {
+    bounces_left: u32,
+    // The fraction of its speed lost on each bounce, from 0 to 1
+    speed_loss: f32,
+}
+
+impl Ricochet {
+    pub fn new(bounces: u32, speed_loss: f32) -> Self {
+        Self {
+            bounces_left: bounces,
+            speed_loss,
+        }
+    }
+
+    pub fn get_bounces_left(&self) -> u32 {
+        self.bounces_left
+    }
+
+    pub fn get_speed_loss(&self) -> f32 {
+        self.speed_loss
+    }
+}
+
+/// Bounces entities[me] off entities[other] if other is a wall and me has any bounces left,
+/// reflecting its velocity about the contact normal. Returns whether it bounced
+pub fn ricochet(me: usize, other: usize, contact: Contact, entities: &mut [Entity]) -> bool {
+    let hit_wall = entities[other]
+        .get_component::<CollisionComponent>()
+        .is_some_and(|collision| collision.get_collision_class().is_wall());
+    let can_bounce = entities[me].get_component::<Ricochet>().is_some_and(|ricochet| ricochet.bounces_left > 0);
+    if !hit_wall || !can_bounce {
+        return false;
+    }
+    // The normal points into the wall, flip whatever part of the velocity goes that way
+    let (n_x, n_y) = contact.normal;
+    let (v_x, v_y) = match entities[me].get_component::<Velocity>() {
+        Some(velocity) => velocity.get_velocity(),
+        None => return false,
+    };
+    let into_wall = v_x * n_x + v_y * n_y;
+    // Already on its way out after bouncing off another part of the wall, that doesn't count twice
+    let bounced = if into_wall > 0.0 {
+        let ricochet = entities[me].get_component_mut::<Ricochet>().unwrap();
+        ricochet.bounces_left -= 1;
+        let kept = 1.0 - ricochet.speed_loss;
+        let bounced = ((v_x - 2.0 * into_wall * n_x) * kept, (v_y - 2.0 * into_wall * n_y) * kept);
+        if let Some(velocity) = entities[me].get_component_mut::<Velocity>() {
+            velocity.set_velocity(bounced.0, bounced.1);
+        }
+        bounced
+    } else {
+        (v_x, v_y)
+    };
+    if let Some(position) = entities[me].get_component_mut::<Position>() {
+        position.set_x(position.get_x() - contact.depth * n_x);
+        position.set_y(position.get_y() - contact.depth * n_y);
+        position.set_angle(bounced.1.atan2(bounced.0).to_degrees());
+    }
+    true
+}
+
+/// A rule of the map, put on an entity of its own: every projectile which can't ricochet yet can, this much.
+/// Projectiles which can already keep their own Ricochet
+pub struct RicochetRule(pub Ricochet);
+
+/// Lets projectiles ricochet wherever there's a RicochetRule, before they get to hit anything
+pub struct RicochetRuleSystem;
+
+impl System for RicochetRuleSystem {
+    fn update(&mut self, entities: &mut [Entity], _ctx: &mut ServerContext) {
+        let rule = match entities.iter().find_map(|entity| entity.get_component::<RicochetRule>()) {
+            Some(rule) => rule.0,
+            None => return,
+        };
+        for entity in entities {
+            let projectile = matches!(
+                entity.get_component::<CollisionComponent>().map(CollisionComponent::get_collision_class),
+                Some(CollisionClass::Bullet(..))
+            );
+            if projectile && !entity.has_component::<Ricochet>() {
+                entity.put_component(rule);
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Simulation
+    }
+
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<CollisionSystem>()]
+    }
+}
@@ -1,12 +1,10 @@
-use crate::game::ecs::{Entity, System};
-use crate::game::ServerContext;
-use crate::net::Event;
+use crate::game::ecs::Replicated;
+use crate::net::{Event, Handle};
 
 /// A struct containing info on how to graphically scale meshes of an object. Mainly used for walls.
 pub struct Scale {
     width: f32,
     height: f32,
-    has_changed: bool,
 }
 
 impl Scale {
@@ -14,7 +12,6 @@ impl Scale {
         Self {
             width,
             height,
-            has_changed: true,
         }
     }
 
@@ -23,9 +20,6 @@ impl Scale {
     }
 
     pub fn set_width(&mut self, new_width: f32) {
-        if new_width != self.width {
-            self.has_changed = true;
-        }
         self.width = new_width;
     }
 
@@ -34,9 +28,6 @@ impl Scale {
     }
 
     pub fn set_height(&mut self, new_height: f32) {
-        if new_height != self.height {
-            self.has_changed = true;
-        }
         self.height = new_height;
     }
 }
@@ -46,24 +37,13 @@ impl Default for Scale {
         Self {
             width: 1.0,
             height: 1.0,
-            has_changed: true
         }
     }
 }
 
-/// A system which relays any changes in scale to the clients
-pub struct ScaleSystem;
-
-impl System for ScaleSystem {
-    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
-        for entity in entities {
-            let handle = entity.get_handle();
-            if let Some(scale) = entity.get_component_mut::<Scale>() {
-                if scale.has_changed {
-                    ctx.push_event(Event::Dimension(handle, scale.width, scale.height));
-                    scale.has_changed = false;
-                }
-            }
-        }
+/// Any change in scale is relayed to the clients as a dimension
+impl Replicated for Scale {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Dimension(handle, self.width, self.height)
     }
 }
@@ -0,0 +1,147 @@
+use crate::game::ecs::{Entity, System};
+use crate::game::ServerContext;
+use std::any::{type_name, TypeId};
+use std::collections::HashMap;
+
+/// Identifies a kind of system, used to declare ordering constraints between systems
+pub type SystemId = TypeId;
+
+pub fn system_id<S: System + 'static>() -> SystemId {
+    TypeId::of::<S>()
+}
+
+/// The stages of a tick, in the order they're run. Every system runs in exactly one stage
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Stage {
+    /// Reading input devices and deciding what entities want to do
+    Input,
+    /// Moving things about and colliding them
+    Simulation,
+    /// Anything reacting to where things ended up, such as timers running out
+    PostPhysics,
+    /// Relaying state to the clients
+    Replication,
+    /// Handling deleted entities, should come last
+    Cleanup,
+}
+
+struct Registration {
+    id: SystemId,
+    name: &'static str,
+    system: Box<dyn System>,
+}
+
+/// Collects systems so that they can be ordered into a Schedule. Systems declare their own
+/// stage and ordering constraints, so registering is all there is to adding a new system
+#[derive(Default)]
+pub struct Scheduler {
+    registrations: Vec<Registration>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            registrations: Vec::new(),
+        }
+    }
+
+    pub fn register<S: System + 'static>(&mut self, system: S) -> &mut Self {
+        self.registrations.push(Registration {
+            id: system_id::<S>(),
+            name: type_name::<S>(),
+            system: Box::new(system),
+        });
+        self
+    }
+
+    /// Orders all registered systems by stage and before/after constraints.
+    /// Constraints on systems that aren't registered are ignored, anything contradictory is an error
+    pub fn build(self) -> Result<Schedule, String> {
+        let registrations = self.registrations;
+        let mut index_by_id = HashMap::new();
+        for (i, registration) in registrations.iter().enumerate() {
+            if index_by_id.insert(registration.id, i).is_some() {
+                return Err(format!("{} was registered twice", registration.name));
+            }
+        }
+        // Edges go from systems which must run first to those which must run after them
+        let mut successors = vec![Vec::new(); registrations.len()];
+        for (i, registration) in registrations.iter().enumerate() {
+            let system = &registration.system;
+            let befores = system.before().into_iter().map(|id| (i, id, true));
+            let afters = system.after().into_iter().map(|id| (i, id, false));
+            for (me, other_id, me_first) in befores.chain(afters) {
+                let other = match index_by_id.get(&other_id) {
+                    Some(other) => *other,
+                    None => continue,
+                };
+                let (first, second) = if me_first { (me, other) } else { (other, me) };
+                let first_stage = registrations[first].system.stage();
+                let second_stage = registrations[second].system.stage();
+                if first_stage > second_stage {
+                    return Err(format!(
+                        "{} ({:?}) can't run before {} ({:?})",
+                        registrations[first].name, first_stage,
+                        registrations[second].name, second_stage
+                    ));
+                }
+                successors[first].push(second);
+            }
+        }
+        // Topological sort (Kahn's algorithm), always picking the earliest stage and then the
+        // earliest registered system among those that are ready, so the order is deterministic
+        let mut predecessor_counts = vec![0; registrations.len()];
+        for successor in successors.iter().flatten() {
+            predecessor_counts[*successor] += 1;
+        }
+        let mut order = Vec::with_capacity(registrations.len());
+        let mut scheduled = vec![false; registrations.len()];
+        while order.len() < registrations.len() {
+            let next = (0..registrations.len())
+                .filter(|i| !scheduled[*i] && predecessor_counts[*i] == 0)
+                .min_by_key(|i| (registrations[*i].system.stage(), *i));
+            let next = match next {
+                Some(next) => next,
+                None => {
+                    let stuck: Vec<&str> = (0..registrations.len())
+                        .filter(|i| !scheduled[*i])
+                        .map(|i| registrations[i].name)
+                        .collect();
+                    return Err(format!("Cyclic ordering between {:?}", stuck));
+                }
+            };
+            scheduled[next] = true;
+            for successor in &successors[next] {
+                predecessor_counts[*successor] -= 1;
+            }
+            order.push(next);
+        }
+        // Move the systems out in their scheduled order
+        let mut registrations: Vec<Option<Registration>> =
+            registrations.into_iter().map(Some).collect();
+        let systems = order
+            .into_iter()
+            .map(|i| registrations[i].take().unwrap())
+            .map(|registration| (registration.name, registration.system))
+            .collect();
+        Ok(Schedule { systems })
+    }
+}
+
+/// A validated order of systems, ready to be run each tick
+pub struct Schedule {
+    systems: Vec<(&'static str, Box<dyn System>)>,
+}
+
+impl Schedule {
+    pub fn run(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        for (_, system) in &mut self.systems {
+            system.update(entities, ctx);
+        }
+    }
+
+    /// The type names of all systems in the order they're run, useful for debugging
+    pub fn system_names(&self) -> Vec<&'static str> {
+        self.systems.iter().map(|(name, _)| *name).collect()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::game::ecs::{Entity, GameEvent, GameEventReader, Stage, System};
+use crate::game::ServerContext;
+use crate::net::{Handle, NULL_HANDLE};
+use std::collections::HashMap;
+
+/// A resource keeping track of how many kills and deaths each entity has racked up this match
+#[derive(Default)]
+pub struct ScoreBoard {
+    kills: HashMap<Handle, u32>,
+    deaths: HashMap<Handle, u32>,
+}
+
+impl ScoreBoard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_kills(&self, handle: Handle) -> u32 {
+        self.kills.get(&handle).cloned().unwrap_or(0)
+    }
+
+    pub fn get_deaths(&self, handle: Handle) -> u32 {
+        self.deaths.get(&handle).cloned().unwrap_or(0)
+    }
+}
+
+/// A resource describing the playable area of the current map, in pixels
+#[derive(Copy, Clone)]
+pub struct MapBounds {
+    pub width: f32,
+    pub height: f32,
+}
+
+/// All maps so far are this big
+impl Default for MapBounds {
+    fn default() -> Self {
+        Self {
+            width: 1000.0,
+            height: 500.0,
+        }
+    }
+}
+
+/// A system which tallies up kills from the game events into the ScoreBoard
+#[derive(Default)]
+pub struct ScoreSystem {
+    reader: GameEventReader,
+}
+
+impl System for ScoreSystem {
+    fn update(&mut self, _entities: &mut [Entity], ctx: &mut ServerContext) {
+        let events = ctx.read_game_events(&mut self.reader);
+        if let Some(score_board) = ctx.resource_mut::<ScoreBoard>() {
+            for event in events {
+                if let GameEvent::Killed(victim, killer) = event {
+                    *score_board.deaths.entry(victim).or_insert(0) += 1;
+                    // Dying to a wall or whatever doesn't count as anyone's kill
+                    if killer != NULL_HANDLE && killer != victim {
+                        *score_board.kills.entry(killer).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+}
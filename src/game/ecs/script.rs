@@ -0,0 +1,164 @@
+//! Scripts make entities behave their own way, be it on input, on colliding, on dying, when used
+//! as an item or in a zone. Each kind is a trait of its own, and any closure with a fitting signature
+//! is one, so scripts can carry state such as how much an item heals. Wrapped in Described they can
+//! be saved, and the ScriptLibrary reads them back by name
+
+use crate::game::ecs::{CollisionScript, Contact, ControlScript, DeathScript, Entity, ItemUseScript, ZoneEvent, ZoneScript};
+use crate::game::ServerContext;
+use crate::misc::TypeSet;
+use ggez::event::KeyCode;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::rc::Rc;
+
+/// Describes a script by the name it's registered under in a ScriptLibrary and its parameters,
+/// so that it can be written to and read back from a file.
+/// Looks like name(param1,param2) when written, or just name if there are no parameters
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScriptDescription {
+    name: String,
+    params: Vec<f32>,
+}
+
+impl ScriptDescription {
+    pub fn new(name: &str, params: &[f32]) -> Self {
+        Self {
+            name: name.to_string(),
+            params: params.to_vec(),
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn param(&self, index: usize) -> Option<f32> {
+        self.params.get(index).cloned()
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.find('(') {
+            Some(open) => {
+                let close = text.rfind(')')?;
+                let params: Option<Vec<f32>> = text[open + 1..close]
+                    .split(',')
+                    .filter(|param| !param.is_empty())
+                    .map(|param| param.trim().parse().ok())
+                    .collect();
+                Some(Self {
+                    name: text[..open].to_string(),
+                    params: params?,
+                })
+            }
+            None if !text.is_empty() => Some(Self::new(text, &[])),
+            None => None,
+        }
+    }
+}
+
+impl Display for ScriptDescription {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.name)?;
+        if !self.params.is_empty() {
+            let params: Vec<String> = self.params.iter().map(|param| param.to_string()).collect();
+            write!(f, "({})", params.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// A script along with its description. Scripts need one of these to be saved to a file
+pub struct Described<S> {
+    description: ScriptDescription,
+    script: S,
+}
+
+impl<S> Described<S> {
+    pub fn new(name: &str, params: &[f32], script: S) -> Self {
+        Self {
+            description: ScriptDescription::new(name, params),
+            script,
+        }
+    }
+}
+
+impl<S: CollisionScript> CollisionScript for Described<S> {
+    fn on_collision(&self, me: usize, other: usize, contact: Contact, entities: &mut [Entity], ctx: &mut ServerContext) {
+        self.script.on_collision(me, other, contact, entities, ctx)
+    }
+
+    fn description(&self) -> Option<&ScriptDescription> {
+        Some(&self.description)
+    }
+}
+
+impl<S: ControlScript> ControlScript for Described<S> {
+    fn on_input(&self, entity: &mut Entity, ctx: &mut ServerContext, keys: HashSet<KeyCode>, delta_time: f32) {
+        self.script.on_input(entity, ctx, keys, delta_time)
+    }
+
+    fn description(&self) -> Option<&ScriptDescription> {
+        Some(&self.description)
+    }
+}
+
+impl<S: DeathScript> DeathScript for Described<S> {
+    fn on_death(&self, me: &mut Entity, ctx: &mut ServerContext) {
+        self.script.on_death(me, ctx)
+    }
+
+    fn description(&self) -> Option<&ScriptDescription> {
+        Some(&self.description)
+    }
+}
+
+impl<S: ItemUseScript> ItemUseScript for Described<S> {
+    fn on_use(&self, user: &mut Entity, ctx: &mut ServerContext) {
+        self.script.on_use(user, ctx)
+    }
+
+    fn description(&self) -> Option<&ScriptDescription> {
+        Some(&self.description)
+    }
+}
+
+impl<S: ZoneScript> ZoneScript for Described<S> {
+    fn on_zone_event(&self, event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {
+        self.script.on_zone_event(event, zone, other, entities, ctx)
+    }
+
+    fn description(&self) -> Option<&ScriptDescription> {
+        Some(&self.description)
+    }
+}
+
+/// Creates a script from its description, None if the parameters don't make sense
+pub type ScriptConstructor<T> = fn(&ScriptDescription) -> Option<Rc<T>>;
+
+/// All scripts that can be referred to by name, for each kind of script
+/// (e.g dyn CollisionScript or dyn ItemUseScript)
+#[derive(Default)]
+pub struct ScriptLibrary {
+    // One HashMap of constructors by name per kind of script
+    constructors: TypeSet,
+}
+
+impl ScriptLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: ?Sized + 'static>(&mut self, name: &str, constructor: ScriptConstructor<T>) -> &mut Self {
+        if !self.constructors.contains::<HashMap<String, ScriptConstructor<T>>>() {
+            self.constructors.insert::<HashMap<String, ScriptConstructor<T>>>(HashMap::new());
+        }
+        let constructors = self.constructors.get_mut::<HashMap<String, ScriptConstructor<T>>>().unwrap();
+        constructors.insert(name.to_string(), constructor);
+        self
+    }
+
+    pub fn construct<T: ?Sized + 'static>(&self, description: &ScriptDescription) -> Option<Rc<T>> {
+        let constructors = self.constructors.get::<HashMap<String, ScriptConstructor<T>>>()?;
+        (constructors.get(description.get_name())?)(description)
+    }
+}
@@ -0,0 +1,282 @@
+use crate::game::ecs::{bounding_radius, hitbox_bounds, hitbox_contact, hitbox_raycast, CollisionClass, CollisionComponent, DamageType, Entity, Hitbox, PendingDamage, PlacedShape, Position, Stage, System};
+use crate::game::ServerContext;
+use crate::net::Handle;
+use std::collections::{HashMap, HashSet};
+
+/// Where a ray hit something
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub handle: Handle,
+    pub class: CollisionClass,
+    pub distance: f32,
+    pub point: (f32, f32),
+}
+
+/// A collidable entity as it was when the index was last built
+struct SpatialEntry {
+    handle: Handle,
+    class: CollisionClass,
+    layers: u32,
+    position: (f32, f32),
+    shapes: Vec<PlacedShape>,
+}
+
+/// A resource which knows where all collidable entities are, so systems and scripts can ask what
+/// is in front of them or around them without going through every entity. It's rebuilt by the
+/// SpatialIndexSystem each tick after everything has moved, so it's a tick behind at most.
+/// Queries take a mask of collision layers, only entities on any of them are found
+pub struct SpatialIndex {
+    entries: Vec<SpatialEntry>,
+    // Which entries overlap each square cell of the map, by cell coordinates
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    // The smallest and largest cell coordinates anything is in, nothing to find outside of them
+    bounds: ((i32, i32), (i32, i32)),
+}
+
+/// How wide each cell of the index is in px, a bit larger than a tank is sensible
+const CELL_SIZE: f32 = 100.0;
+
+fn cell_of(x: f32, y: f32) -> (i32, i32) {
+    ((x / CELL_SIZE).floor() as i32, (y / CELL_SIZE).floor() as i32)
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpatialIndex {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            cells: HashMap::new(),
+            bounds: ((0, 0), (-1, -1)),
+        }
+    }
+
+    /// Forgets everything and indexes the entities where they are right now
+    pub fn rebuild(&mut self, entities: &[Entity]) {
+        self.entries.clear();
+        self.bounds = ((i32::MAX, i32::MAX), (i32::MIN, i32::MIN));
+        // Keep the cell vectors around, no need to allocate them anew each tick
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        for entity in entities.iter().filter(|entity| !entity.deleted()) {
+            let (class, layers) = match entity.get_component::<CollisionComponent>() {
+                Some(collision) => (collision.get_collision_class(), collision.get_filter().layers),
+                None => continue,
+            };
+            let position = match entity.get_component::<Position>() {
+                Some(position) => position,
+                None => continue,
+            };
+            let shapes = match entity.get_component::<Hitbox>() {
+                Some(hitbox) => hitbox.place_at(position),
+                None => Hitbox::from_class(class).place_at(position),
+            };
+            let (x, y) = (position.get_x(), position.get_y());
+            let reach = bounding_radius(&shapes, (x, y));
+            let index = self.entries.len();
+            let (min_x, min_y) = cell_of(x - reach, y - reach);
+            let (max_x, max_y) = cell_of(x + reach, y + reach);
+            let ((low_x, low_y), (high_x, high_y)) = self.bounds;
+            self.bounds = ((low_x.min(min_x), low_y.min(min_y)), (high_x.max(max_x), high_y.max(max_y)));
+            for cell_x in min_x..=max_x {
+                for cell_y in min_y..=max_y {
+                    self.cells.entry((cell_x, cell_y)).or_default().push(index);
+                }
+            }
+            self.entries.push(SpatialEntry {
+                handle: entity.get_handle(),
+                class,
+                layers,
+                position: (x, y),
+                shapes,
+            });
+        }
+    }
+
+    /// Indices of entries which might be within a rectangle, given by its top left and bottom right corners
+    fn candidates(&self, top_left: (f32, f32), bottom_right: (f32, f32), mask: u32) -> Vec<usize> {
+        let (min_x, min_y) = cell_of(top_left.0, top_left.1);
+        let (max_x, max_y) = cell_of(bottom_right.0, bottom_right.1);
+        let mut found = HashSet::new();
+        // A huge square is better off just going through everything
+        let cell_count = (max_x - min_x + 1) as i64 * (max_y - min_y + 1) as i64;
+        if cell_count > self.entries.len() as i64 {
+            found.extend(0..self.entries.len());
+        } else {
+            for cell_x in min_x..=max_x {
+                for cell_y in min_y..=max_y {
+                    if let Some(cell) = self.cells.get(&(cell_x, cell_y)) {
+                        found.extend(cell.iter().copied());
+                    }
+                }
+            }
+        }
+        let mut found: Vec<usize> = found
+            .into_iter()
+            .filter(|index| self.entries[*index].layers & mask != 0)
+            .collect();
+        // Keep results the same from run to run, regardless of how the HashSet felt like ordering them
+        found.sort_unstable();
+        found
+    }
+
+    /// The first thing a ray from the origin runs into within some distance, ignoring a handle
+    /// such as whoever's firing it. The angle is in degrees, just like Position's
+    pub fn raycast(&self, origin: (f32, f32), angle: f32, max_distance: f32, mask: u32, ignore: Handle) -> Option<RayHit> {
+        let direction = (angle.to_radians().cos(), angle.to_radians().sin());
+        let mut closest: Option<RayHit> = None;
+        // Walk the cells the ray passes through in order, stopping once something's been hit
+        // closer than the cell we're at
+        let mut cell = cell_of(origin.0, origin.1);
+        let sign = |direction: f32| if direction > 0.0 { 1 } else if direction < 0.0 { -1 } else { 0 };
+        let step = (sign(direction.0), sign(direction.1));
+        // How far along the ray the next cell border is, along x and y
+        let border = |cell: i32, step: i32, origin: f32, direction: f32| {
+            if direction == 0.0 {
+                f32::INFINITY
+            } else {
+                let next = if step > 0 { cell + 1 } else { cell } as f32 * CELL_SIZE;
+                (next - origin) / direction
+            }
+        };
+        let mut next_x = border(cell.0, step.0, origin.0, direction.0);
+        let mut next_y = border(cell.1, step.1, origin.1, direction.1);
+        let delta = (CELL_SIZE / direction.0.abs(), CELL_SIZE / direction.1.abs());
+        let mut checked = HashSet::new();
+        let mut travelled = 0.0;
+        while travelled <= max_distance {
+            for &index in self.cells.get(&cell).into_iter().flatten() {
+                let entry = &self.entries[index];
+                if entry.handle == ignore || entry.layers & mask == 0 || !checked.insert(index) {
+                    continue;
+                }
+                if let Some(distance) = hitbox_raycast(&entry.shapes, origin, direction) {
+                    if distance <= max_distance && closest.is_none_or(|closest| distance < closest.distance) {
+                        closest = Some(RayHit {
+                            handle: entry.handle,
+                            class: entry.class,
+                            distance,
+                            point: (origin.0 + direction.0 * distance, origin.1 + direction.1 * distance),
+                        });
+                    }
+                }
+            }
+            if next_x < next_y {
+                travelled = next_x;
+                next_x += delta.0;
+                cell.0 += step.0;
+            } else {
+                travelled = next_y;
+                next_y += delta.1;
+                cell.1 += step.1;
+            }
+            if closest.is_some_and(|closest| closest.distance <= travelled) {
+                break;
+            }
+            // Past the last of the entities, so there's nothing more to run into
+            let ((min_x, min_y), (max_x, max_y)) = self.bounds;
+            let beyond = |cell: i32, step: i32, min: i32, max: i32| (cell > max && step >= 0) || (cell < min && step <= 0);
+            if beyond(cell.0, step.0, min_x, max_x) || beyond(cell.1, step.1, min_y, max_y) {
+                break;
+            }
+        }
+        closest
+    }
+
+    /// Whether nothing on the masked layers is in the way between two points, e.g walls.
+    /// Whatever's ignored, such as whoever's looking, doesn't block the view
+    pub fn line_of_sight(&self, from: (f32, f32), to: (f32, f32), mask: u32, ignore: Handle) -> bool {
+        let (d_x, d_y) = (to.0 - from.0, to.1 - from.1);
+        let distance = (d_x * d_x + d_y * d_y).sqrt();
+        if distance == 0.0 {
+            return true;
+        }
+        self.raycast(from, d_y.atan2(d_x).to_degrees(), distance, mask, ignore).is_none()
+    }
+
+    /// Everything whose hitbox overlaps some shapes, such as the hitbox of a trigger zone
+    pub fn overlap(&self, shapes: &[PlacedShape], mask: u32) -> Vec<Handle> {
+        if shapes.is_empty() {
+            return Vec::new();
+        }
+        let (top_left, bottom_right) = hitbox_bounds(shapes);
+        self.candidates(top_left, bottom_right, mask)
+            .into_iter()
+            .map(|index| &self.entries[index])
+            .filter(|entry| hitbox_contact(shapes, &entry.shapes).is_some())
+            .map(|entry| entry.handle)
+            .collect()
+    }
+
+    /// Everything whose hitbox overlaps a circle
+    pub fn overlap_circle(&self, center: (f32, f32), radius: f32, mask: u32) -> Vec<Handle> {
+        self.overlap(&[PlacedShape::Circle(center, radius)], mask)
+    }
+
+    /// The entity closest to a point within some distance, by their positions, along with how far away it is
+    pub fn nearest(&self, point: (f32, f32), max_distance: f32, mask: u32, ignore: Handle) -> Option<(Handle, f32)> {
+        let (x, y) = point;
+        self.candidates((x - max_distance, y - max_distance), (x + max_distance, y + max_distance), mask)
+            .into_iter()
+            .map(|index| &self.entries[index])
+            .filter(|entry| entry.handle != ignore)
+            .map(|entry| {
+                let (d_x, d_y) = (entry.position.0 - point.0, entry.position.1 - point.1);
+                (entry.handle, (d_x * d_x + d_y * d_y).sqrt())
+            })
+            .filter(|(_, distance)| *distance <= max_distance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// Where an indexed entity was, if it's indexed at all
+    pub fn position_of(&self, handle: Handle) -> Option<(f32, f32)> {
+        self.entries.iter().find(|entry| entry.handle == handle).map(|entry| entry.position)
+    }
+}
+
+/// Fires an instant shot, damaging the first thing on the masked layers it hits.
+/// The damage is dealt by the DamageSystem later in the tick
+pub fn hitscan(ctx: &mut ServerContext, shooter: Handle, origin: (f32, f32), angle: f32, range: f32, damage: u16, mask: u32) -> Option<RayHit> {
+    let hit = ctx.resource::<SpatialIndex>()?.raycast(origin, angle, range, mask, shooter)?;
+    if let Some(pending) = ctx.resource_mut::<PendingDamage>() {
+        pending.add(hit.handle, shooter, damage, DamageType::Bullet);
+    }
+    Some(hit)
+}
+
+/// Damages everything on the masked layers within a radius, such as when something explodes
+pub fn area_damage(ctx: &mut ServerContext, source: Handle, center: (f32, f32), radius: f32, damage: u16, mask: u32) -> Vec<Handle> {
+    let hit = match ctx.resource::<SpatialIndex>() {
+        Some(index) => index.overlap_circle(center, radius, mask),
+        None => return Vec::new(),
+    };
+    if let Some(pending) = ctx.resource_mut::<PendingDamage>() {
+        for target in &hit {
+            pending.add(*target, source, damage, DamageType::Explosion);
+        }
+    }
+    hit
+}
+
+/// Keeps the SpatialIndex resource up to date with where everything ended up this tick
+pub struct SpatialIndexSystem;
+
+impl System for SpatialIndexSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        if ctx.resource::<SpatialIndex>().is_none() {
+            ctx.insert_resource(SpatialIndex::new());
+        }
+        if let Some(index) = ctx.resource_mut::<SpatialIndex>() {
+            index.rebuild(entities);
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+}
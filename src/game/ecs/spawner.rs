@@ -0,0 +1,187 @@
+use crate::game::ecs::prefabs::{self, spawn_prefab, PrefabArgs};
+use crate::game::ecs::{system_id, Entity, GameRng, Position, Replicated, SpatialIndex, SpatialIndexSystem, Stage, System, SystemId, ITEM_LAYER, TANK_LAYER, WALL_LAYER};
+use crate::game::graphics::ITEM_SIZE;
+use crate::game::ServerContext;
+use crate::net::{Event, Handle, NULL_HANDLE};
+use rand::Rng;
+use std::collections::HashSet;
+
+/// How many random spots are tried for an item before settling for wherever the last one was
+const PLACEMENT_ATTEMPTS: usize = 10;
+
+/// Where a spawner puts its items
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Placement {
+    // Always wherever the spawner is
+    Fixed,
+    // Anywhere inside a rectangle (x, y, width, height) that isn't taken by walls, tanks or other items
+    Area(f32, f32, f32, f32),
+}
+
+/// Keeps an item around, spawning another one some seconds after the last one was picked up.
+/// The item is picked from a table of prefabs, each with a weight for how likely it is.
+/// The spawner's Position is where the next item will show up, so the clients can see it coming
+pub struct ItemSpawner {
+    // The prefab names of the items and their weights
+    table: Vec<(String, u32)>,
+    // Seconds between an item being taken and the next one spawning
+    cooldown: f32,
+    placement: Placement,
+    until_spawn: f32,
+    // The item that's currently out there, NULL_HANDLE if there isn't one
+    spawned: Handle,
+}
+
+impl ItemSpawner {
+    /// A spawner that spawns its first item right away
+    pub fn new(table: Vec<(String, u32)>, cooldown: f32, placement: Placement) -> Self {
+        Self {
+            table,
+            cooldown,
+            placement,
+            until_spawn: 0.0,
+            spawned: NULL_HANDLE,
+        }
+    }
+
+    /// Picks up where a spawner left off, e.g when loading a saved match
+    pub fn with_state(mut self, until_spawn: f32, spawned: Handle) -> Self {
+        self.until_spawn = until_spawn;
+        self.spawned = spawned;
+        self
+    }
+
+    pub fn get_table(&self) -> &[(String, u32)] {
+        &self.table
+    }
+
+    pub fn get_cooldown(&self) -> f32 {
+        self.cooldown
+    }
+
+    pub fn get_placement(&self) -> Placement {
+        self.placement
+    }
+
+    pub fn get_until_spawn(&self) -> f32 {
+        self.until_spawn
+    }
+
+    pub fn get_spawned(&self) -> Handle {
+        self.spawned
+    }
+
+    /// Picks an item from the table by weight, None if there's nothing in it worth picking
+    pub fn pick(&self, rng: &mut impl Rng) -> Option<&str> {
+        let total: u32 = self.table.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0..total);
+        for (prefab, weight) in &self.table {
+            if roll < *weight {
+                return Some(prefab);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
+
+/// Tell the clients how long until the next item spawns, 0 if there's one out there already
+impl Replicated for ItemSpawner {
+    fn to_event(&self, handle: Handle) -> Event {
+        let until_spawn = if self.spawned == NULL_HANDLE { self.until_spawn } else { 0.0 };
+        Event::Countdown(handle, until_spawn, self.cooldown)
+    }
+}
+
+/// Finds a spot in an area that an item fits in without overlapping walls, tanks or other items
+fn free_spot(index: Option<&SpatialIndex>, area: (f32, f32, f32, f32), rng: &mut impl Rng) -> (f32, f32) {
+    let (x, y, width, height) = area;
+    let mut spot = (x, y);
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        // Items are anchored at their top left corner, keep all of it inside the area
+        spot = (
+            x + rng.gen_range(0.0..=(width - ITEM_SIZE).max(0.0)),
+            y + rng.gen_range(0.0..=(height - ITEM_SIZE).max(0.0)),
+        );
+        let center = (spot.0 + 0.5 * ITEM_SIZE, spot.1 + 0.5 * ITEM_SIZE);
+        let taken = index.is_some_and(|index| !index.overlap_circle(center, ITEM_SIZE, WALL_LAYER | TANK_LAYER | ITEM_LAYER).is_empty());
+        if !taken {
+            break;
+        }
+    }
+    spot
+}
+
+/// Respawns items once the ones spawned before are gone.
+/// Runs after the spatial index is rebuilt, so random spots are checked against where things are now
+pub struct ItemSpawnerSystem;
+
+impl System for ItemSpawnerSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let delta_time = ctx.delta_time();
+        let alive: HashSet<Handle> = entities.iter().filter(|entity| !entity.deleted()).map(Entity::get_handle).collect();
+        for entity in entities {
+            let spawner = match entity.get_replicated_state::<ItemSpawner>() {
+                Some(state) => state.untracked(),
+                None => continue,
+            };
+            if spawner.spawned != NULL_HANDLE {
+                if alive.contains(&spawner.spawned) {
+                    continue;
+                }
+                // The item's been taken, decide where the next one goes and start counting down
+                spawner.spawned = NULL_HANDLE;
+                spawner.until_spawn = spawner.cooldown;
+                let placement = spawner.placement;
+                if let Placement::Area(x, y, width, height) = placement {
+                    let mut rng = ctx.take_resource::<GameRng>().unwrap_or_else(GameRng::from_entropy);
+                    let (x, y) = free_spot(ctx.resource::<SpatialIndex>(), (x, y, width, height), &mut rng);
+                    ctx.insert_resource(rng);
+                    if let Some(position) = entity.get_component_mut::<Position>() {
+                        position.set_x(x);
+                        position.set_y(y);
+                    }
+                }
+                if let Some(state) = entity.get_replicated_state::<ItemSpawner>() {
+                    state.invalidate();
+                }
+                continue;
+            }
+            spawner.until_spawn -= delta_time;
+            if spawner.until_spawn > 0.0 {
+                continue;
+            }
+            spawner.until_spawn = 0.0;
+            let name = match spawner.pick(ctx.rng()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            let (x, y) = match entity.get_component::<Position>() {
+                Some(position) => (position.get_x(), position.get_y()),
+                None => continue,
+            };
+            let args = PrefabArgs::new().with("x", x).with("y", y);
+            let spawned = spawn_prefab(&name, &args, ctx);
+            if let Some(state) = entity.get_replicated_state::<ItemSpawner>() {
+                let spawner = state.untracked();
+                match spawned {
+                    Some(handle) => spawner.spawned = handle,
+                    // Don't keep on trying every tick if the prefab's broken
+                    None => spawner.until_spawn = spawner.cooldown,
+                }
+                state.invalidate();
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+
+    fn after(&self) -> Vec<SystemId> {
+        vec![system_id::<SpatialIndexSystem>()]
+    }
+}
@@ -1,4 +1,4 @@
-use crate::game::ecs::{Entity, System};
+use crate::game::ecs::{Entity, Stage, System};
 use crate::game::ServerContext;
 
 pub struct TimeToLive(f32);
@@ -7,6 +7,10 @@ impl TimeToLive {
     pub fn new(time_to_live: f32) -> Self {
         Self(time_to_live)
     }
+
+    pub fn get_time_to_live(&self) -> f32 {
+        self.0
+    }
 }
 
 /// TTL stands for time to live.
@@ -25,4 +29,8 @@ impl System for TtlSystem {
             }
         }
     }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
 }
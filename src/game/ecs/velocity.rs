@@ -1,54 +1,79 @@
-use crate::game::ecs::{Entity, Position, System};
+use crate::game::ecs::{Entity, Physics, Position, System};
 use crate::game::ServerContext;
 
-/// Well, it's just a velocity. I don't think we ever ended up using the angular_velocity though
+/// How fast something moves along x and y in px/s, and how fast it turns in degrees/s.
+/// It doesn't have to move the way it's facing, tanks sliding along walls don't
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Velocity {
-    velocity: f32,
+    x: f32,
+    y: f32,
     angular_velocity: f32,
 }
 
 impl Velocity {
-    pub fn new(velocity: f32, angular_velocity: f32) -> Self {
+    pub fn new(x: f32, y: f32, angular_velocity: f32) -> Self {
         Self {
-            velocity,
+            x,
+            y,
             angular_velocity,
         }
     }
-    pub fn get_velocity(&self) -> f32 {
-        self.velocity
+    /// A velocity of some speed in the direction of an angle, in degrees
+    pub fn forward(speed: f32, angle: f32) -> Self {
+        let angle = angle.to_radians();
+        Self::new(angle.cos() * speed, angle.sin() * speed, 0.0)
+    }
+    pub fn get_velocity(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+    pub fn get_speed(&self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
     }
     pub fn get_angular_velocity(&self) -> f32 {
         self.angular_velocity
     }
-    pub fn set_velocity(&mut self, velocity: f32) {
-        self.velocity = velocity;
+    pub fn set_velocity(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
     }
     pub fn set_angular_velocity(&mut self, angular_velocity: f32) {
         self.angular_velocity = angular_velocity;
     }
 }
 
-/// A physics system which calculates an entities new position from its velocity each frame
+/// A physics system which calculates an entities new position from its velocity each frame.
+/// Entities with Physics get their forces applied first, and are slowed down by drag afterwards
 pub struct VelocitySystem;
 
 impl System for VelocitySystem {
     fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
         let delta_time = ctx.delta_time();
         for entity in entities {
-            if entity.has_component::<Velocity>() {
-                let velocity = if let Some(velocity) = entity.get_component::<Velocity>() {
-                    velocity.velocity
-                } else {
-                    unreachable!()
-                };
+            let mut velocity = match entity.get_component::<Velocity>() {
+                Some(velocity) => *velocity,
+                None => continue,
+            };
+            if let Some(physics) = entity.get_component_mut::<Physics>() {
+                physics.accelerate(&mut velocity, delta_time);
+            }
+            let (v_x, v_y) = velocity.get_velocity();
+            let angular_velocity = velocity.get_angular_velocity();
+            // Don't touch the position unless we're moving, otherwise it's relayed needlessly
+            if v_x != 0.0 || v_y != 0.0 || angular_velocity != 0.0 {
                 if let Some(position) = entity.get_component_mut::<Position>() {
-                    let angle = position.get_angle();
-                    let v_x = angle.to_radians().cos() * (velocity);
-                    let v_y = angle.to_radians().sin() * (velocity);
                     position.set_x(position.get_x() + v_x * delta_time);
                     position.set_y(position.get_y() + v_y * delta_time);
+                    if angular_velocity != 0.0 {
+                        position.set_angle(position.get_angle() + angular_velocity * delta_time);
+                    }
                 }
             }
+            if let Some(physics) = entity.get_component::<Physics>() {
+                physics.slow_down(&mut velocity, delta_time);
+            }
+            if let Some(old_velocity) = entity.get_component_mut::<Velocity>() {
+                *old_velocity = velocity;
+            }
         }
     }
 }
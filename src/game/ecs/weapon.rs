@@ -0,0 +1,244 @@
+use crate::game::ecs::{effect_multiplier, system_id, ControlSystem, Entity, Replicated, Stage, StatusEffects, System, SystemId};
+use crate::game::ServerContext;
+use crate::net::{Event, Handle};
+use rand::Rng;
+
+/// What hitscan weapons fire instead of a projectile prefab, hitting whatever's in the line of fire right away
+pub const HITSCAN: &str = "hitscan";
+
+/// How a weapon fires. Weapons of a kind all share these, only their ammo and timing differ
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeaponStats {
+    // The name of the prefab that's fired, e.g bullet or rocket, or hitscan
+    pub projectile: String,
+    // How fast the projectiles fly in px/s, for hitscan weapons how far the shots reach in px
+    pub speed: f32,
+    pub damage: u16,
+    // Seconds between shots
+    pub cooldown: f32,
+    // How many degrees the shots are spread over. Several projectiles fan out evenly across it,
+    // a single one goes off somewhere random within it
+    pub spread: f32,
+    // How many projectiles each shot fires at once
+    pub count: u16,
+    // How many shots until it needs reloading, 0 for bottomless
+    pub magazine: u16,
+    // Seconds it takes to reload
+    pub reload_time: f32,
+    // Whether holding the trigger down keeps on firing, rather than having to press it for each shot
+    pub automatic: bool,
+}
+
+impl WeaponStats {
+    /// Fires a single projectile for each press of the trigger, with no cooldown or reloading
+    pub fn new(projectile: &str, speed: f32, damage: u16) -> Self {
+        Self {
+            projectile: projectile.to_string(),
+            speed,
+            damage,
+            cooldown: 0.0,
+            spread: 0.0,
+            count: 1,
+            magazine: 0,
+            reload_time: 0.0,
+            automatic: false,
+        }
+    }
+
+    pub fn with_cooldown(mut self, cooldown: f32) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn with_spread(mut self, spread: f32, count: u16) -> Self {
+        self.spread = spread;
+        self.count = count;
+        self
+    }
+
+    pub fn with_magazine(mut self, magazine: u16, reload_time: f32) -> Self {
+        self.magazine = magazine;
+        self.reload_time = reload_time;
+        self
+    }
+
+    pub fn automatic(mut self) -> Self {
+        self.automatic = true;
+        self
+    }
+
+    pub fn is_hitscan(&self) -> bool {
+        self.projectile == HITSCAN
+    }
+
+    /// The angles each projectile of a shot flies off at, for a weapon aimed at some angle
+    pub fn shot_angles(&self, aim: f32, rng: &mut impl Rng) -> Vec<f32> {
+        match self.count {
+            0 => Vec::new(),
+            1 if self.spread > 0.0 => vec![aim + rng.gen_range(-0.5..0.5) * self.spread],
+            1 => vec![aim],
+            count => {
+                let between = self.spread / (count - 1) as f32;
+                (0..count).map(|i| aim - 0.5 * self.spread + i as f32 * between).collect()
+            }
+        }
+    }
+}
+
+/// A weapon of an entity, keeping track of its ammo and how long until it can fire again.
+/// The clients get told about it whenever it fires or reloads, not while it's cooling down,
+/// they can count down on their own
+pub struct Weapon {
+    stats: WeaponStats,
+    ammo: u16,
+    // Seconds until it can fire again, whether it's cooling down or reloading
+    until_ready: f32,
+    // How long the current wait was to begin with
+    wait_length: f32,
+    reloading: bool,
+    // Whether the trigger was held down the last time it was checked
+    trigger_held: bool,
+}
+
+impl Weapon {
+    /// A weapon with a full magazine, ready to fire
+    pub fn new(stats: WeaponStats) -> Self {
+        let ammo = stats.magazine;
+        Self::with_ammo(stats, ammo)
+    }
+
+    pub fn with_ammo(stats: WeaponStats, ammo: u16) -> Self {
+        Self {
+            stats,
+            ammo,
+            until_ready: 0.0,
+            wait_length: 0.0,
+            reloading: false,
+            trigger_held: false,
+        }
+    }
+
+    pub fn get_stats(&self) -> &WeaponStats {
+        &self.stats
+    }
+
+    pub fn get_ammo(&self) -> u16 {
+        self.ammo
+    }
+
+    pub fn get_until_ready(&self) -> f32 {
+        self.until_ready
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reloading
+    }
+
+    /// Counts down the cooldown or reload, topping up the ammo once a reload is done
+    pub fn wait(&mut self, delta_time: f32) {
+        if self.until_ready > 0.0 {
+            self.until_ready -= delta_time;
+        }
+        if self.until_ready <= 0.0 {
+            self.until_ready = 0.0;
+            if self.reloading {
+                self.reloading = false;
+                self.ammo = self.stats.magazine;
+            }
+        }
+    }
+
+    /// Starts reloading, unless it's already full, bottomless or reloading
+    pub fn reload(&mut self) -> bool {
+        if self.reloading || self.stats.magazine == 0 || self.ammo >= self.stats.magazine {
+            return false;
+        }
+        self.reloading = true;
+        self.until_ready = self.stats.reload_time;
+        self.wait_length = self.stats.reload_time;
+        // Weapons which reload instantly have nothing to wait for
+        if self.until_ready <= 0.0 {
+            self.wait(0.0);
+        }
+        true
+    }
+
+    /// Holds the trigger down or lets go of it. Returns whether a shot goes off, in which case
+    /// it's used up some ammo and started cooling down or reloading
+    pub fn pull_trigger(&mut self, held: bool) -> bool {
+        let pressed = held && !self.trigger_held;
+        self.trigger_held = held;
+        if !(pressed || held && self.stats.automatic) || self.until_ready > 0.0 {
+            return false;
+        }
+        if self.stats.magazine > 0 {
+            if self.ammo == 0 {
+                self.reload();
+                return false;
+            }
+            self.ammo -= 1;
+        }
+        self.until_ready = self.stats.cooldown;
+        self.wait_length = self.stats.cooldown;
+        // Don't make anyone press reload for an empty magazine
+        if self.stats.magazine > 0 && self.ammo == 0 {
+            self.reload();
+        }
+        true
+    }
+}
+
+/// Tell the clients how much ammo the weapon has, and how long until it's ready to fire
+impl Replicated for Weapon {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Weapon(handle, self.ammo, self.stats.magazine, self.until_ready, self.wait_length)
+    }
+}
+
+/// Holds down or lets go of the trigger of an entity's weapon, and reloads it if asked to.
+/// Returns what the weapon fires, if it fires. Only firing and reloading get relayed to the clients
+pub fn operate_weapon(entity: &mut Entity, trigger: bool, reload: bool) -> Option<WeaponStats> {
+    let state = entity.get_replicated_state::<Weapon>()?;
+    let weapon = state.untracked();
+    let (was_reloading, had_ammo) = (weapon.reloading, weapon.ammo);
+    if reload {
+        weapon.reload();
+    }
+    let fired = weapon.pull_trigger(trigger);
+    let stats = if fired { Some(weapon.stats.clone()) } else { None };
+    // An instant reload tops the ammo up without ever reloading
+    if fired || weapon.reloading != was_reloading || weapon.ammo != had_ammo {
+        state.invalidate();
+    }
+    stats
+}
+
+/// Counts down the cooldowns and reloads of all weapons, before anyone tries firing them
+pub struct WeaponSystem;
+
+impl System for WeaponSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let delta_time = ctx.delta_time();
+        for entity in entities {
+            // Rapid fire makes the time go by faster
+            let fire_rate = effect_multiplier(entity, StatusEffects::fire_rate_multiplier);
+            if let Some(state) = entity.get_replicated_state::<Weapon>() {
+                let weapon = state.untracked();
+                let was_reloading = weapon.reloading;
+                weapon.wait(delta_time * fire_rate);
+                // The clients can count down on their own, but they can't tell when the ammo's back
+                if weapon.reloading != was_reloading {
+                    state.invalidate();
+                }
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Input
+    }
+
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<ControlSystem>()]
+    }
+}
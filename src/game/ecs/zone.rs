@@ -0,0 +1,195 @@
+use crate::game::ecs::{system_id, Entity, Hitbox, Position, Replicated, ScriptDescription, SpatialIndex, SpatialIndexSystem, Stage, System, SystemId};
+use crate::game::ServerContext;
+use crate::net::{Event, Handle};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// What just happened between a trigger zone and something else
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ZoneEvent {
+    // Something came into the zone
+    Enter,
+    // Something is still in the zone, sent every interval of the zone
+    Stay,
+    // Something left the zone. Things which leave by getting deleted don't get one of these
+    Exit,
+}
+
+/// A zone script is called with the index of the zone and of whatever set it off, along with the
+/// regular system parameters
+pub trait ZoneScript {
+    fn on_zone_event(&self, event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext);
+
+    /// How to refer to this script by name, if it can be
+    fn description(&self) -> Option<&ScriptDescription> {
+        None
+    }
+}
+
+impl<F: Fn(ZoneEvent, usize, usize, &mut [Entity], &mut ServerContext)> ZoneScript for F {
+    fn on_zone_event(&self, event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {
+        (self)(event, zone, other, entities, ctx)
+    }
+}
+
+/// Makes the area covered by an entity's Hitbox do something when things are in it. Unlike
+/// collisions nothing's stopped from going in, so zones don't need a CollisionComponent at all.
+/// Only things on the collision layers of the mask set it off
+pub struct TriggerZone {
+    mask: u32,
+    // Seconds between each Stay event, or 0 for every tick
+    interval: f32,
+    until_stay: f32,
+    // Whatever was inside the last time the zone was checked
+    occupants: Vec<Handle>,
+    script: Rc<dyn ZoneScript>,
+}
+
+impl TriggerZone {
+    pub fn new(mask: u32, interval: f32, script: impl ZoneScript + 'static) -> Self {
+        Self::from_shared(mask, interval, Rc::new(script))
+    }
+
+    /// Creates a zone from an already shared script, e.g one constructed from a ScriptLibrary
+    pub fn from_shared(mask: u32, interval: f32, script: Rc<dyn ZoneScript>) -> Self {
+        Self {
+            mask,
+            interval,
+            until_stay: interval,
+            occupants: Vec::new(),
+            script,
+        }
+    }
+
+    pub fn get_mask(&self) -> u32 {
+        self.mask
+    }
+
+    pub fn get_interval(&self) -> f32 {
+        self.interval
+    }
+
+    pub fn get_occupants(&self) -> &[Handle] {
+        &self.occupants
+    }
+
+    pub fn get_script(&self) -> Rc<dyn ZoneScript> {
+        self.script.clone()
+    }
+}
+
+/// How far along something is, from 0 to 1, like the capturing of a capture point.
+/// Clients draw it as a bar above the entity
+pub struct Progress(pub f32);
+
+impl Replicated for Progress {
+    fn to_event(&self, handle: Handle) -> Event {
+        Event::Progress(handle, self.0)
+    }
+}
+
+/// Who holds a capture point, and who's busy taking it from them.
+/// How far along they are is kept in the capture point's Progress
+pub struct CapturePoint {
+    owner: Handle,
+    claimant: Handle,
+}
+
+impl CapturePoint {
+    pub fn new(owner: Handle, claimant: Handle) -> Self {
+        Self { owner, claimant }
+    }
+
+    pub fn get_owner(&self) -> Handle {
+        self.owner
+    }
+
+    pub fn set_owner(&mut self, owner: Handle) {
+        self.owner = owner;
+    }
+
+    pub fn get_claimant(&self) -> Handle {
+        self.claimant
+    }
+
+    pub fn set_claimant(&mut self, claimant: Handle) {
+        self.claimant = claimant;
+    }
+}
+
+/// Keeps track of what's in each trigger zone, calling their scripts accordingly.
+/// Zones are checked against the SpatialIndex, so anything without a CollisionComponent won't set them off
+pub struct ZoneSystem;
+
+impl System for ZoneSystem {
+    fn update(&mut self, entities: &mut [Entity], ctx: &mut ServerContext) {
+        let delta_time = ctx.delta_time();
+        let indices: HashMap<Handle, usize> = entities
+            .iter()
+            .enumerate()
+            .filter(|(_, entity)| !entity.deleted())
+            .map(|(index, entity)| (entity.get_handle(), index))
+            .collect();
+        for zone in 0..entities.len() {
+            let handle = entities[zone].get_handle();
+            let (mask, interval) = match entities[zone].get_component::<TriggerZone>() {
+                Some(trigger) => (trigger.mask, trigger.interval),
+                None => continue,
+            };
+            let shapes = match (entities[zone].get_component::<Position>(), entities[zone].get_component::<Hitbox>()) {
+                (Some(position), Some(hitbox)) => hitbox.place_at(position),
+                _ => continue,
+            };
+            let inside: Vec<Handle> = match ctx.resource::<SpatialIndex>() {
+                Some(index) => index
+                    .overlap(&shapes, mask)
+                    .into_iter()
+                    .filter(|other| *other != handle && indices.contains_key(other))
+                    .collect(),
+                None => continue,
+            };
+            let (old_occupants, stay, script) = {
+                let trigger = entities[zone].get_component_mut::<TriggerZone>().unwrap();
+                trigger.until_stay -= delta_time;
+                let stay = trigger.until_stay <= 0.0;
+                if stay {
+                    trigger.until_stay += interval.max(delta_time);
+                }
+                let old_occupants = std::mem::replace(&mut trigger.occupants, inside.clone());
+                (old_occupants, stay, trigger.script.clone())
+            };
+            for other in &inside {
+                let event = if !old_occupants.contains(other) {
+                    ZoneEvent::Enter
+                } else if stay {
+                    ZoneEvent::Stay
+                } else {
+                    continue;
+                };
+                call_script(&script, event, zone, indices[other], entities, ctx);
+            }
+            for other in old_occupants.iter().filter(|other| !inside.contains(other)) {
+                if let Some(other) = indices.get(other) {
+                    call_script(&script, ZoneEvent::Exit, zone, *other, entities, ctx);
+                }
+            }
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostPhysics
+    }
+
+    // Zones are checked against where things ended up this tick
+    fn after(&self) -> Vec<SystemId> {
+        vec![system_id::<SpatialIndexSystem>()]
+    }
+}
+
+fn call_script(script: &Rc<dyn ZoneScript>, event: ZoneEvent, zone: usize, other: usize, entities: &mut [Entity], ctx: &mut ServerContext) {
+    // Scripts earlier on may have deleted the zone or whatever's in it, e.g by teleporting it into a hazard
+    if entities[zone].deleted() || entities[other].deleted() {
+        return;
+    }
+    script.on_zone_event(event, zone, other, entities, ctx);
+}
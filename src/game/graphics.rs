@@ -1,9 +1,21 @@
 use ggez::graphics::{Color, DrawMode, Mesh, MeshBuilder, Rect};
 use ggez::nalgebra::Point2;
 use ggez::{Context, GameResult};
+use crate::game::ecs::{EffectKind, PlacedShape};
 
 const DEFAULT_MESH: MeshGenerator = bullet_mesh;
 
+/* Measurements of the meshes, shared with the hitboxes so that they stay consistent */
+pub const TANK_RADIUS: f32 = 25.0;
+pub const TANK_BARREL_LENGTH: f32 = 30.0;
+pub const TANK_BARREL_WIDTH: f32 = 30.0;
+pub const BULLET_RADIUS: f32 = 5.0;
+pub const ITEM_SIZE: f32 = 30.0;
+pub const ITEM_BOLDNESS: f32 = 10.0;
+pub const ROCKET_LENGTH: f32 = 20.0;
+pub const ROCKET_WIDTH: f32 = 8.0;
+pub const MINE_RADIUS: f32 = 10.0;
+
 pub type MeshGenerator = fn(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh>;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -14,6 +26,25 @@ pub enum MeshType {
     Wall,
     Heal,
     None,
+    // A translucent area, such as a trigger zone. Scaled up just like walls
+    Zone,
+    Rocket,
+    // A crate with a weapon in it
+    Weapon,
+    // Power-ups putting whoever uses them under an effect
+    SpeedBoost,
+    RapidFire,
+    Shield,
+    // The pad an item spawner puts its items on
+    Spawner,
+    // A mine, both lying in wait and as an item
+    Mine,
+    // An item placing a barrier, the barrier itself is a Wall
+    Barrier,
+    // The rubble left behind by a destroyed wall. Scaled up just like walls
+    Debris,
+    // An item putting armor on whoever uses it
+    Armor,
 }
 
 impl Default for MeshType {
@@ -27,8 +58,18 @@ pub fn generator_from_mesh_type(mesh_type: MeshType) -> MeshGenerator {
     match mesh_type {
         MeshType::Tank => tank_mesh,
         MeshType::Bullet => bullet_mesh,
-        MeshType::Wall => wall_mesh,
+        MeshType::Wall | MeshType::Zone => wall_mesh,
         MeshType::Heal => heal_item_mesh,
+        MeshType::Rocket => rocket_mesh,
+        MeshType::Weapon => weapon_item_mesh,
+        MeshType::SpeedBoost => speed_boost_mesh,
+        MeshType::RapidFire => rapid_fire_mesh,
+        MeshType::Shield => shield_mesh,
+        MeshType::Spawner => spawner_mesh,
+        MeshType::Mine => mine_mesh,
+        MeshType::Barrier => barrier_item_mesh,
+        MeshType::Debris => debris_mesh,
+        MeshType::Armor => armor_item_mesh,
         MeshType::Default | _ => DEFAULT_MESH
     }
 }
@@ -38,20 +79,20 @@ pub fn tank_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<
         .circle(
             DrawMode::fill(),
             Point2::new(x /*+ 25.0*/, y /*+ 25.0*/),
-            25.0,
+            TANK_RADIUS,
             0.1,
             color,
         )
         .rectangle(
             DrawMode::fill(),
-            Rect::new(x /*+ 25.0*/, y - 15.0 /*+ 10.0*/, 30.0, 30.0),
+            Rect::new(x /*+ 25.0*/, y - 0.5 * TANK_BARREL_WIDTH /*+ 10.0*/, TANK_BARREL_LENGTH, TANK_BARREL_WIDTH),
             color,
         )
         .build(ctx)
 }
 
 pub fn bullet_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
-    const RADIUS: f32 = 5.0;
+    const RADIUS: f32 = BULLET_RADIUS;
     MeshBuilder::new()
         .circle(
             DrawMode::fill(),
@@ -68,6 +109,18 @@ pub fn bullet_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResul
         .build(ctx)
 }
 
+/// A long body with a round tip at the front, pointing along the x axis like the bullet
+pub fn rocket_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    MeshBuilder::new()
+        .circle(DrawMode::fill(), Point2::new(x, y), 0.5 * ROCKET_WIDTH, 0.1, color)
+        .rectangle(
+            DrawMode::fill(),
+            Rect::new(x - ROCKET_LENGTH, y - 0.5 * ROCKET_WIDTH, ROCKET_LENGTH, ROCKET_WIDTH),
+            color,
+        )
+        .build(ctx)
+}
+
 pub fn wall_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
     MeshBuilder::new()
         .rectangle(
@@ -78,21 +131,169 @@ pub fn wall_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<
         .build(ctx)
 }
 
-pub fn inventory_mesh(ctx: &mut Context, x: f32, y: f32) -> GameResult<Mesh> {
+/// Chunks of a wall strewn over where it stood, in a unit square to be scaled up like the wall
+pub fn debris_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    // Where each chunk is within the square, and how big it is
+    const CHUNKS: [(f32, f32, f32); 6] = [
+        (0.05, 0.1, 0.3),
+        (0.5, 0.0, 0.25),
+        (0.7, 0.45, 0.3),
+        (0.2, 0.55, 0.2),
+        (0.45, 0.8, 0.2),
+        (0.0, 0.85, 0.15),
+    ];
     let mut builder = MeshBuilder::new();
-    const INVENTORY_SIZE: f32 = 40.0;
-    builder.rectangle(
+    for (c_x, c_y, size) in &CHUNKS {
+        builder.rectangle(DrawMode::fill(), Rect::new(x + c_x, y + c_y, *size, *size), color);
+    }
+    builder.build(ctx)
+}
+
+/// The color of a wall that's taken some damage, fading towards white the more it's taken
+pub fn worn_color(color: Color, health: u16, max_health: u16) -> Color {
+    const MAX_FADE: f32 = 0.7;
+    if max_health == 0 {
+        return color;
+    }
+    let fade = MAX_FADE * (1.0 - health as f32 / max_health as f32);
+    let fade_channel = |channel: f32| channel + (1.0 - channel) * fade;
+    Color::new(fade_channel(color.r), fade_channel(color.g), fade_channel(color.b), color.a)
+}
+
+/// The color items of a kind are drawn in, wherever they are
+pub fn item_color(mesh_type: MeshType) -> Color {
+    match mesh_type {
+        MeshType::Heal => Color::new(1.0, 0.0, 0.0, 1.0),
+        MeshType::SpeedBoost => Color::new(0.9, 0.7, 0.0, 1.0),
+        MeshType::RapidFire => Color::new(1.0, 0.4, 0.0, 1.0),
+        MeshType::Shield => Color::new(0.2, 0.4, 1.0, 1.0),
+        MeshType::Mine => Color::new(0.2, 0.2, 0.2, 1.0),
+        MeshType::Barrier => Color::new(0.5, 0.35, 0.2, 1.0),
+        MeshType::Armor => Color::new(0.55, 0.6, 0.65, 1.0),
+        _ => Color::new(0.3, 0.3, 0.3, 1.0),
+    }
+}
+
+/// The color effects of a kind are shown in, the same as the power-ups giving them
+pub fn effect_color(kind: EffectKind) -> Color {
+    match kind {
+        EffectKind::SpeedBoost => item_color(MeshType::SpeedBoost),
+        EffectKind::RapidFire => item_color(MeshType::RapidFire),
+        EffectKind::Shield => item_color(MeshType::Shield),
+        EffectKind::Slow => Color::new(0.4, 0.3, 0.2, 1.0),
+    }
+}
+
+/// A ring around a tank under an effect, further out for each effect it's under
+pub fn effect_ring(ctx: &mut Context, x: f32, y: f32, ring: usize, color: Color) -> GameResult<Mesh> {
+    const WIDTH: f32 = 3.0;
+    let radius = TANK_RADIUS + 2.0 * WIDTH + ring as f32 * 2.0 * WIDTH;
+    MeshBuilder::new()
+        .circle(DrawMode::stroke(WIDTH), Point2::new(x, y), radius, 0.1, color)
+        .build(ctx)
+}
+
+/// The round token all power-ups are, anchored at its top left corner like the other items
+fn power_up_token(x: f32, y: f32, color: Color) -> MeshBuilder {
+    let mut builder = MeshBuilder::new();
+    let center = Point2::new(x + 0.5 * ITEM_SIZE, y + 0.5 * ITEM_SIZE);
+    builder.circle(DrawMode::stroke(0.2 * ITEM_BOLDNESS), center, 0.5 * ITEM_SIZE - 0.1 * ITEM_BOLDNESS, 0.1, color);
+    builder
+}
+
+/// A power-up with an arrow pointing ahead
+pub fn speed_boost_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    let mut builder = power_up_token(x, y, color);
+    let (c_x, c_y) = (x + 0.5 * ITEM_SIZE, y + 0.5 * ITEM_SIZE);
+    builder.polygon(
         DrawMode::fill(),
-        Rect::new(x, y, INVENTORY_SIZE, INVENTORY_SIZE),
-        Color::new(0.0, 0.0, 0.0, 0.25),
-    );
+        &[Point2::new(c_x - 6.0, c_y - 8.0), Point2::new(c_x + 8.0, c_y), Point2::new(c_x - 6.0, c_y + 8.0)],
+        color,
+    )?;
+    builder.build(ctx)
+}
+
+/// A power-up with a row of bullets
+pub fn rapid_fire_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    let mut builder = power_up_token(x, y, color);
+    for i in -1..=1 {
+        let center = Point2::new(x + 0.5 * ITEM_SIZE + i as f32 * 7.0, y + 0.5 * ITEM_SIZE);
+        builder.circle(DrawMode::fill(), center, 3.0, 0.1, color);
+    }
+    builder.build(ctx)
+}
+
+/// A power-up with a solid core
+pub fn shield_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    let mut builder = power_up_token(x, y, color);
+    builder.circle(DrawMode::fill(), Point2::new(x + 0.5 * ITEM_SIZE, y + 0.5 * ITEM_SIZE), 0.25 * ITEM_SIZE, 0.1, color);
+    builder.build(ctx)
+}
+
+/// A round mine with a dot in the middle, anchored at its top left corner like the items
+pub fn mine_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    let center = Point2::new(x + 0.5 * ITEM_SIZE, y + 0.5 * ITEM_SIZE);
+    MeshBuilder::new()
+        .circle(DrawMode::stroke(3.0), center, MINE_RADIUS, 0.1, color)
+        .circle(DrawMode::fill(), center, 0.3 * MINE_RADIUS, 0.1, color)
+        .build(ctx)
+}
+
+/// A crate of bricks, one row offset from the other
+pub fn barrier_item_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    const BRICK_HEIGHT: f32 = ITEM_SIZE / 3.0;
+    let mut builder = MeshBuilder::new();
+    for row in 0..3 {
+        let offset = if row % 2 == 0 { 0.0 } else { 0.25 * ITEM_SIZE };
+        let y = y + row as f32 * BRICK_HEIGHT;
+        builder.rectangle(DrawMode::stroke(2.0), Rect::new(x + offset, y, 0.5 * ITEM_SIZE, BRICK_HEIGHT), color);
+        builder.rectangle(DrawMode::stroke(2.0), Rect::new(x + offset + 0.5 * ITEM_SIZE, y, 0.5 * ITEM_SIZE - offset, BRICK_HEIGHT), color);
+    }
+    builder.build(ctx)
+}
+
+/// A plate of armor, straight across the top and coming to a point at the bottom
+pub fn armor_item_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    let mut builder = MeshBuilder::new();
+    builder.polygon(
+        DrawMode::stroke(0.3 * ITEM_BOLDNESS),
+        &[
+            Point2::new(x + 0.15 * ITEM_SIZE, y),
+            Point2::new(x + 0.85 * ITEM_SIZE, y),
+            Point2::new(x + 0.85 * ITEM_SIZE, y + 0.55 * ITEM_SIZE),
+            Point2::new(x + 0.5 * ITEM_SIZE, y + ITEM_SIZE),
+            Point2::new(x + 0.15 * ITEM_SIZE, y + 0.55 * ITEM_SIZE),
+        ],
+        color,
+    )?;
+    builder.build(ctx)
+}
+
+/// A faint outline around where an item spawner puts its items, anchored like the items themselves
+pub fn spawner_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    const MARGIN: f32 = 5.0;
+    let pad = Rect::new(x - MARGIN, y - MARGIN, ITEM_SIZE + 2.0 * MARGIN, ITEM_SIZE + 2.0 * MARGIN);
+    MeshBuilder::new()
+        .rectangle(DrawMode::stroke(2.0), pad, Color::new(color.r, color.g, color.b, 0.3 * color.a))
+        .build(ctx)
+}
+
+/// How big each slot of the inventory on the HUD is
+pub const INVENTORY_SLOT_SIZE: f32 = 40.0;
+
+/// The translucent background of an inventory slot, outlined if it's the selected one
+pub fn inventory_mesh(ctx: &mut Context, x: f32, y: f32, selected: bool) -> GameResult<Mesh> {
+    let mut builder = MeshBuilder::new();
+    let slot = Rect::new(x, y, INVENTORY_SLOT_SIZE, INVENTORY_SLOT_SIZE);
+    builder.rectangle(DrawMode::fill(), slot, Color::new(0.0, 0.0, 0.0, 0.25));
+    if selected {
+        builder.rectangle(DrawMode::stroke(2.0), slot, Color::new(0.0, 0.0, 0.0, 0.8));
+    }
     builder.build(ctx)
 }
 
 pub fn heal_item_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
     let mut builder = MeshBuilder::new();
-    const ITEM_SIZE: f32 = 30.0;
-    const ITEM_BOLDNESS: f32 = 10.0;
     builder.rectangle(
         DrawMode::fill(),
         Rect::new(x + 0.5 * (ITEM_SIZE - ITEM_BOLDNESS), y,
@@ -108,42 +309,77 @@ pub fn heal_item_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameRe
     builder.build(ctx)
 }
 
+/// A crate with a barrel lying across it, anchored at its top left corner like the heal item
+pub fn weapon_item_mesh(ctx: &mut Context, x: f32, y: f32, color: Color) -> GameResult<Mesh> {
+    let mut builder = MeshBuilder::new();
+    builder.rectangle(DrawMode::stroke(0.5 * ITEM_BOLDNESS), Rect::new(x, y, ITEM_SIZE, ITEM_SIZE), color);
+    builder.rectangle(
+        DrawMode::fill(),
+        Rect::new(x, y + 0.5 * (ITEM_SIZE - 0.5 * ITEM_BOLDNESS), ITEM_SIZE, 0.5 * ITEM_BOLDNESS),
+        color,
+    );
+    builder.build(ctx)
+}
+
+/// A bar showing how much health is left, as wide for every max health.
+/// It's filled up according to how much of the max health is left
 pub fn health_bar(
     ctx: &mut Context,
     x: f32,
     y: f32,
     color: Color,
-    health: u8,
-    total_health: u8,
+    health: u16,
+    max_health: u16,
 ) -> GameResult<Mesh> {
     const HORIZONTAL_MARGIN: f32 = 5.0;
     const VERTICAL_MARGIN: f32 = 5.0;
-    const SPACING: f32 = 0.0;
     const HEALTH_HEIGHT: f32 = 40.0;
-    const HEALTH_WIDTH: f32 = 5.0;
+    // As wide as the 50 health of a tank used to be at 5px each
+    const HEALTH_WIDTH: f32 = 250.0;
     const BAR_HEIGHT: f32 = 2.0 * VERTICAL_MARGIN + HEALTH_HEIGHT;
-    let mut bar_width =
-        HORIZONTAL_MARGIN + (total_health as f32) * (HEALTH_WIDTH + SPACING) + VERTICAL_MARGIN;
-    if total_health > 0 {
-        bar_width -= SPACING;
-    }
+    let bar_width = 2.0 * HORIZONTAL_MARGIN + HEALTH_WIDTH;
     let mut builder = MeshBuilder::new();
     builder.rectangle(
         DrawMode::fill(),
         Rect::new(x, y, bar_width, BAR_HEIGHT),
         Color::new(0.0, 0.0, 0.0, 0.25),
     );
-    for i in 0..health {
+    if health > 0 && max_health > 0 {
+        let fraction = (health as f32 / max_health as f32).min(1.0);
         builder.rectangle(
             DrawMode::fill(),
-            Rect::new(
-                x + HORIZONTAL_MARGIN + (i as f32) * (HEALTH_WIDTH + SPACING),
-                y + VERTICAL_MARGIN,
-                HEALTH_WIDTH,
-                HEALTH_HEIGHT,
-            ),
+            Rect::new(x + HORIZONTAL_MARGIN, y + VERTICAL_MARGIN, fraction * HEALTH_WIDTH, HEALTH_HEIGHT),
             color,
         );
     }
     builder.build(ctx)
 }
+
+/// A thin bar filled up according to a progress from 0 to 1, e.g over a capture point
+pub fn progress_bar(ctx: &mut Context, x: f32, y: f32, width: f32, progress: f32, color: Color) -> GameResult<Mesh> {
+    const HEIGHT: f32 = 6.0;
+    let mut builder = MeshBuilder::new();
+    builder.rectangle(DrawMode::fill(), Rect::new(x, y, width, HEIGHT), Color::new(0.0, 0.0, 0.0, 0.25));
+    if progress > 0.0 {
+        builder.rectangle(DrawMode::fill(), Rect::new(x, y, width * progress.min(1.0), HEIGHT), color);
+    }
+    builder.build(ctx)
+}
+
+/// Outlines of hitboxes, already placed in the world. Used by the debug overlay
+pub fn hitbox_mesh(ctx: &mut Context, shapes: &[PlacedShape], color: Color) -> GameResult<Mesh> {
+    const LINE_WIDTH: f32 = 1.0;
+    let mut builder = MeshBuilder::new();
+    for shape in shapes {
+        match shape {
+            PlacedShape::Circle((x, y), radius) => {
+                builder.circle(DrawMode::stroke(LINE_WIDTH), Point2::new(*x, *y), *radius, 0.1, color);
+            }
+            PlacedShape::Polygon(points) => {
+                let points: Vec<Point2<f32>> = points.iter().map(|(x, y)| Point2::new(*x, *y)).collect();
+                builder.polygon(DrawMode::stroke(LINE_WIDTH), &points, color)?;
+            }
+        }
+    }
+    builder.build(ctx)
+}
@@ -1,18 +1,37 @@
-use crate::game::ecs::{npc, prefabs, ColorSystem, ControlSystem, Entity, HealthSystem, PositionWatcherSystem, System, TtlSystem, VelocitySystem, CollisionSystem, ReaperSystem, ScaleSystem, InventorySystem, NpcSystem};
+use crate::game::ecs::prefabs::{parse_map, PrefabArgs, PrefabLibrary};
+use crate::game::ecs::{prefabs, register_systems, ComponentRegistry, Entity, GameEvent, GameRng, GameEventBus, GameEventReader, MapBounds, PendingDamage, Schedule, Scheduler, ScoreBoard, SpatialIndex, NPC};
+use crate::misc::TypeSet;
 use crate::game::graphics::MeshType;
 use crate::net::{Connection, Event, EventListener, Handle, Protocol, NULL_HANDLE};
 use ggez::event::KeyCode;
-use ggez::graphics::{Color, WHITE, BLACK};
+use ggez::graphics::{Color, WHITE};
 use std::collections::{HashSet, VecDeque};
 use std::time::{Duration, Instant};
-use crate::misc::constants::DEFAULT_COLOR;
+use crate::misc::constants::{DEFAULT_COLOR, MAP_DIRECTORY, PREFAB_DIRECTORY};
 
 const CLIENT_COUNT: usize = 2;
-const NPC_COUNT: usize = 1;
-const CLIENT_COLORS: [Color; CLIENT_COUNT + NPC_COUNT] = [
+/// How many bots join a match unless told otherwise
+pub const DEFAULT_BOT_COUNT: usize = 1;
+// Each slot of a match, taken by a client or a bot, has its own input device, color and spawn point.
+// There's only so many of them, so slots past them start over from the first
+const SLOT_COLORS: [Color; 7] = [
     Color::new(1.0, 0.0, 0.0, 1.0),
     Color::new(0.0, 0.0, 1.0, 1.0),
     Color::new(0.0, 1.0, 1.0, 1.0),
+    Color::new(1.0, 1.0, 0.0, 1.0),
+    Color::new(0.0, 1.0, 0.0, 1.0),
+    Color::new(1.0, 0.5, 0.0, 1.0),
+    Color::new(1.0, 1.0, 1.0, 1.0),
+];
+// The first two are the clients', facing each other
+const SLOT_SPAWN_POINTS: [(f32, f32, f32); 7] = [
+    (800.0, 250.0, 180.0),
+    (200.0, 250.0, 0.0),
+    (500.0, 250.0, 0.0),
+    (100.0, 80.0, 0.0),
+    (900.0, 420.0, 180.0),
+    (900.0, 80.0, 180.0),
+    (100.0, 420.0, 0.0),
 ];
 
 pub const PLAYER1_HANDLE: Handle = 1;
@@ -22,23 +41,54 @@ const CLIENT_PLAYER_HANDLES: [Handle; CLIENT_COUNT] = [
     PLAYER2_HANDLE
 ];
 
-const CLIENT_PLAYER_SPAWN_POINTS: [(f32, f32, f32); CLIENT_COUNT] = [
-    (800.0, 250.0, 180.0),
-    (200.0, 250.0, 0.0)
-];
+/// How long each tick of a headless match is, in seconds
+pub const HEADLESS_DELTA_TIME: f32 = 0.02;
+
+// Where the host's quicksaves (F5) are written to and quickloads (F9) are read from
+const QUICKSAVE_PATH: &str = "quicksave.match";
+
+/// Orders all systems of the game world, panicking if their ordering constraints contradict
+fn build_schedule() -> Schedule {
+    let mut scheduler = Scheduler::new();
+    register_systems(&mut scheduler);
+    scheduler.build().expect("Invalid system ordering")
+}
+
+/// How a headless match went
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchSummary {
+    /// How many ticks were played, each of them HEADLESS_DELTA_TIME seconds long
+    pub ticks: u32,
+    /// The bots still standing at the end
+    pub survivors: Vec<Handle>,
+    /// Every bot's handle with its kills and deaths, in the order they joined
+    pub scores: Vec<(Handle, u32, u32)>,
+}
 
 pub struct Server<PROTOCOL: Protocol> {
-    clients: [Connection<PROTOCOL>; CLIENT_COUNT],
+    // No clients at all in headless matches
+    clients: Vec<Connection<PROTOCOL>>,
+    // How many bots join each match on top of the clients, and how well they play
+    bots: usize,
+    bot_difficulty: String,
+    // The handles of the bots of the running match
+    bot_handles: Vec<Handle>,
+    // Seeds the randomness of each match, so it plays out the same every time. Random if there's none
+    seed: Option<u64>,
     //Every entity in the game has a handle that works as an identifier
     handles: HashSet<Handle>,
     // The latest handle to be assigned
     last_handle: Handle,
-    // Keys currently held down for each client
-    pressed_keys: [HashSet<KeyCode>; CLIENT_COUNT + NPC_COUNT],
-    systems: Vec<Box<dyn System>>,
+    // Keys currently held down for each slot, the clients' first and then the bots'
+    pressed_keys: Vec<HashSet<KeyCode>>,
+    schedule: Schedule,
     //All game objects are considered entities
     entities: Vec<Entity>,
     events: VecDeque<Event>,
+    // Gameplay events passed between systems, these never reach the clients
+    game_events: GameEventBus,
+    // Global state shared by all systems, such as the score board. Lasts until the match is over
+    resources: TypeSet,
     delta_time: f32,
     game_over: bool,
 }
@@ -51,14 +101,8 @@ impl<PROTOCOL: Protocol> Server<PROTOCOL> {
             let map_index = self.await_map_choice();
             // Let the clients know the game is ready to start
             self.broadcast_event(&Event::Start);
-            self.spawn_npc();
-            self.spawn_players();
-            match map_index {
-                1 => self.map1(), // The bad,
-                2 => self.map2(), // The ugly
-                _ => ()
-            }
-            self.spawn_border_walls();
+            // map1 is the bad, map2 the ugly
+            self.start_match(&format!("map{}", map_index));
             let mut last_frame = Instant::now();
             let mut last_broadcast = Instant::now();
             const MIN_BROADCAST_DURATION: Duration = Duration::from_micros(0);
@@ -90,71 +134,179 @@ impl<PROTOCOL: Protocol> Server<PROTOCOL> {
         }
     }
 
-    pub fn new(client1: Connection<PROTOCOL>, client2: Connection<PROTOCOL>) -> Self {
-        let clients = [client1, client2];
-        Self {
+    /// A server for two clients, failing if the prefabs everything is spawned from can't be loaded
+    pub fn new(client1: Connection<PROTOCOL>, client2: Connection<PROTOCOL>) -> Result<Self, String> {
+        Self::with_clients(vec![client1, client2])
+    }
+
+    /// A server for bots only, without anyone to send anything to. See run_headless
+    pub fn headless() -> Result<Self, String> {
+        Self::with_clients(Vec::new())
+    }
+
+    fn with_clients(clients: Vec<Connection<PROTOCOL>>) -> Result<Self, String> {
+        // Everything is spawned from the prefab files, there's no playing without them
+        let prefab_library = PrefabLibrary::load_directory(PREFAB_DIRECTORY).map_err(|e| format!("Couldn't load prefabs: {}", e))?;
+        let mut server = Self {
             clients,
+            bots: DEFAULT_BOT_COUNT,
+            bot_difficulty: "normal".to_string(),
+            bot_handles: Vec::new(),
+            seed: None,
             handles: Default::default(),
             last_handle: PLAYER2_HANDLE + 1,
-            pressed_keys: [HashSet::new(), HashSet::new(), HashSet::new()],
-            systems: vec![
-                Box::new(NpcSystem),
-                Box::new(ControlSystem),
-                // Collision system should proceed velocity system
-                Box::new(CollisionSystem),
-                Box::new(VelocitySystem),
-                Box::new(PositionWatcherSystem),
-                Box::new(ScaleSystem),
-                Box::new(ColorSystem),
-                Box::new(HealthSystem),
-                Box::new(TtlSystem),
-                Box::new(InventorySystem),
-                // Should come last, since it handles deleted entities
-                Box::new(ReaperSystem),
-            ],
+            pressed_keys: Vec::new(),
+            schedule: build_schedule(),
             entities: vec![],
             events: VecDeque::new(),
+            game_events: GameEventBus::new(),
+            resources: TypeSet::new(),
             delta_time: 0.0,
             game_over: false,
-        }
+        };
+        // Systems can count on the resources being there from the first tick on
+        server.insert_default_resources(prefab_library);
+        Ok(server)
+    }
+
+    /// Sets how many bots join each match on top of the clients, and how well they play:
+    /// easy, normal or hard
+    pub fn with_bots(mut self, count: usize, difficulty: &str) -> Self {
+        self.bots = count;
+        self.bot_difficulty = difficulty.to_string();
+        self
+    }
+
+    /// Makes every match play out the same way, given the same players do the same things
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// How many slots a match has, one for each client and each bot
+    fn slot_count(&self) -> usize {
+        self.clients.len() + self.bots
     }
 
     /// Reset a bunch of state between maps
-    fn purge_state(&mut self) {
+    pub(crate) fn purge_state(&mut self) {
         self.game_over = false;
         self.handles.clear();
-        self.pressed_keys.iter_mut().for_each(|s| s.clear());
-        self.systems = vec![
-            Box::new(NpcSystem),
-            Box::new(ControlSystem),
-            // Collision system should proceed velocity system
-            Box::new(CollisionSystem),
-            Box::new(VelocitySystem),
-            Box::new(PositionWatcherSystem),
-            Box::new(ScaleSystem),
-            Box::new(ColorSystem),
-            Box::new(HealthSystem),
-            Box::new(TtlSystem),
-            Box::new(InventorySystem),
-            // Should come last, since it handles deleted entities
-            Box::new(ReaperSystem),
-        ];
+        self.bot_handles.clear();
+        self.pressed_keys = vec![HashSet::new(); self.slot_count()];
+        self.schedule = build_schedule();
         self.entities.clear();
         self.events.clear();
+        self.game_events = GameEventBus::new();
+        // Read the prefabs anew for each match, so they can be tuned between matches.
+        // If they've been broken since, keep on playing with the ones from before
+        let previous = self.resources.remove::<PrefabLibrary>();
+        let prefab_library = PrefabLibrary::load_directory(PREFAB_DIRECTORY).unwrap_or_else(|e| {
+            eprintln!("Couldn't reload prefabs: {}", e);
+            previous.unwrap_or_default()
+        });
+        self.resources.clear();
+        self.insert_default_resources(prefab_library);
+    }
+
+    /// Spawns the clients' players, the bots and everything on the map
+    fn start_match(&mut self, map: &str) {
+        self.spawn_bots();
+        self.spawn_players();
+        self.load_map(map);
+        self.load_map("border");
+    }
+
+    /// Plays a match between bots only on a map, as fast as it can be simulated rather than in
+    /// real time. It's over once at most one bot is left standing, or after a number of ticks.
+    /// Meant for trying out changes to the balance or the AI without anyone having to play
+    pub fn run_headless(&mut self, map: &str, max_ticks: u32) -> MatchSummary {
+        self.purge_state();
+        self.start_match(map);
+        self.delta_time = HEADLESS_DELTA_TIME;
+        let mut ticks = 0;
+        while ticks < max_ticks && !self.game_over && self.survivors().len() > 1 {
+            self.call_systems();
+            // There's nobody to tell about any of it
+            self.events.clear();
+            ticks += 1;
+        }
+        let scores = match self.resource::<ScoreBoard>() {
+            Some(score_board) => self
+                .bot_handles
+                .iter()
+                .map(|handle| (*handle, score_board.get_kills(*handle), score_board.get_deaths(*handle)))
+                .collect(),
+            None => Vec::new(),
+        };
+        MatchSummary {
+            ticks,
+            survivors: self.survivors(),
+            scores,
+        }
+    }
+
+    /// The bots which are still around
+    fn survivors(&self) -> Vec<Handle> {
+        self.entities
+            .iter()
+            .filter(|entity| !entity.deleted() && entity.has_component::<NPC>())
+            .map(Entity::get_handle)
+            .collect()
+    }
+
+    /// Get a global resource shared by all systems, see ServerContext::resource
+    pub(crate) fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get::<T>()
+    }
+
+    /// Insert a global resource, which lasts until the state is purged
+    pub(crate) fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources.insert(resource);
+    }
+
+    /// Puts all resources systems expect to be present at the start of a match
+    fn insert_default_resources(&mut self, prefab_library: PrefabLibrary) {
+        self.insert_resource(ScoreBoard::new());
+        self.insert_resource(prefab_library);
+        self.insert_resource(SpatialIndex::new());
+        self.insert_resource(PendingDamage::new());
+        self.insert_resource(match self.seed {
+            Some(seed) => GameRng::seeded(seed),
+            None => GameRng::from_entropy(),
+        });
+        self.insert_resource(MapBounds::default());
     }
 
     /// Call any ecs Systems part of the game world
-    fn call_systems(&mut self) {
+    pub(crate) fn call_systems(&mut self) {
         // todo: maybe don't clone the keys each time
-        let mut ctx =
-            ServerContext::new(self.pressed_keys.clone(), self.delta_time, self.last_handle);
-        for system in &mut self.systems {
-            system.update(&mut self.entities, &mut ctx);
-        }
+        let game_events = std::mem::take(&mut self.game_events);
+        let resources = std::mem::take(&mut self.resources);
+        let mut ctx = ServerContext::new(
+            self.pressed_keys.clone(),
+            self.delta_time,
+            self.last_handle,
+            game_events,
+            resources,
+        );
+        self.schedule.run(&mut self.entities, &mut ctx);
         self.despawn_deleted();
         ctx.transfer_state(self);
     }
 
+    #[cfg(test)] // Only used in tests, for now
+    /// Hands the context systems get to some code outside of any system, along with the resources.
+    /// Whatever it spawns is spawned right after, just like at the end of a tick
+    pub(crate) fn with_context<T>(&mut self, run: impl FnOnce(&mut ServerContext) -> T) -> T {
+        let game_events = std::mem::take(&mut self.game_events);
+        let resources = std::mem::take(&mut self.resources);
+        let mut ctx = ServerContext::new(self.pressed_keys.clone(), self.delta_time, self.last_handle, game_events, resources);
+        let result = run(&mut ctx);
+        ctx.transfer_state(self);
+        result
+    }
+
     /// Send despawn events for all entities that have been marked for deletion and delete the entities
     fn despawn_deleted(&mut self) {
         let mut despawn_events = VecDeque::new();
@@ -168,13 +320,32 @@ impl<PROTOCOL: Protocol> Server<PROTOCOL> {
         self.entities.retain(|entity| !entity.deleted());
     }
 
-    fn spawn_npc(&mut self) {
+    /// The prefab arguments of whatever takes a slot, its input device, spawn point and color
+    fn slot_args(slot: usize) -> PrefabArgs {
+        let spawn_point = SLOT_SPAWN_POINTS[slot % SLOT_SPAWN_POINTS.len()];
+        PrefabArgs::new()
+            .with("input", slot)
+            .with("x", spawn_point.0)
+            .with("y", spawn_point.1)
+            .with("angle", spawn_point.2)
+            .with_color(SLOT_COLORS[slot % SLOT_COLORS.len()])
+    }
+
+    /// Fills every slot after the clients' with a bot
+    fn spawn_bots(&mut self) {
+        for slot in self.clients.len()..self.slot_count() {
+            self.spawn_bot(slot);
+        }
+    }
+
+    fn spawn_bot(&mut self, slot: usize) {
         let handle = self.last_handle + 1;
         self.last_handle = handle;
-        let npc = npc(handle, 2, 500.0, 250.0, 0.0, CLIENT_COLORS[2]);
+        let args = Self::slot_args(slot).with("difficulty", &self.bot_difficulty);
         self.events
-            .push_back(Event::Color(handle, CLIENT_COLORS[2]));
-        self.spawn(npc);
+            .push_back(Event::Color(handle, SLOT_COLORS[slot % SLOT_COLORS.len()]));
+        self.bot_handles.push(handle);
+        self.spawn_prefab("npc", handle, &args);
     }
 
     fn spawn_players(&mut self) {
@@ -183,20 +354,16 @@ impl<PROTOCOL: Protocol> Server<PROTOCOL> {
         }
     }
 
-    fn spawn_player(&mut self, client_index: usize) {
-        let handle = CLIENT_PLAYER_HANDLES[client_index];
-        let spawn_point = CLIENT_PLAYER_SPAWN_POINTS[client_index];
-        let player = prefabs::player(
-            handle,
-            client_index,
-            spawn_point.0,
-            spawn_point.1,
-            spawn_point.2,
-            CLIENT_COLORS[client_index],
-        );
-        self.clients[client_index].send(&Event::Yield(handle));
-        self.events.push_back(Event::Color(handle, CLIENT_COLORS[client_index]));
-        self.spawn(player);
+    /// Spawns the player of a client, or a bot if nobody's taken the slot
+    fn spawn_player(&mut self, slot: usize) {
+        if slot >= self.clients.len() {
+            self.spawn_bot(slot);
+            return;
+        }
+        let handle = CLIENT_PLAYER_HANDLES[slot];
+        self.clients[slot].send(&Event::Yield(handle));
+        self.events.push_back(Event::Color(handle, SLOT_COLORS[slot]));
+        self.spawn_prefab("player", handle, &Self::slot_args(slot));
     }
 
     /// Spawn an entity (WARNING: Will not reset its handle, so handle must be unique)
@@ -208,18 +375,18 @@ impl<PROTOCOL: Protocol> Server<PROTOCOL> {
             .unwrap_or_default();
         self.handles.insert(handle);
         self.events.push_back(Event::Spawn(handle, mesh_type));
+        self.game_events.publish(GameEvent::Spawned(handle, mesh_type));
         self.entities.push(entity);
     }
 
     // Wait for clients to send a Ready-event before starting game
     fn await_clients(&mut self) {
-        let mut ready = [false, false];
-        while !ready[0] || !ready[1] {
-            if let Some(Event::Ready) = self.clients[0].recv() {
-                ready[0] = true;
-            }
-            if let Some(Event::Ready) = self.clients[1].recv() {
-                ready[1] = true;
+        let mut ready = vec![false; self.clients.len()];
+        while ready.contains(&false) {
+            for (i, client) in self.clients.iter_mut().enumerate() {
+                if let Some(Event::Ready) = client.recv() {
+                    ready[i] = true;
+                }
             }
         }
         self.broadcast_event(&Event::Standby);
@@ -256,54 +423,65 @@ impl<PROTOCOL: Protocol> Server<PROTOCOL> {
         }
     }
 
-    // Creates map1
-    fn map1(&mut self) {
-        const SHIELD_HEIGHT: f32 = 100.0;
-        self.spawn(prefabs::heal_item(self.last_handle + 1, 275.0, 125.0));
-        self.last_handle += 1;
-        self.spawn(prefabs::heal_item(self.last_handle + 1, 275.0, 375.0));
-        self.last_handle += 1;
-        self.spawn(prefabs::heal_item(self.last_handle + 1, 675.0, 125.0));
-        self.last_handle += 1;
-        self.spawn(prefabs::heal_item(self.last_handle + 1, 675.0, 375.0));
-        self.last_handle += 1;
-        self.spawn(prefabs::wall(self.last_handle + 1, 250.0, 250.0 - SHIELD_HEIGHT * 0.5, 10.0, SHIELD_HEIGHT, BLACK));
-        self.last_handle += 1;
-        self.spawn(prefabs::wall(self.last_handle + 1, 750.0, 250.0 - SHIELD_HEIGHT * 0.5, 10.0, SHIELD_HEIGHT, BLACK));
-        self.last_handle += 1;
-    }
-
-    // Creates map2
-    fn map2(&mut self) {
-        const BROWN: Color = Color::new(0.4, 0.3, 0.2, 1.0);
-        self.spawn(prefabs::wall(self.last_handle + 1, 400.0, 185.0, 20.0, 150.0, BROWN));
-        self.last_handle += 1;
-        self.spawn(prefabs::wall(self.last_handle + 1, 605.0, 185.0, 20.0, 150.0, BROWN));
-        self.last_handle += 1;
-        self.spawn(prefabs::wall(self.last_handle + 1, 500.0, 100.0, 20.0, 100.0, BROWN));
-        self.last_handle += 1;
-        self.spawn(prefabs::wall(self.last_handle + 1, 500.0, 300.0, 20.0, 100.0, BROWN));
-        self.last_handle += 1;
-        self.spawn(prefabs::heal_item(self.last_handle + 1, 50.0, 250.0));
-        self.last_handle += 1;
-        self.spawn(prefabs::heal_item(self.last_handle + 1, 920.0, 250.0));
-        self.last_handle += 1;
-    }
-
-    fn spawn_border_walls(&mut self) {
-        const PURPLE: Color = Color::new(0.7, 0.0, 0.7, 1.0);
-        // Upper
-        self.spawn(prefabs::wall(self.last_handle + 1, -10.0, -10.0, 1020.0, 20.0, PURPLE));
-        self.last_handle += 1;
-        // Lower
-        self.spawn(prefabs::wall(self.last_handle + 1, -10.0, 490.0, 1020.0, 20.0, PURPLE));
-        self.last_handle += 1;
-        // Right
-        self.spawn(prefabs::wall(self.last_handle + 1, -10.0, 0.0, 20.0, 500.0, PURPLE));
-        self.last_handle += 1;
-        // Left
-        self.spawn(prefabs::wall(self.last_handle + 1, 990.0, 0.0, 20.0, 500.0, PURPLE));
-        self.last_handle += 1;
+    /// Spawns an entity from the prefab library, complaining if it can't be done
+    fn spawn_prefab(&mut self, name: &str, handle: Handle, args: &PrefabArgs) {
+        let entity = match self.resource::<PrefabLibrary>() {
+            Some(library) => library.instantiate(name, handle, args),
+            None => Err("No prefab library".to_string()),
+        };
+        match entity {
+            Ok(entity) => self.spawn(entity),
+            Err(e) => eprintln!("Couldn't spawn {}: {}", name, e),
+        }
+    }
+
+    /// Spawns everything listed in a map file
+    fn load_map(&mut self, name: &str) {
+        let path = format!("{}/{}.map", MAP_DIRECTORY, name);
+        let spawns = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| parse_map(&text));
+        match spawns {
+            Ok(spawns) => {
+                for spawn in spawns {
+                    self.last_handle += 1;
+                    self.spawn_prefab(&spawn.prefab, self.last_handle, &spawn.args);
+                }
+            }
+            Err(e) => eprintln!("Couldn't load {}: {}", path, e),
+        }
+    }
+
+    /// Writes every entity of the running match to a file, which can later be loaded by load_match
+    fn save_match(&self, path: &str) -> Result<(), String> {
+        let registry = ComponentRegistry::with_game_components();
+        let entities = registry.save_entities(&self.entities)?;
+        let text = format!("last_handle {}\n{}", self.last_handle, entities);
+        std::fs::write(path, text).map_err(|e| format!("Couldn't write {}: {}", path, e))
+    }
+
+    /// Replaces every entity of the running match with those saved to a file by save_match
+    fn load_match(&mut self, path: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+        let last_handle = text
+            .lines()
+            .find(|line| line.starts_with("last_handle"))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|handle| handle.parse::<Handle>().ok())
+            .ok_or_else(|| format!("{} has no last_handle", path))?;
+        let registry = ComponentRegistry::with_game_components();
+        // Load everything before touching the match, so a broken file leaves it as it was
+        let entities = registry.load_entities(&text, &prefabs::script_library())?;
+        for entity in &mut self.entities {
+            entity.delete();
+        }
+        self.despawn_deleted();
+        self.handles.clear();
+        for entity in entities {
+            self.spawn(entity);
+        }
+        self.last_handle = last_handle;
+        Ok(())
     }
 }
 
@@ -315,6 +493,17 @@ impl<PROTOCOL: Protocol> EventListener for Server<PROTOCOL> {
 
     fn on_key_down(&mut self, conn_index: usize, key_code: KeyCode) {
         self.pressed_keys[conn_index].insert(key_code);
+        // Only the host gets to quicksave and quickload
+        if conn_index == 0 {
+            let result = match key_code {
+                KeyCode::F5 => self.save_match(QUICKSAVE_PATH),
+                KeyCode::F9 => self.load_match(QUICKSAVE_PATH),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+        }
     }
 }
 
@@ -326,19 +515,23 @@ enum ServerCommand {
 
 /// A context object providing an API for some limited interaction with the server made available to ecs Systems
 pub struct ServerContext {
-    // HashSets of keys pressed on each client
-    input_devices: [HashSet<KeyCode>; CLIENT_COUNT + NPC_COUNT],
+    // HashSets of keys pressed on each slot's input device, the clients' and the bots'
+    input_devices: Vec<HashSet<KeyCode>>,
     delta_time: f32,
     events: VecDeque<Event>,
     commands: VecDeque<ServerCommand>,
     last_handle: Handle,
+    game_events: GameEventBus,
+    resources: TypeSet,
 }
 
 impl ServerContext {
     fn new(
-        input_devices: [HashSet<KeyCode>; CLIENT_COUNT + NPC_COUNT],
+        input_devices: Vec<HashSet<KeyCode>>,
         delta_time: f32,
         last_handle: Handle,
+        game_events: GameEventBus,
+        resources: TypeSet,
     ) -> Self {
         Self {
             input_devices,
@@ -346,14 +539,18 @@ impl ServerContext {
             events: VecDeque::new(),
             commands: Default::default(),
             last_handle,
+            game_events,
+            resources,
         }
     }
 
-    pub fn spawn(&mut self, mut entity: Entity) {
+    /// Spawns an entity at the end of the tick, returning the handle it's been given
+    pub fn spawn(&mut self, mut entity: Entity) -> Handle {
         let handle = self.last_handle + 1;
         self.last_handle = handle;
         entity = entity.change_handle(handle);
         self.commands.push_back(ServerCommand::Spawn(entity));
+        handle
     }
 
     pub fn spawn_player(&mut self, client_index: usize) {
@@ -376,11 +573,54 @@ impl ServerContext {
         self.events.push_back(event);
     }
 
+    /// Announce a gameplay event to any subscribing systems
+    pub fn publish(&mut self, event: GameEvent) {
+        self.game_events.publish(event);
+    }
+
+    /// Get all gameplay events the reader hasn't seen yet
+    pub fn read_game_events(&self, reader: &mut GameEventReader) -> Vec<GameEvent> {
+        self.game_events.read(reader)
+    }
+
+    /// Get a global resource shared by all systems, if one of that type has been inserted
+    pub fn resource<T: 'static>(&self) -> Option<&T> {
+        self.resources.get::<T>()
+    }
+
+    pub fn resource_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut::<T>()
+    }
+
+    /// Take a global resource out, e.g to use it while looking at other resources.
+    /// Put it back with insert_resource, or it's gone for the rest of the match
+    pub fn take_resource<T: 'static>(&mut self) -> Option<T> {
+        self.resources.remove::<T>()
+    }
+
+    /// The match's random number generator, a fresh unseeded one if it's been taken out
+    pub fn rng(&mut self) -> &mut GameRng {
+        if !self.resources.contains::<GameRng>() {
+            self.resources.insert(GameRng::from_entropy());
+        }
+        self.resources.get_mut::<GameRng>().unwrap()
+    }
+
+    /// Insert a global resource, replacing any previous one of the same type.
+    /// It persists across ticks until the match is over
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources.insert(resource);
+    }
+
     pub fn trigger_game_over(&mut self) {
         self.commands.push_back(ServerCommand::GameOver);
     }
 
     fn transfer_state<PROTOCOL: Protocol>(&mut self, server: &mut Server<PROTOCOL>) {
+        // Hand back the game events before spawning anything, since spawns are announced there too
+        server.game_events = std::mem::take(&mut self.game_events);
+        server.game_events.end_tick();
+        server.resources = std::mem::take(&mut self.resources);
         while !self.commands.is_empty() {
             match self.commands.pop_front() {
                 Some(ServerCommand::Spawn(entity)) => server.spawn(entity),
@@ -11,9 +11,13 @@ mod game;
 mod misc;
 mod net;
 
-use crate::game::Server;
+use crate::game::ecs::Difficulty;
+use crate::game::{Server, DEFAULT_BOT_COUNT, HEADLESS_DELTA_TIME};
 use std::process::exit;
 
+// How long a headless match may go on for unless told otherwise, in seconds
+const DEFAULT_HEADLESS_SECONDS: f32 = 120.0;
+
 fn main() {
     // Skip the first command-line argument, it's just the working dir
     let args: Vec<String> = std::env::args().skip(1).collect();
@@ -23,17 +27,49 @@ fn main() {
         exit(1);
     }
     if args[0] == "-host" {
+        // -host [bots] [difficulty]
+        let bots = parse_arg(&args, 1, DEFAULT_BOT_COUNT);
+        let difficulty = parse_difficulty(&args, 2);
         // Spawn the server in another thread and connect to localhost
-        std::thread::spawn(server_main);
+        std::thread::spawn(move || server_main(bots, &difficulty));
         client_main(&"localhost".to_string(), true)
+    } else if args[0] == "-headless" {
+        // -headless [bots] [map] [seconds] [difficulty] [seed], bots against each other without any clients
+        let bots = parse_arg(&args, 1, 2);
+        let map = args.get(2).cloned().unwrap_or_else(|| "map1".to_string());
+        let seconds = parse_arg(&args, 3, DEFAULT_HEADLESS_SECONDS);
+        let difficulty = parse_difficulty(&args, 4);
+        let seed = args.get(5).map(|_| parse_arg(&args, 5, 0));
+        headless_main(bots, &map, seconds, &difficulty, seed)
     } else {
         // Connect to a remote host
         client_main(&args[0], false)
     }
 }
 
+/// An optional command-line argument, exiting if it's there but not what it should be
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, default: T) -> T {
+    match args.get(index) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            eprintln!("Usage error: {} isn't a valid argument", arg);
+            exit(1)
+        }),
+        None => default,
+    }
+}
+
+/// The name of an optional difficulty argument, normal if it's not there
+fn parse_difficulty(args: &[String], index: usize) -> String {
+    let name = args.get(index).cloned().unwrap_or_else(|| "normal".to_string());
+    if Difficulty::by_name(&name).is_none() {
+        eprintln!("Usage error: the difficulty is easy, normal or hard, not {}", name);
+        exit(1);
+    }
+    name
+}
+
 /// Set up a server at port 1337 and any address
-fn server_main() {
+fn server_main(bots: usize, difficulty: &str) {
     let listener = TcpListener::bind("0.0.0.0:1337").unwrap();
     // Connect to clients
     let (socket1, address1) = listener.accept().unwrap();
@@ -49,10 +85,33 @@ fn server_main() {
     let host_conn = Connection::<SmartProtocol>::from_socket(host);
     let guest_conn = Connection::<SmartProtocol>::from_socket(guest);
     // Start the server up
-    let mut server = Server::new(host_conn, guest_conn);
+    let server = Server::new(host_conn, guest_conn).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+    let mut server = server.with_bots(bots, difficulty);
     server.main();
 }
 
+/// Plays a match between bots as fast as possible and prints how it went.
+/// The same seed plays the same match again
+fn headless_main(bots: usize, map: &str, seconds: f32, difficulty: &str, seed: Option<u64>) {
+    let server = Server::<SmartProtocol>::headless().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1)
+    });
+    let mut server = server.with_bots(bots, difficulty);
+    if let Some(seed) = seed {
+        server = server.with_seed(seed);
+    }
+    let summary = server.run_headless(map, (seconds / HEADLESS_DELTA_TIME) as u32);
+    println!("{} bots on {} for {} ticks", bots, map, summary.ticks);
+    for (handle, kills, deaths) in summary.scores {
+        let standing = if summary.survivors.contains(&handle) { "standing" } else { "down" };
+        println!("bot {}: {} kills, {} deaths, {}", handle, kills, deaths, standing);
+    }
+}
+
 /// Connect to a server at port 1337 and the given address
 fn client_main(address: &String, host: bool) {
     // Connect to a local or remote host at port 1337
@@ -7,6 +7,11 @@ use std::collections::HashMap;
 /// The default color for any color use where no other color exists to take its place
 pub const DEFAULT_COLOR: Color = Color::new(0.7, 0.0, 0.7, 1.0);
 
+/// Where the server reads its .prefab files from, relative to the working directory
+pub const PREFAB_DIRECTORY: &str = "assets/prefabs";
+/// Where the server reads its .map files from, relative to the working directory
+pub const MAP_DIRECTORY: &str = "assets/maps";
+
 /// All the keys in ggez, at least as far as I know. Used for lookup during deserialization
 pub const ALL_KEYS: [KeyCode; 161] = [
     KeyCode::Key1,
@@ -173,13 +178,24 @@ pub const ALL_KEYS: [KeyCode; 161] = [
 ];
 
 /// All the mesh types we support for serializing. Used for lookup during deserialization
-pub const ALL_MESH_TYPES: [MeshType; 6] = [
+pub const ALL_MESH_TYPES: [MeshType; 17] = [
     MeshType::Default,
     MeshType::Tank,
     MeshType::Bullet,
     MeshType::Wall,
     MeshType::Heal,
-    MeshType::None
+    MeshType::None,
+    MeshType::Zone,
+    MeshType::Rocket,
+    MeshType::Weapon,
+    MeshType::SpeedBoost,
+    MeshType::RapidFire,
+    MeshType::Shield,
+    MeshType::Spawner,
+    MeshType::Mine,
+    MeshType::Barrier,
+    MeshType::Debris,
+    MeshType::Armor,
 ];
 
 // Created lazily using some genius's magic (I think it's smart-pointers or something)
@@ -18,6 +18,10 @@ impl<T> State<T> {
     pub fn invalidated(&self) -> bool {
         self.invalidated
     }
+    /// Marks the state as changed without touching it
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
     /// Returns a bool signifying whether this state may have changed
     /// since the last call to invalidated_since
     pub fn invalidated_since(&mut self) -> bool {
@@ -27,6 +31,11 @@ impl<T> State<T> {
         self.invalidated = false;
         old_invalidation_status
     }
+    /// Changes the state without marking it as changed,
+    /// for changes nobody needs to hear about such as a countdown the clients keep themselves
+    pub fn untracked(&mut self) -> &mut T {
+        &mut self.t
+    }
 }
 
 // For any further info on Deref and DerefMut, see the Rust documentation
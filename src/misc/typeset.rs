@@ -37,10 +37,17 @@ impl TypeSet {
     }
 
     pub fn remove<E: Sized + 'static>(&mut self) -> Option<E> {
-        *self.elements.remove(&TypeId::of::<E>())?.downcast().ok()?
+        let element = self.elements.remove(&TypeId::of::<E>())?.downcast::<E>().ok()?;
+        Some(*element)
     }
 
     pub fn clear(&mut self) {
         self.elements.clear()
     }
 }
+
+impl Default for TypeSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(deprecated)]
 
+use crate::game::ecs::{EffectKind, Shape};
 use crate::game::graphics::MeshType;
 use ggez::event::KeyCode;
 use ggez::graphics::Color;
@@ -29,16 +30,17 @@ pub enum Event {
     Yield(Handle),
     // Tells the client to spawn a mesh and associate it with a handle
     Spawn(Handle, MeshType),
-    // Tells the client that it has picked up an item and what mesh to render it as
-    PickUp(Handle, MeshType),
+    // Tells the client what's in each slot of an entity's inventory and which slot is selected,
+    // as the mesh to render each item as and how many of it there are. Empty slots are MeshType::None
+    Inventory(Handle, usize, Vec<(MeshType, u16)>),
     // Tells the client to no longer consider (i.e render) an entity/mesh of a certain handle
     Despawn(Handle),
     // Tells the server that a key has just been pressed, client-side
     KeyDown(KeyCode),
     // Tells the server that a key has just been released, client-side
     KeyUp(KeyCode),
-    // Tells the client how much health a certain entity has
-    Health(Handle, u8),
+    // Tells the client how much health a certain entity has, out of how much it can have at most
+    Health(Handle, u16, u16),
     // Tells the client what color an entity should be rendered in
     Color(Handle, Color),
     // Tells the client how an entity should be scaled up when rendering
@@ -46,7 +48,20 @@ pub enum Event {
     // Tells the client that the game is over for any reason
     GameOver,
     // Used by the client to choose a map
-    Map(usize)
+    Map(usize),
+    // Tells the client what shapes an entity collides with, for the debug overlay
+    Hitbox(Handle, Vec<Shape>),
+    // Tells the client how far along something is from 0 to 1, e.g the capturing of a capture point
+    Progress(Handle, f32),
+    // Tells the client how much ammo an entity's weapon has out of how much it holds,
+    // how many seconds until it's ready to fire and how long that wait was to begin with
+    Weapon(Handle, u16, u16, f32, f32),
+    // Tells the client which effects an entity is under, how many seconds each has left
+    // and how long each lasted to begin with
+    Effects(Handle, Vec<(EffectKind, f32, f32)>),
+    // Tells the client how many seconds until something happens, e.g an item spawning,
+    // out of how long the wait is in total. 0 seconds if it isn't counting down
+    Countdown(Handle, f32, f32),
 }
 
 /// A trait which allows easy routing of events into other methods that want to deal with them
@@ -67,15 +82,22 @@ pub trait EventListener {
             Event::Custom(kind, data) => self.on_custom(conn_index, kind, data),
             Event::Yield(handle) => self.on_yield(conn_index, handle),
             Event::Spawn(handle, mesh_type) => self.on_spawn(conn_index, handle, mesh_type),
-            Event::PickUp(handle, mesh_type) => self.on_pick_up(conn_index, handle, mesh_type),
+            Event::Inventory(handle, selected, slots) => self.on_inventory(conn_index, handle, selected, slots),
             Event::Despawn(handle) => self.on_despawn(conn_index, handle),
             Event::KeyDown(key_code) => self.on_key_down(conn_index, key_code),
             Event::KeyUp(key_code) => self.on_key_up(conn_index, key_code),
-            Event::Health(handle, health) => self.on_health(conn_index, handle, health),
+            Event::Health(handle, health, max_health) => self.on_health(conn_index, handle, health, max_health),
             Event::Color(handle, color) => self.on_color(conn_index, handle, color),
             Event::Dimension(handle, width, height) => self.on_dimension(conn_index, handle, width, height),
             Event::GameOver => self.on_game_over(conn_index),
-            Event::Map(map_index) => self.on_map(conn_index, map_index)
+            Event::Map(map_index) => self.on_map(conn_index, map_index),
+            Event::Hitbox(handle, shapes) => self.on_hitbox(conn_index, handle, shapes),
+            Event::Progress(handle, progress) => self.on_progress(conn_index, handle, progress),
+            Event::Weapon(handle, ammo, magazine, until_ready, wait) => {
+                self.on_weapon(conn_index, handle, ammo, magazine, until_ready, wait)
+            }
+            Event::Effects(handle, effects) => self.on_effects(conn_index, handle, effects),
+            Event::Countdown(handle, remaining, total) => self.on_countdown(conn_index, handle, remaining, total),
         }
     }
 
@@ -96,13 +118,18 @@ pub trait EventListener {
     fn on_custom(&mut self, _conn_index: usize, _kind: u32, _data: Vec<u8>) {}
     fn on_yield(&mut self, _conn_index: usize, _handle: Handle) {}
     fn on_spawn(&mut self, _conn_index: usize, _handle: Handle, _mesh_type: MeshType) {}
-    fn on_pick_up(&mut self, _conn_index: usize, _handle: Handle, _mesh_type: MeshType) {}
+    fn on_inventory(&mut self, _conn_index: usize, _handle: Handle, _selected: usize, _slots: Vec<(MeshType, u16)>) {}
     fn on_despawn(&mut self, _conn_index: usize, _handle: Handle) {}
     fn on_key_up(&mut self, _conn_index: usize, _key_code: KeyCode) {}
     fn on_key_down(&mut self, _conn_index: usize, _key_code: KeyCode) {}
-    fn on_health(&mut self, _conn_index: usize, _handle: Handle, _health: u8) {}
+    fn on_health(&mut self, _conn_index: usize, _handle: Handle, _health: u16, _max_health: u16) {}
     fn on_color(&mut self, _conn_index: usize, _handle: Handle, _color: Color) {}
     fn on_dimension(&mut self, _conn_index: usize, _handle: Handle, _width: f32, _height: f32) {}
     fn on_game_over(&mut self, _conn_index: usize) {}
     fn on_map(&mut self, _conn_index: usize, _map_index: usize) {}
+    fn on_hitbox(&mut self, _conn_index: usize, _handle: Handle, _shapes: Vec<Shape>) {}
+    fn on_progress(&mut self, _conn_index: usize, _handle: Handle, _progress: f32) {}
+    fn on_weapon(&mut self, _conn_index: usize, _handle: Handle, _ammo: u16, _magazine: u16, _until_ready: f32, _wait: f32) {}
+    fn on_effects(&mut self, _conn_index: usize, _handle: Handle, _effects: Vec<(EffectKind, f32, f32)>) {}
+    fn on_countdown(&mut self, _conn_index: usize, _handle: Handle, _remaining: f32, _total: f32) {}
 }
@@ -1,5 +1,6 @@
 #![allow(deprecated, unused)]
 
+use crate::game::ecs::{EffectKind, Shape, ALL_EFFECT_KINDS};
 use crate::game::graphics::MeshType;
 use crate::misc::constants::{ALL_KEYS, ALL_MESH_TYPES, KEY_INDEX_MAP, MESH_INDEX_MAP};
 use crate::net::{Event, Handle};
@@ -29,15 +30,22 @@ impl Protocol for SmartProtocol {
             Event::Custom(kind, data) => Self::encode_custom(*kind, data),
             Event::Yield(handle) => Self::encode_yield(*handle),
             Event::Spawn(handle, mesh_type) => Self::encode_spawn(*handle, *mesh_type),
-            Event::PickUp(handle, mesh_type) => Self::encode_pick_up(*handle, *mesh_type),
+            Event::Inventory(handle, selected, slots) => Self::encode_inventory(*handle, *selected, slots),
             Event::Despawn(handle) => Self::encode_despawn(*handle),
             Event::KeyDown(key_code) => Self::encode_key_down(*key_code),
             Event::KeyUp(key_code) => Self::encode_key_up(*key_code),
-            Event::Health(handle, health) => Self::encode_health(*handle, *health),
+            Event::Health(handle, health, max_health) => Self::encode_health(*handle, *health, *max_health),
             Event::Color(handle, color) => Self::encode_color(*handle, *color),
             Event::Dimension(handle, width, height) => Self::encode_dimension(*handle, *width, *height),
             Event::GameOver => Self::encode_game_over(),
-            Event::Map(map_index) => Self::encode_map(*map_index)
+            Event::Map(map_index) => Self::encode_map(*map_index),
+            Event::Hitbox(handle, shapes) => Self::encode_hitbox(*handle, shapes),
+            Event::Progress(handle, progress) => Self::encode_progress(*handle, *progress),
+            Event::Weapon(handle, ammo, magazine, until_ready, wait) => {
+                Self::encode_weapon(*handle, *ammo, *magazine, *until_ready, *wait)
+            }
+            Event::Effects(handle, effects) => Self::encode_effects(*handle, effects),
+            Event::Countdown(handle, remaining, total) => Self::encode_countdown(*handle, *remaining, *total),
         }
     }
 
@@ -70,8 +78,8 @@ impl SmartProtocol {
             b'Y' => Self::decode_yield(data),
             // P is for ...sPawn?
             b'P' => Self::decode_spawn(data),
-            // p is for pick up
-            b'p' => Self::decode_pick_up(data),
+            // I is for Inventory
+            b'I' => Self::decode_inventory(data),
             // d is for down
             b'd' => Self::decode_key_down(data),
             // u is for up
@@ -88,6 +96,16 @@ impl SmartProtocol {
             b'g' => Self::decode_game_over(data),
             // L is for Level
             b'L' => Self::decode_map(data),
+            // X is for hitboX
+            b'X' => Self::decode_hitbox(data),
+            // G is for Gauge
+            b'G' => Self::decode_progress(data),
+            // W is for Weapon
+            b'W' => Self::decode_weapon(data),
+            // E is for Effects
+            b'E' => Self::decode_effects(data),
+            // T is for Timer
+            b'T' => Self::decode_countdown(data),
             // _ is for unsupported or invalid
             _ => None,
         }
@@ -248,28 +266,39 @@ impl SmartProtocol {
         bytes
     }
 
-    fn decode_pick_up(data: &[u8]) -> Option<Event> {
-        if data.len() == size_of::<Handle>() + size_of::<usize>() {
-            let handle = unsigned_from_bytes(&data[..size_of::<Handle>()]) as Handle;
-            // Meshes are serialized using an array, look up which index contains this mesh,
-            // then send the index
-            let mesh_index = unsigned_from_bytes(&data[size_of::<Handle>()..]) as usize;
-            let mesh_type = ALL_MESH_TYPES[mesh_index];
-            Some(Event::PickUp(handle, mesh_type))
-        } else {
-            None
+    fn decode_inventory(data: &[u8]) -> Option<Event> {
+        const HANDLE_SIZE: usize = size_of::<Handle>();
+        // The index of the mesh and how many items there are
+        const SLOT_SIZE: usize = 1 + size_of::<u16>();
+        if data.len() < HANDLE_SIZE + 1 || !(data.len() - HANDLE_SIZE - 1).is_multiple_of(SLOT_SIZE) {
+            return None;
         }
-    }
-
-    fn encode_pick_up(handle: Handle, mesh_type: MeshType) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + size_of::<usize>());
-        let mesh_index = MESH_INDEX_MAP.get(&mesh_type).expect(&format!(
-            "Critical protocol failure. Missing mesh_type {:?} in MESH_INDEX_MAP",
-            mesh_type
-        ));
-        bytes.push(b'p');
+        let handle = unsigned_from_bytes(&data[..HANDLE_SIZE]) as Handle;
+        let selected = data[HANDLE_SIZE] as usize;
+        let slots: Option<Vec<(MeshType, u16)>> = data[HANDLE_SIZE + 1..]
+            .chunks(SLOT_SIZE)
+            .map(|slot| {
+                let mesh_type = *ALL_MESH_TYPES.get(slot[0] as usize)?;
+                Some((mesh_type, unsigned_from_bytes(&slot[1..]) as u16))
+            })
+            .collect();
+        Some(Event::Inventory(handle, selected, slots?))
+    }
+
+    fn encode_inventory(handle: Handle, selected: usize, slots: &[(MeshType, u16)]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + size_of::<Handle>() + slots.len() * (1 + size_of::<u16>()));
+        bytes.push(b'I');
         bytes.append(&mut u64_to_bytes(handle));
-        bytes.append(&mut usize_to_bytes(*mesh_index));
+        bytes.push(selected.min(255) as u8);
+        // Inventories don't get anywhere near 256 slots
+        for (mesh_type, count) in slots.iter().take(255) {
+            let mesh_index = MESH_INDEX_MAP.get(mesh_type).expect(&format!(
+                "Critical protocol failure. Missing mesh_type {:?} in MESH_INDEX_MAP",
+                mesh_type
+            ));
+            bytes.push(*mesh_index as u8);
+            bytes.extend_from_slice(&count.to_be_bytes());
+        }
         bytes
     }
 
@@ -336,20 +365,22 @@ impl SmartProtocol {
     }
 
     fn decode_health(data: &[u8]) -> Option<Event> {
-        if data.len() == size_of::<Handle>() + size_of::<u8>() {
+        if data.len() == size_of::<Handle>() + 2 * size_of::<u16>() {
             let handle = unsigned_from_bytes(&data[..8]) as Handle;
-            let health = unsigned_from_bytes(&data[8..]) as u8;
-            Some(Event::Health(handle, health))
+            let health = unsigned_from_bytes(&data[8..10]) as u16;
+            let max_health = unsigned_from_bytes(&data[10..]) as u16;
+            Some(Event::Health(handle, health, max_health))
         } else {
             None
         }
     }
 
-    fn encode_health(handle: Handle, health: u8) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + size_of::<u8>());
+    fn encode_health(handle: Handle, health: u16, max_health: u16) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + 2 * size_of::<u16>());
         bytes.push(b'H');
         bytes.append(&mut u64_to_bytes(handle));
-        bytes.push(health);
+        bytes.extend_from_slice(&health.to_be_bytes());
+        bytes.extend_from_slice(&max_health.to_be_bytes());
         bytes
     }
 
@@ -427,6 +458,167 @@ impl SmartProtocol {
         bytes.append(&mut usize_to_bytes(map_index));
         bytes
     }
+
+    // Each shape is a byte saying which kind it is followed by its floats,
+    // polygons also have a byte saying how many points they have
+    fn decode_hitbox(data: &[u8]) -> Option<Event> {
+        const HANDLE_SIZE: usize = size_of::<Handle>();
+        if data.len() < HANDLE_SIZE {
+            return None;
+        }
+        let handle = unsigned_from_bytes(&data[..HANDLE_SIZE]) as Handle;
+        let mut shapes = Vec::new();
+        let mut rest = &data[HANDLE_SIZE..];
+        while !rest.is_empty() {
+            let kind = rest[0];
+            rest = &rest[1..];
+            let shape = match kind {
+                b'c' => {
+                    let f = read_floats(&mut rest, 3)?;
+                    Shape::Circle(f[0], f[1], f[2])
+                }
+                b'r' => {
+                    let f = read_floats(&mut rest, 4)?;
+                    Shape::Rect(f[0], f[1], f[2], f[3])
+                }
+                b'p' if !rest.is_empty() => {
+                    let point_count = rest[0] as usize;
+                    rest = &rest[1..];
+                    let f = read_floats(&mut rest, 2 * point_count)?;
+                    Shape::Polygon(f.chunks(2).map(|point| (point[0], point[1])).collect())
+                }
+                _ => return None,
+            };
+            shapes.push(shape);
+        }
+        Some(Event::Hitbox(handle, shapes))
+    }
+
+    fn encode_hitbox(handle: Handle, shapes: &[Shape]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push(b'X');
+        bytes.append(&mut u64_to_bytes(handle));
+        for shape in shapes {
+            match shape {
+                Shape::Circle(x, y, radius) => {
+                    bytes.push(b'c');
+                    for float in &[*x, *y, *radius] {
+                        bytes.append(&mut f32_to_bytes(*float));
+                    }
+                }
+                Shape::Rect(x, y, width, height) => {
+                    bytes.push(b'r');
+                    for float in &[*x, *y, *width, *height] {
+                        bytes.append(&mut f32_to_bytes(*float));
+                    }
+                }
+                Shape::Polygon(points) => {
+                    // More than 255 points is a bit much for a hitbox anyway
+                    bytes.push(b'p');
+                    bytes.push(points.len().min(255) as u8);
+                    for (x, y) in points.iter().take(255) {
+                        bytes.append(&mut f32_to_bytes(*x));
+                        bytes.append(&mut f32_to_bytes(*y));
+                    }
+                }
+            }
+        }
+        bytes
+    }
+
+    fn decode_progress(data: &[u8]) -> Option<Event> {
+        if data.len() == size_of::<Handle>() + size_of::<f32>() {
+            let handle = unsigned_from_bytes(&data[..8]) as Handle;
+            let progress = f32_from_bytes(&data[8..]);
+            Some(Event::Progress(handle, progress))
+        } else {
+            None
+        }
+    }
+
+    fn encode_progress(handle: Handle, progress: f32) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + size_of::<f32>());
+        bytes.push(b'G');
+        bytes.append(&mut u64_to_bytes(handle));
+        bytes.append(&mut f32_to_bytes(progress));
+        bytes
+    }
+
+    fn decode_weapon(data: &[u8]) -> Option<Event> {
+        const HANDLE_SIZE: usize = size_of::<Handle>();
+        const AMMO_SIZE: usize = size_of::<u16>();
+        if data.len() != HANDLE_SIZE + 2 * AMMO_SIZE + 2 * size_of::<f32>() {
+            return None;
+        }
+        let handle = unsigned_from_bytes(&data[..HANDLE_SIZE]) as Handle;
+        let ammo = unsigned_from_bytes(&data[HANDLE_SIZE..HANDLE_SIZE + AMMO_SIZE]) as u16;
+        let magazine = unsigned_from_bytes(&data[HANDLE_SIZE + AMMO_SIZE..HANDLE_SIZE + 2 * AMMO_SIZE]) as u16;
+        let mut rest = &data[HANDLE_SIZE + 2 * AMMO_SIZE..];
+        let times = read_floats(&mut rest, 2)?;
+        Some(Event::Weapon(handle, ammo, magazine, times[0], times[1]))
+    }
+
+    fn encode_weapon(handle: Handle, ammo: u16, magazine: u16, until_ready: f32, wait: f32) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + 2 * size_of::<u16>() + 2 * size_of::<f32>());
+        bytes.push(b'W');
+        bytes.append(&mut u64_to_bytes(handle));
+        bytes.extend_from_slice(&ammo.to_be_bytes());
+        bytes.extend_from_slice(&magazine.to_be_bytes());
+        bytes.append(&mut f32_to_bytes(until_ready));
+        bytes.append(&mut f32_to_bytes(wait));
+        bytes
+    }
+
+    fn decode_effects(data: &[u8]) -> Option<Event> {
+        const HANDLE_SIZE: usize = size_of::<Handle>();
+        if data.len() < HANDLE_SIZE {
+            return None;
+        }
+        let handle = unsigned_from_bytes(&data[..HANDLE_SIZE]) as Handle;
+        let mut effects = Vec::new();
+        let mut rest = &data[HANDLE_SIZE..];
+        while !rest.is_empty() {
+            let kind = *ALL_EFFECT_KINDS.get(rest[0] as usize)?;
+            rest = &rest[1..];
+            let times = read_floats(&mut rest, 2)?;
+            effects.push((kind, times[0], times[1]));
+        }
+        Some(Event::Effects(handle, effects))
+    }
+
+    fn encode_effects(handle: Handle, effects: &[(EffectKind, f32, f32)]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + effects.len() * (1 + 2 * size_of::<f32>()));
+        bytes.push(b'E');
+        bytes.append(&mut u64_to_bytes(handle));
+        for (kind, remaining, duration) in effects {
+            // There's only a handful of kinds, a byte is plenty
+            let kind_index = ALL_EFFECT_KINDS.iter().position(|other| other == kind).unwrap_or_default();
+            bytes.push(kind_index as u8);
+            bytes.append(&mut f32_to_bytes(*remaining));
+            bytes.append(&mut f32_to_bytes(*duration));
+        }
+        bytes
+    }
+
+    fn decode_countdown(data: &[u8]) -> Option<Event> {
+        const HANDLE_SIZE: usize = size_of::<Handle>();
+        if data.len() != HANDLE_SIZE + 2 * size_of::<f32>() {
+            return None;
+        }
+        let handle = unsigned_from_bytes(&data[..HANDLE_SIZE]) as Handle;
+        let mut rest = &data[HANDLE_SIZE..];
+        let times = read_floats(&mut rest, 2)?;
+        Some(Event::Countdown(handle, times[0], times[1]))
+    }
+
+    fn encode_countdown(handle: Handle, remaining: f32, total: f32) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + size_of::<Handle>() + 2 * size_of::<f32>());
+        bytes.push(b'T');
+        bytes.append(&mut u64_to_bytes(handle));
+        bytes.append(&mut f32_to_bytes(remaining));
+        bytes.append(&mut f32_to_bytes(total));
+        bytes
+    }
 }
 
 fn u32_to_bytes(number: u32) -> Vec<u8> {
@@ -449,6 +641,20 @@ fn f32_to_bytes(number: f32) -> Vec<u8> {
     number.to_be_bytes().to_vec()
 }
 
+/// Reads a number of floats off the front of some data, None if there aren't enough
+fn read_floats(data: &mut &[u8], count: usize) -> Option<Vec<f32>> {
+    const FLOAT_SIZE: usize = size_of::<f32>();
+    if data.len() < count * FLOAT_SIZE {
+        return None;
+    }
+    let floats = data[..count * FLOAT_SIZE]
+        .chunks(FLOAT_SIZE)
+        .map(f32_from_bytes)
+        .collect();
+    *data = &data[count * FLOAT_SIZE..];
+    Some(floats)
+}
+
 fn unsigned_from_bytes(bytes: &[u8]) -> u128 {
     let mut unsigned = 0;
     for byte in bytes {
@@ -0,0 +1,745 @@
+use crate::game::ecs::{hitbox_contact, GameRng, aim_point, decide, predict_hit, AiState, Difficulty, Perception, Projectile, Sighting, NPC, MapBounds, NavGrid, ricochet, Contact, Ricochet, RicochetRule, Owner, ItemSpawner, Placement, Effect, EffectKind, StatusEffects, Armor, InventoryComponent, PickUpComponent, STACK_SIZE, DamageType, Invulnerable, Weapon, WeaponStats, HITSCAN, DamageSystem, ColorComponent, ControlComponent, prefabs, system_id, ComponentRegistry, Entity, Hitbox, Shape, Physics, Velocity, CollisionClass, CollisionComponent, CollisionFilter, SpatialIndex, ALL_LAYERS, TANK_LAYER, WALL_LAYER, GameEvent, GameEventBus, GameEventReader, ScoreBoard, Health, Position, Scheduler, Stage, System, SystemId};
+use crate::game::ecs::prefabs::{parse_map, PrefabArgs, PrefabLibrary};
+use crate::misc::constants::{MAP_DIRECTORY, PREFAB_DIRECTORY};
+use ggez::graphics::Color;
+use crate::game::{Server, ServerContext};
+use crate::net::{Handle, SmartProtocol};
+use crate::game::graphics::MeshType;
+
+// Some do-nothing systems with different ordering constraints
+struct Replicate;
+struct Move;
+struct Collide;
+struct Steer;
+struct Reap;
+
+impl System for Replicate {
+    fn update(&mut self, _entities: &mut [Entity], _ctx: &mut ServerContext) {}
+    fn stage(&self) -> Stage {
+        Stage::Replication
+    }
+}
+
+impl System for Move {
+    fn update(&mut self, _entities: &mut [Entity], _ctx: &mut ServerContext) {}
+}
+
+impl System for Collide {
+    fn update(&mut self, _entities: &mut [Entity], _ctx: &mut ServerContext) {}
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<Move>()]
+    }
+}
+
+impl System for Steer {
+    fn update(&mut self, _entities: &mut [Entity], _ctx: &mut ServerContext) {}
+    fn after(&self) -> Vec<SystemId> {
+        vec![system_id::<Collide>(), system_id::<Replicate>()]
+    }
+}
+
+impl System for Reap {
+    fn update(&mut self, _entities: &mut [Entity], _ctx: &mut ServerContext) {}
+    fn stage(&self) -> Stage {
+        Stage::Cleanup
+    }
+    fn before(&self) -> Vec<SystemId> {
+        vec![system_id::<Replicate>()]
+    }
+}
+
+fn short_names(names: Vec<&'static str>) -> Vec<&'static str> {
+    names.into_iter().map(|name| name.rsplit("::").next().unwrap()).collect()
+}
+
+#[test]
+fn scheduler_orders_by_stage_and_constraints() {
+    let mut scheduler = Scheduler::new();
+    scheduler.register(Replicate).register(Move).register(Collide);
+    let schedule = scheduler.build().unwrap();
+    assert_eq!(vec!["Collide", "Move", "Replicate"], short_names(schedule.system_names()));
+}
+
+#[test]
+fn scheduler_ignores_unregistered_constraints() {
+    // Reap runs before Replicate and Steer after it, but Replicate isn't around.
+    // Steer still has to wait for Collide, which is
+    let mut scheduler = Scheduler::new();
+    scheduler.register(Reap).register(Steer).register(Move).register(Collide);
+    let schedule = scheduler.build().unwrap();
+    assert_eq!(vec!["Collide", "Steer", "Move", "Reap"], short_names(schedule.system_names()));
+}
+
+#[test]
+fn scheduler_rejects_contradicting_stages() {
+    let mut scheduler = Scheduler::new();
+    scheduler.register(Replicate).register(Collide).register(Steer);
+    assert!(scheduler.build().is_err());
+    let mut scheduler = Scheduler::new();
+    scheduler.register(Replicate).register(Reap);
+    assert!(scheduler.build().is_err());
+}
+
+#[test]
+fn scheduler_rejects_duplicates() {
+    let mut scheduler = Scheduler::new();
+    scheduler.register(Move).register(Move);
+    assert!(scheduler.build().is_err());
+}
+
+#[test]
+fn game_event_bus_delivers_each_event_once() {
+    let mut bus = GameEventBus::new();
+    let mut early_reader = GameEventReader::new();
+    let mut late_reader = GameEventReader::new();
+    // The early reader runs before anything is published this tick
+    assert!(bus.read(&mut early_reader).is_empty());
+    bus.publish(GameEvent::Killed(1, 2));
+    assert_eq!(vec![GameEvent::Killed(1, 2)], bus.read(&mut late_reader));
+    bus.end_tick();
+    // Next tick the early reader catches up, while the late reader has nothing new
+    assert_eq!(vec![GameEvent::Killed(1, 2)], bus.read(&mut early_reader));
+    assert!(bus.read(&mut late_reader).is_empty());
+    bus.end_tick();
+    // By now the event is forgotten
+    assert!(bus.read(&mut GameEventReader::new()).is_empty());
+}
+
+#[test]
+fn resources_last_until_the_state_is_purged() {
+    struct Marker(u32);
+    let mut server = Server::<SmartProtocol>::headless().unwrap();
+    // The defaults are there before any match has started
+    assert!(server.resource::<ScoreBoard>().is_some());
+    assert!(server.resource::<GameRng>().is_some());
+    server.insert_resource(Marker(7));
+    server.call_systems();
+    server.call_systems();
+    assert_eq!(server.resource::<Marker>().map(|marker| marker.0), Some(7));
+    server.purge_state();
+    assert!(server.resource::<Marker>().is_none());
+    assert!(server.resource::<ScoreBoard>().is_some());
+}
+
+// A world with a wounded tank carrying a heal item, and a wall
+const WORLD_FIXTURE: &str = "\
+# comments and empty lines are fine
+
+entity 1
+Mesh Tank
+Position 800 250 180
+Velocity 0 0 0
+Physics 10 0.9
+Health 20 50
+Armor 10 25 0.5
+Invulnerable 1.5
+Color 1 0 0 1
+Inventory 4 1 0 2 Heal heal_item(0.5)
+Collision tank 1 player_collision
+Control 0 player_control(1000,180)
+Death player_death
+Weapon bullet 800 1 0.8 30 5 6 2 false 4
+Effects shield 0.5 2.5 8 slow 0.5 1 1
+end
+entity 7
+Mesh Wall
+Position 250 200 0
+Color 0 0 0 1
+Scale 10 100
+Collision wall 10 100 wall_collision
+end
+";
+
+#[test]
+fn world_fixture_loads_and_saves_unchanged() {
+    let registry = ComponentRegistry::with_game_components();
+    let entities = registry.load_entities(WORLD_FIXTURE, &prefabs::script_library()).unwrap();
+    assert_eq!(entities.len(), 2);
+    assert_eq!(entities[0].get_handle(), 1);
+    assert_eq!(entities[0].get_component::<Health>().unwrap().get_health(), 20);
+    assert_eq!(entities[1].get_component::<Position>().unwrap().get_x(), 250.0);
+    let saved = registry.save_entities(&entities).unwrap();
+    let fixture_entities = WORLD_FIXTURE.lines().skip_while(|line| !line.starts_with("entity"));
+    assert!(saved.lines().eq(fixture_entities));
+}
+
+// Something out of the prefabs in assets/prefabs, the only place anything the game spawns is defined
+fn prefab(name: &str, handle: Handle, args: PrefabArgs) -> Entity {
+    PrefabLibrary::load_directory(PREFAB_DIRECTORY).unwrap().instantiate(name, handle, &args).unwrap()
+}
+
+fn at(x: f32, y: f32) -> PrefabArgs {
+    PrefabArgs::new().with("x", x).with("y", y)
+}
+
+fn wall(handle: Handle, x: f32, y: f32, w: f32, h: f32) -> Entity {
+    prefab("wall", handle, at(x, y).with("w", w).with("h", h))
+}
+
+fn tank(handle: Handle, x: f32, y: f32, angle: f32) -> Entity {
+    prefab("player", handle, at(x, y).with("angle", angle))
+}
+
+// The weapon a prefab comes with, e.g the cannon of a tank or whatever's in a weapon pickup
+fn weapon_of(name: &str) -> WeaponStats {
+    prefab(name, 1, PrefabArgs::new()).get_component::<Weapon>().unwrap().get_stats().clone()
+}
+
+#[test]
+fn prefabs_survive_a_round_trip() {
+    let library = PrefabLibrary::load_directory(PREFAB_DIRECTORY).unwrap();
+    let registry = ComponentRegistry::with_game_components();
+    let mut names: Vec<&str> = library.names().collect();
+    names.sort_unstable();
+    for (handle, name) in names.into_iter().enumerate() {
+        let entity = library.instantiate(name, handle as Handle, &PrefabArgs::new()).unwrap();
+        let saved = registry.save_entities(&[entity]).unwrap();
+        let loaded = registry.load_entities(&saved, &prefabs::script_library()).unwrap();
+        assert_eq!(registry.save_entities(&loaded).unwrap(), saved, "{} changes when it's loaded", name);
+    }
+}
+
+#[test]
+fn loading_reports_bad_lines() {
+    let registry = ComponentRegistry::with_game_components();
+    let result = registry.load_entities("entity 1\nPosition 1 2\nend\n", &prefabs::script_library());
+    assert_eq!(result.err().unwrap(), "Line 2: Invalid Position: Position 1 2");
+    let result = registry.load_entities("entity 1\nControl 0 no_such_script\nend\n", &prefabs::script_library());
+    assert!(result.is_err());
+}
+
+#[test]
+fn prefab_parameters_are_substituted() {
+    let mut library = PrefabLibrary::new();
+    library
+        .add_prefabs("prefab thing\nparam x 1\nparam xy 2\nPosition $x $xy 0\nTimeToLive 3\nend\n")
+        .unwrap();
+    let thing = library.instantiate("thing", 4, &PrefabArgs::new().with("xy", 5)).unwrap();
+    assert_eq!(thing.get_handle(), 4);
+    let position = thing.get_component::<Position>().unwrap();
+    assert_eq!((position.get_x(), position.get_y()), (1.0, 5.0));
+    assert!(library.instantiate("thing", 4, &PrefabArgs::new().with("z", 5)).is_err());
+    assert!(library.instantiate("nothing", 4, &PrefabArgs::new()).is_err());
+}
+
+#[test]
+fn prefabs_take_their_arguments() {
+    let color = Color::new(1.0, 0.0, 0.0, 1.0);
+    let player = prefab("player", 2, at(50.0, 250.0).with("input", 1).with("angle", 90).with_color(color));
+    assert_eq!(player.get_handle(), 2);
+    let position = player.get_component::<Position>().unwrap();
+    assert_eq!((position.get_x(), position.get_y(), position.get_angle()), (50.0, 250.0, 90.0));
+    assert_eq!(player.get_component::<ColorComponent>().unwrap().get_color(), color);
+    assert_eq!(player.get_component::<ControlComponent>().unwrap().get_input_device_index(), 1);
+    // Bullets belong to whoever shot them
+    let bullet = prefab("bullet", 3, at(0.0, 0.0).with("shooter", 2));
+    let collision = bullet.get_component::<CollisionComponent>().unwrap();
+    assert_eq!(collision.get_collision_class(), CollisionClass::Bullet(2));
+    let npc = prefab("npc", 4, at(10.0, 20.0).with("difficulty", "hard"));
+    assert_eq!(npc.get_component::<NPC>().unwrap().get_difficulty(), &Difficulty::hard());
+    assert_eq!(npc.get_component::<NPC>().unwrap().get_target_point(), (10.0, 20.0));
+    let barrier = prefab("barrier", 5, PrefabArgs::new().with("owner", 1).with("team", 2).with("health", 40));
+    assert_eq!(barrier.get_component::<Owner>(), Some(&Owner::new(1, 2)));
+    assert_eq!(barrier.get_component::<Health>().unwrap().get_health(), 40);
+}
+
+#[test]
+fn asset_maps_only_spawn_known_prefabs() {
+    let library = PrefabLibrary::load_directory(PREFAB_DIRECTORY).unwrap();
+    for map in &["map1", "map2", "border"] {
+        let text = std::fs::read_to_string(format!("{}/{}.map", MAP_DIRECTORY, map)).unwrap();
+        for spawn in parse_map(&text).unwrap() {
+            library.instantiate(&spawn.prefab, 10, &spawn.args).unwrap();
+        }
+    }
+}
+
+#[test]
+fn hitboxes_rotate_with_their_entity() {
+    // A bullet just in front of the barrel, which points right unless rotated
+    let tank = Hitbox::tank();
+    let bullet = Hitbox::bullet().place(38.0, 0.0, 0.0);
+    assert!(hitbox_contact(&tank.place(0.0, 0.0, 0.0), &bullet).is_some());
+    assert!(hitbox_contact(&tank.place(0.0, 0.0, 180.0), &bullet).is_none());
+    // Rotated a bit, the corner of the barrel still reaches it
+    assert!(hitbox_contact(&tank.place(0.0, 0.0, 20.0), &bullet).is_some());
+}
+
+#[test]
+fn hitbox_contacts_point_from_first_to_second() {
+    let wall = Hitbox::wall(10.0, 100.0).place(100.0, 0.0, 0.0);
+    // A tank overlapping the wall by 5 px from the left
+    let tank = Hitbox::new(vec![Shape::Circle(0.0, 0.0, 25.0)]).place(80.0, 50.0, 0.0);
+    let contact = hitbox_contact(&tank, &wall).unwrap();
+    assert!((contact.normal.0 - 1.0).abs() < 1e-4 && contact.normal.1.abs() < 1e-4);
+    assert!((contact.depth - 5.0).abs() < 1e-4);
+    let contact = hitbox_contact(&wall, &tank).unwrap();
+    assert!((contact.normal.0 + 1.0).abs() < 1e-4);
+    // Diagonally off the corner the circle doesn't reach, even though its bounding box would
+    let tank = Hitbox::new(vec![Shape::Circle(0.0, 0.0, 25.0)]).place(82.0, -18.0, 0.0);
+    assert!(hitbox_contact(&tank, &wall).is_none());
+}
+
+#[test]
+fn hitboxes_load_polygons_within_reason() {
+    let registry = ComponentRegistry::with_game_components();
+    let scripts = prefabs::script_library();
+    let mut entity = Entity::new(1);
+    registry.load_component(&mut entity, "Hitbox polygon 3 0 0 10 0 0 10 circle 0 0 5", &scripts).unwrap();
+    assert_eq!(
+        entity.get_component::<Hitbox>().unwrap().get_shapes(),
+        &[Shape::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]), Shape::Circle(0.0, 0.0, 5.0)][..]
+    );
+    // Point counts that don't fit in memory, or would overflow, are refused rather than trusted
+    assert!(registry.load_component(&mut entity, "Hitbox polygon 1000 0 0", &scripts).is_err());
+    let overflowing = format!("Hitbox polygon {} 0 0", usize::MAX / 2 + 1);
+    assert!(registry.load_component(&mut entity, &overflowing, &scripts).is_err());
+}
+
+#[test]
+fn heal_item_hitbox_covers_its_mesh() {
+    let item = Hitbox::heal_item().place(100.0, 100.0, 0.0);
+    let point = |x, y| Hitbox::new(vec![Shape::Circle(x, y, 0.5)]).place(0.0, 0.0, 0.0);
+    // The middle of the cross, not its anchor
+    assert!(hitbox_contact(&point(115.0, 115.0), &item).is_some());
+    assert!(hitbox_contact(&point(100.0, 100.0), &item).is_none());
+}
+
+#[test]
+fn physics_forces_accelerate_by_mass_and_drag_slows_down() {
+    let mut physics = Physics::new(10.0, 0.5);
+    let mut velocity = Velocity::new(0.0, 0.0, 0.0);
+    physics.apply_force(100.0, 0.0);
+    physics.apply_impulse(0.0, -20.0);
+    physics.accelerate(&mut velocity, 0.5);
+    assert_eq!(velocity.get_velocity(), (5.0, -2.0));
+    // The force only lasts for the tick it was applied in
+    physics.accelerate(&mut velocity, 0.5);
+    assert_eq!(velocity.get_velocity(), (5.0, -2.0));
+    // Losing half the speed per second, so a quarter over two
+    physics.slow_down(&mut velocity, 2.0);
+    assert_eq!(velocity.get_velocity(), (1.25, -0.5));
+}
+
+#[test]
+fn collision_filters_keep_the_old_class_rules() {
+    let filter = |class| CollisionFilter::from_class(class);
+    let tank = filter(CollisionClass::Tank(1));
+    let own_bullet = filter(CollisionClass::Bullet(1));
+    let other_bullet = filter(CollisionClass::Bullet(2));
+    let wall = filter(CollisionClass::Wall(10.0, 10.0));
+    assert!(!tank.can_collide(&own_bullet));
+    assert!(tank.can_collide(&other_bullet));
+    assert!(own_bullet.can_collide(&other_bullet));
+    assert!(!wall.can_collide(&wall));
+    assert!(wall.can_collide(&filter(CollisionClass::Item)));
+}
+
+#[test]
+fn team_bullets_pass_through_teammates_but_tanks_bump() {
+    let tank = CollisionFilter::from_class(CollisionClass::Tank(1)).with_team(3, false);
+    let teammate = CollisionFilter::from_class(CollisionClass::Tank(2)).with_team(3, false);
+    let bullet = CollisionFilter::from_class(CollisionClass::Bullet(2));
+    assert!(tank.can_collide(&teammate));
+    assert!(tank.can_collide(&bullet));
+    assert!(!tank.can_collide(&bullet.with_team(3, true)));
+}
+
+#[test]
+fn collision_filters_survive_a_round_trip() {
+    let registry = ComponentRegistry::with_game_components();
+    let mut entity = wall(6, 0.0, 0.0, 20.0, 500.0);
+    let collision = entity.get_component_mut::<CollisionComponent>().unwrap();
+    let mut filter = collision.get_filter().with_team(2, true);
+    filter.mask &= !(1 << 20);
+    collision.set_filter(filter);
+    let saved = registry.save_entities(&[entity]).unwrap();
+    assert!(saved.contains("Collision wall 20 500 wall_collision mask=all-wall-1048576 team=2 ignore_team=true"));
+    let loaded = registry.load_entities(&saved, &prefabs::script_library()).unwrap();
+    assert_eq!(loaded[0].get_component::<CollisionComponent>().unwrap().get_filter(), filter);
+}
+
+// A tank at the origin facing right, a wall in front of it and another tank behind that wall
+fn spatial_fixture() -> SpatialIndex {
+    let mut index = SpatialIndex::new();
+    index.rebuild(&[
+        tank(1, 0.0, 0.0, 0.0),
+        wall(2, 200.0, -100.0, 20.0, 200.0),
+        tank(3, 400.0, 0.0, 180.0),
+    ]);
+    index
+}
+
+#[test]
+fn raycasts_hit_the_first_thing_in_the_way() {
+    let index = spatial_fixture();
+    let hit = index.raycast((0.0, 0.0), 0.0, 1000.0, ALL_LAYERS, 1).unwrap();
+    assert_eq!(hit.handle, 2);
+    assert!((hit.distance - 200.0).abs() < 1e-3);
+    // Looking past walls, or not far enough to reach anything
+    assert_eq!(index.raycast((0.0, 0.0), 0.0, 1000.0, TANK_LAYER, 1).unwrap().handle, 3);
+    assert!(index.raycast((0.0, 0.0), 0.0, 100.0, ALL_LAYERS, 1).is_none());
+    // Nothing at all behind us, even with an endless ray
+    assert!(index.raycast((0.0, 0.0), 180.0, f32::INFINITY, ALL_LAYERS, 1).is_none());
+    assert!(!index.line_of_sight((0.0, 0.0), (400.0, 0.0), WALL_LAYER, 1));
+    assert!(index.line_of_sight((0.0, 0.0), (0.0, 300.0), WALL_LAYER, 1));
+}
+
+#[test]
+fn spatial_queries_find_things_nearby() {
+    let index = spatial_fixture();
+    assert_eq!(index.overlap_circle((190.0, 0.0), 20.0, ALL_LAYERS), vec![2]);
+    assert_eq!(index.overlap_circle((0.0, 0.0), 10.0, WALL_LAYER), Vec::<u64>::new());
+    assert_eq!(index.nearest((0.0, 0.0), 1000.0, TANK_LAYER, 1), Some((3, 400.0)));
+    assert_eq!(index.nearest((0.0, 0.0), 300.0, TANK_LAYER, 1), None);
+}
+
+#[test]
+fn spatial_index_finds_what_overlaps_a_zone() {
+    let index = spatial_fixture();
+    let zone = Hitbox::wall(100.0, 100.0).place(350.0, -50.0, 0.0);
+    assert_eq!(index.overlap(&zone, TANK_LAYER), vec![3]);
+    assert!(index.overlap(&zone, WALL_LAYER).is_empty());
+}
+
+#[test]
+fn weapons_cool_down_run_dry_and_reload() {
+    let mut shotgun = Weapon::new(weapon_of("shotgun_pickup"));
+    assert!(shotgun.pull_trigger(true));
+    // Holding the trigger doesn't do anything for weapons that aren't automatic
+    assert!(!shotgun.pull_trigger(true));
+    shotgun.pull_trigger(false);
+    // Still cooling down
+    shotgun.wait(0.5);
+    assert!(!shotgun.pull_trigger(true));
+    shotgun.pull_trigger(false);
+    shotgun.wait(0.5);
+    assert!(shotgun.pull_trigger(true));
+    assert_eq!(shotgun.get_ammo(), 4);
+    for _ in 0..4 {
+        shotgun.pull_trigger(false);
+        shotgun.wait(1.0);
+        assert!(shotgun.pull_trigger(true));
+    }
+    // The last shot starts a reload all by itself
+    assert_eq!(shotgun.get_ammo(), 0);
+    assert!(shotgun.is_reloading());
+    shotgun.wait(1.0);
+    shotgun.pull_trigger(false);
+    assert!(!shotgun.pull_trigger(true));
+    shotgun.wait(1.0);
+    assert_eq!(shotgun.get_ammo(), 6);
+    assert!(!shotgun.is_reloading());
+
+    // Automatic weapons keep firing while the trigger's held, once they've cooled down
+    let mut machine_gun = Weapon::new(weapon_of("machine_gun_pickup"));
+    assert!(machine_gun.pull_trigger(true));
+    assert!(!machine_gun.pull_trigger(true));
+    machine_gun.wait(0.1);
+    assert!(machine_gun.pull_trigger(true));
+    // Reloading early throws away what's left
+    assert!(machine_gun.reload());
+    machine_gun.wait(2.5);
+    assert_eq!(machine_gun.get_ammo(), 30);
+
+    // The cannon never runs out
+    let mut cannon = Weapon::new(weapon_of("player"));
+    for _ in 0..100 {
+        assert!(cannon.pull_trigger(true));
+        cannon.pull_trigger(false);
+    }
+    assert!(!cannon.reload());
+
+    // Weapons which reload instantly are ready to fire again right away rather than jamming
+    let mut revolver = Weapon::new(weapon_of("player").with_magazine(2, 0.0));
+    assert!(revolver.pull_trigger(true));
+    revolver.pull_trigger(false);
+    assert!(revolver.pull_trigger(true));
+    assert_eq!(revolver.get_ammo(), 2);
+    assert!(!revolver.is_reloading());
+    revolver.pull_trigger(false);
+    assert!(revolver.pull_trigger(true));
+    assert!(revolver.reload());
+    assert_eq!(revolver.get_ammo(), 2);
+    revolver.wait(0.1);
+    assert!(!revolver.is_reloading());
+}
+
+#[test]
+fn hitscan_weapons_hit_the_first_thing_in_line_right_away() {
+    let railgun = weapon_of("railgun_pickup");
+    assert!(railgun.is_hitscan());
+    // A tank at the origin facing right and a wall in front of it
+    let wall_args = at(200.0, -100.0).with("w", 20).with("h", 200).with("health", 30);
+    let mut entities = vec![tank(1, 0.0, 0.0, 0.0), prefab("destructible_wall", 2, wall_args)];
+    let short_range = WeaponStats::new(HITSCAN, 150.0, 1);
+    let mut server = Server::<SmartProtocol>::headless().unwrap();
+    server.with_context(|ctx| {
+        ctx.resource_mut::<SpatialIndex>().unwrap().rebuild(&entities);
+        prefabs::fire(&entities[0], &railgun, ctx);
+        // Not far enough to reach the wall
+        prefabs::fire(&entities[0], &short_range, ctx);
+        DamageSystem.update(&mut entities, ctx);
+    });
+    let health = |entity: &Entity| entity.get_component::<Health>().unwrap().get_health();
+    assert_eq!(health(&entities[1]), 30 - railgun.damage);
+}
+
+#[test]
+fn shots_spread_out_around_the_aim() {
+    let mut rng = GameRng::seeded(0);
+    assert_eq!(weapon_of("shotgun_pickup").shot_angles(90.0, &mut rng), vec![75.0, 82.5, 90.0, 97.5, 105.0]);
+    assert_eq!(weapon_of("player").shot_angles(90.0, &mut rng), vec![90.0]);
+    let machine_gun = weapon_of("machine_gun_pickup");
+    for _ in 0..100 {
+        let angles = machine_gun.shot_angles(90.0, &mut rng);
+        assert_eq!(angles.len(), 1);
+        assert!((angles[0] - 90.0).abs() <= 4.0);
+    }
+}
+
+#[test]
+fn armor_takes_its_share_until_worn_through() {
+    let mut armor = Armor::new(3, 10, 0.5);
+    assert_eq!(armor.absorb(4, DamageType::Bullet), 2);
+    assert_eq!(armor.get_points(), 1);
+    // There's only a single point left to take the 3 it'd take of this blast
+    assert_eq!(armor.absorb(6, DamageType::Explosion), 5);
+    assert_eq!(armor.get_points(), 0);
+    assert_eq!(armor.absorb(4, DamageType::Bullet), 4);
+    armor.set_points(100);
+    assert_eq!(armor.get_points(), 10);
+    // The environment goes right around armor
+    assert_eq!(armor.absorb(4, DamageType::Environment), 4);
+    assert_eq!(armor.get_points(), 10);
+    // Half of a single point is nothing, but it adds up over two hits
+    assert_eq!(armor.absorb(1, DamageType::Bullet), 1);
+    assert_eq!(armor.absorb(1, DamageType::Bullet), 0);
+    assert_eq!(armor.get_points(), 9);
+}
+
+#[test]
+fn tanks_spawn_invulnerable() {
+    let player = tank(1, 800.0, 250.0, 180.0);
+    let invulnerable = player.get_component::<Invulnerable>().unwrap();
+    assert!(invulnerable.is_active());
+    assert!(!Invulnerable::new(0.0).is_active());
+}
+
+fn heal() -> crate::game::ecs::Item {
+    prefab("heal_item", 7, PrefabArgs::new()).get_component::<PickUpComponent>().unwrap().get_item()
+}
+
+#[test]
+fn inventories_stack_items_until_full() {
+    let mut inventory = InventoryComponent::with_slots(2);
+    for _ in 0..2 * STACK_SIZE {
+        assert!(inventory.put_item(heal()));
+    }
+    assert!(!inventory.put_item(heal()));
+    assert_eq!(inventory.contents(), vec![(MeshType::Heal, STACK_SIZE), (MeshType::Heal, STACK_SIZE)]);
+    assert!(inventory.select(1));
+    assert!(!inventory.select(2));
+    assert!(inventory.take_selected().is_some());
+    assert_eq!(inventory.peek_slot(1).unwrap().1, STACK_SIZE - 1);
+    // Stacks only take items of their own kind
+    let registry = ComponentRegistry::with_game_components();
+    let other = registry.load_entities("entity 1\nPickUp Heal heal_item(0.25)\nend\n", &prefabs::script_library()).unwrap();
+    assert!(inventory.put_item(heal()));
+    assert!(!inventory.put_item(other[0].get_component::<PickUpComponent>().unwrap().get_item()));
+}
+
+#[test]
+fn inventories_only_load_slots_they_have() {
+    let registry = ComponentRegistry::with_game_components();
+    let scripts = prefabs::script_library();
+    let mut entity = Entity::new(1);
+    registry.load_component(&mut entity, "Inventory 4 1 3 2 Heal heal_item(0.5)", &scripts).unwrap();
+    let inventory = entity.get_component::<InventoryComponent>().unwrap();
+    assert_eq!((inventory.slot_count(), inventory.get_selected()), (4, 1));
+    assert_eq!(inventory.peek_slot(3).unwrap().1, 2);
+    // A stack in a slot that isn't there is refused rather than lost
+    assert!(registry.load_component(&mut entity, "Inventory 4 0 4 2 Heal heal_item(0.5)", &scripts).is_err());
+    // So is a number of slots nobody could ever select
+    assert!(registry.load_component(&mut entity, "Inventory 4000000000 0", &scripts).is_err());
+}
+
+#[test]
+fn effects_stack_by_their_kind_and_wear_off() {
+    let mut effects = StatusEffects::new();
+    effects.apply(Effect::new(EffectKind::SpeedBoost, 1.5, 5.0));
+    effects.apply(Effect::new(EffectKind::SpeedBoost, 1.2, 5.0));
+    effects.apply(Effect::new(EffectKind::Shield, 0.5, 8.0));
+    effects.apply(Effect::new(EffectKind::Shield, 0.8, 2.0));
+    effects.apply(Effect::new(EffectKind::Slow, 0.5, 1.0));
+    // Speed boosts add up and keep the stronger boost, shields keep the longer and stronger one
+    assert_eq!(effects.get_effects()[0].remaining, 10.0);
+    assert_eq!(effects.get_effects()[0].strength, 1.5);
+    assert_eq!(effects.get_effects()[1].remaining, 8.0);
+    assert_eq!(effects.get_effects()[1].strength, 0.5);
+    assert_eq!(effects.speed_multiplier(), 0.75);
+    assert_eq!(effects.damage_multiplier(), 0.5);
+    assert_eq!(effects.fire_rate_multiplier(), 1.0);
+    assert!(effects.wait(1.0));
+    assert_eq!(effects.speed_multiplier(), 1.5);
+    assert!(!effects.wait(1.0));
+}
+
+#[test]
+fn spawners_pick_by_weight_and_survive_a_round_trip() {
+    let table = vec![("heal_item".to_string(), 0), ("shield_item".to_string(), 3)];
+    let spawner = ItemSpawner::new(table, 10.0, Placement::Area(100.0, 100.0, 200.0, 50.0));
+    // Nothing with a weight of 0 ever gets picked
+    let mut rng = GameRng::seeded(0);
+    assert!((0..20).all(|_| spawner.pick(&mut rng) == Some("shield_item")));
+    assert_eq!(ItemSpawner::new(Vec::new(), 10.0, Placement::Fixed).pick(&mut rng), None);
+    let mut entity = Entity::new(1);
+    entity.put_replicated(spawner.with_state(4.5, 0));
+    let registry = ComponentRegistry::with_game_components();
+    let saved = registry.save_entities(&[entity]).unwrap();
+    assert_eq!(saved, "entity 1\nSpawner 10 4.5 0 area 100 100 200 50 heal_item 0 shield_item 3\nend\n");
+    let loaded = registry.load_entities(&saved, &prefabs::script_library()).unwrap();
+    assert_eq!(registry.save_entities(&loaded).unwrap(), saved);
+}
+
+#[test]
+fn deployables_leave_their_owner_and_team_alone() {
+    let on_team = |handle, team| {
+        let mut tank = tank(handle, 0.0, 0.0, 0.0);
+        let collision = tank.get_component_mut::<CollisionComponent>().unwrap();
+        collision.set_filter(collision.get_filter().with_team(team, true));
+        tank
+    };
+    let owner = Owner::of(&on_team(1, 2));
+    assert_eq!(owner, Owner::new(1, 2));
+    assert!(owner.is_friendly(&on_team(1, 2)));
+    assert!(owner.is_friendly(&on_team(3, 2)));
+    assert!(!owner.is_friendly(&on_team(4, 1)));
+    // Being on nobody's team doesn't make anyone friends
+    let loner = Owner::new(1, 0);
+    assert!(loner.is_friendly(&on_team(1, 0)));
+    assert!(!loner.is_friendly(&on_team(3, 0)));
+}
+
+#[test]
+fn ricochets_reflect_off_walls_until_out_of_bounces() {
+    let mut bullet = prefab("bullet", 1, PrefabArgs::new().with("shooter", 5).with("vx", 100));
+    bullet.put_component(Ricochet::new(1, 0.5));
+    let mut entities = vec![bullet, wall(2, 10.0, -50.0, 10.0, 100.0), tank(3, 0.0, 0.0, 0.0)];
+    let contact = Contact { normal: (1.0, 0.0), depth: 2.0 };
+    // Tanks aren't walls, there's nothing to bounce off
+    assert!(!ricochet(0, 2, contact, &mut entities));
+    assert!(ricochet(0, 1, contact, &mut entities));
+    assert_eq!(entities[0].get_component::<Velocity>().unwrap().get_velocity(), (-50.0, 0.0));
+    let position = entities[0].get_component::<Position>().unwrap();
+    assert_eq!((position.get_x(), position.get_angle()), (-2.0, 180.0));
+    assert_eq!(entities[0].get_component::<Ricochet>().unwrap().get_bounces_left(), 0);
+    assert!(!ricochet(0, 1, contact, &mut entities));
+    // Maps can make every projectile ricochet
+    let library = PrefabLibrary::load_directory(PREFAB_DIRECTORY).unwrap();
+    let rule = library.instantiate("ricochet_rule", 4, &PrefabArgs::new().with("bounces", 3)).unwrap();
+    assert_eq!(rule.get_component::<RicochetRule>().unwrap().0, Ricochet::new(3, 0.2));
+}
+
+#[test]
+fn navigation_finds_a_way_around_walls() {
+    let bounds = MapBounds { width: 200.0, height: 200.0 };
+    let entities = vec![wall(1, 90.0, 0.0, 20.0, 140.0), tank(2, 30.0, 30.0, 0.0)];
+    let grid = NavGrid::build(&entities, bounds, 10.0, 20.0);
+    assert!(grid.is_free_at((30.0, 30.0)));
+    assert!(!grid.is_free_at((100.0, 30.0)));
+    assert!(!grid.clear_line((30.0, 30.0), (170.0, 30.0)));
+    let path = grid.find_path((30.0, 30.0), (170.0, 30.0)).unwrap();
+    assert_eq!(path.last(), Some(&(170.0, 30.0)));
+    // It goes under the wall, in straight lines which stay clear of it
+    assert!(path.iter().any(|waypoint| waypoint.1 > 160.0));
+    let mut from = (30.0, 30.0);
+    for waypoint in path {
+        assert!(grid.clear_line(from, waypoint));
+        from = waypoint;
+    }
+    // No way through a wall from top to bottom
+    let entities = vec![wall(1, 90.0, 0.0, 20.0, 200.0)];
+    let grid = NavGrid::build(&entities, bounds, 10.0, 20.0);
+    assert_eq!(grid.find_path((30.0, 30.0), (170.0, 30.0)), None);
+}
+
+#[test]
+fn npcs_pick_what_to_do_and_lead_their_shots() {
+    let difficulty = Difficulty::normal();
+    let enemy = Sighting { handle: 5, position: (100.0, 0.0), velocity: (0.0, 0.0) };
+    let mut perception = Perception { health_fraction: 1.0, ..Perception::default() };
+    assert_eq!(decide(&perception, &difficulty), AiState::Patrol);
+    perception.pickup = Some((7, (50.0, 50.0)));
+    assert_eq!(decide(&perception, &difficulty), AiState::Collect(7));
+    perception.remembered = Some(enemy);
+    assert_eq!(decide(&perception, &difficulty), AiState::Chase(5));
+    perception.enemy = Some(enemy);
+    assert_eq!(decide(&perception, &difficulty), AiState::Attack(5));
+    perception.enemy = Some(Sighting { position: (1000.0, 0.0), ..enemy });
+    assert_eq!(decide(&perception, &difficulty), AiState::Chase(5));
+    // Hurt, but there's nothing to heal with yet
+    perception.health_fraction = 0.2;
+    assert_eq!(decide(&perception, &difficulty), AiState::Chase(5));
+    perception.heal_slot = Some(1);
+    assert_eq!(decide(&perception, &difficulty), AiState::Retreat);
+    // Easy NPCs fight to the end
+    assert_eq!(decide(&perception, &Difficulty::easy()), AiState::Chase(5));
+
+    // Standing still, there's nothing to lead
+    assert_eq!(aim_point((0.0, 0.0), enemy, 1000.0, 1.0), (100.0, 0.0));
+    // Crossing at 100 px/s with shots going 1000 px/s, a full lead meets it on the way
+    let crossing = Sighting { velocity: (0.0, 100.0), ..enemy };
+    let (x, y) = aim_point((0.0, 0.0), crossing, 1000.0, 1.0);
+    assert_eq!(x, 100.0);
+    assert!((x.hypot(y) / 1000.0 - y / 100.0).abs() < 1e-4);
+    assert_eq!(aim_point((0.0, 0.0), crossing, 1000.0, 0.0), (100.0, 0.0));
+
+    // Difficulties are saved by name, and there's no NPC without one
+    let registry = ComponentRegistry::with_game_components();
+    let mut entity = Entity::new(1);
+    entity.put_component(NPC::with_difficulty((1.0, 2.0), Difficulty::hard()));
+    assert_eq!(registry.save_components(&entity).unwrap(), vec!["NPC 1 2 hard".to_string()]);
+    let scripts = prefabs::script_library();
+    assert!(registry.load_component(&mut entity, "NPC 1 2", &scripts).is_err());
+    assert!(registry.load_component(&mut entity, "NPC 1 2 impossible", &scripts).is_err());
+}
+
+#[test]
+fn bots_play_each_other_headless() {
+    let play = || Server::<SmartProtocol>::headless().unwrap().with_bots(4, "easy").with_seed(1).run_headless("map1", 3000);
+    let summary = play();
+    // Every bot got a slot of its own
+    assert_eq!(summary.scores.len(), 4);
+    assert!(summary.ticks <= 3000);
+    // However the match went, nobody scores a kill without somebody dying, and only bots survive
+    let kills: u32 = summary.scores.iter().map(|score| score.1).sum();
+    let deaths: u32 = summary.scores.iter().map(|score| score.2).sum();
+    assert!(kills <= deaths);
+    assert!(summary.survivors.iter().all(|survivor| summary.scores.iter().any(|score| score.0 == *survivor)));
+    // The same seed plays the same match all over again
+    assert_eq!(play(), summary);
+}
+
+#[test]
+fn npcs_see_projectiles_coming_and_get_out_of_the_way() {
+    let bullet = |position, velocity| Projectile { handle: 9, position, velocity };
+    // Dead on from the left, it touches the circle once it's come 90 of the 100 px
+    let threat = predict_hit((0.0, 0.0), 10.0, bullet((-100.0, 0.0), (100.0, 0.0))).unwrap();
+    assert!((threat.time_to_impact - 0.9).abs() < 1e-4);
+    assert_eq!(threat.away, (0.0, 1.0));
+    // Passing just above, it's better to get out of the way below
+    let threat = predict_hit((0.0, 0.0), 10.0, bullet((-100.0, -5.0), (100.0, 0.0))).unwrap();
+    assert_eq!(threat.away, (0.0, 1.0));
+    // Passing by far enough away, or flying away altogether
+    assert_eq!(predict_hit((0.0, 0.0), 10.0, bullet((-100.0, -20.0), (100.0, 0.0))), None);
+    assert_eq!(predict_hit((0.0, 0.0), 10.0, bullet((100.0, 0.0), (100.0, 0.0))), None);
+    // Dodging comes before anything else
+    let perception = Perception {
+        health_fraction: 1.0,
+        enemy: Some(Sighting { handle: 5, position: (100.0, 0.0), velocity: (0.0, 0.0) }),
+        threat: Some(threat),
+        ..Perception::default()
+    };
+    assert_eq!(decide(&perception, &Difficulty::easy()), AiState::Evade(9));
+}
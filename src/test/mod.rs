@@ -1 +1,2 @@
+mod ecs;
 mod net;
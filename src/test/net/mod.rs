@@ -1,5 +1,6 @@
 #![allow(deprecated)]
 
+use crate::game::ecs::{EffectKind, Shape};
 use crate::game::graphics::MeshType;
 use crate::misc::constants::ALL_KEYS;
 use crate::net::{Connection, Event, Protocol, SmartProtocol};
@@ -83,7 +84,7 @@ fn smart_protocol_encode_decode_key_up() {
 
 #[test]
 fn smart_protocol_encode_decode_health() {
-    let expected = Event::Health(3452, 255);
+    let expected = Event::Health(3452, 300, 1000);
     test_protocol_encode_decode::<SmartProtocol>(expected);
 }
 
@@ -100,8 +101,8 @@ fn smart_protocol_encode_decode_dimension() {
 }
 
 #[test]
-fn smart_protocol_encode_decode_pick_up() {
-    let expected = Event::PickUp(32342, MeshType::Heal);
+fn smart_protocol_encode_decode_inventory() {
+    let expected = Event::Inventory(32342, 2, vec![(MeshType::Heal, 3), (MeshType::None, 0), (MeshType::Weapon, 1)]);
     test_protocol_encode_decode::<SmartProtocol>(expected);
 }
 
@@ -117,6 +118,43 @@ fn smart_protocol_encode_decode_map() {
     test_protocol_encode_decode::<SmartProtocol>(expected);
 }
 
+#[test]
+fn smart_protocol_encode_decode_hitbox() {
+    let expected = Event::Hitbox(
+        0xff,
+        vec![
+            Shape::Circle(0.0, 0.0, 25.0),
+            Shape::Rect(0.0, -15.0, 30.0, 30.0),
+            Shape::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, -10.0)]),
+        ],
+    );
+    test_protocol_encode_decode::<SmartProtocol>(expected);
+    test_protocol_encode_decode::<SmartProtocol>(Event::Hitbox(0, vec![]));
+}
+
+#[test]
+fn smart_protocol_encode_decode_progress() {
+    test_protocol_encode_decode::<SmartProtocol>(Event::Progress(0xff, 0.25));
+}
+
+#[test]
+fn smart_protocol_encode_decode_weapon() {
+    test_protocol_encode_decode::<SmartProtocol>(Event::Weapon(0xff, 29, 30, 0.1, 0.1));
+    test_protocol_encode_decode::<SmartProtocol>(Event::Weapon(0xff, 0, 0, 0.0, 0.0));
+}
+
+#[test]
+fn smart_protocol_encode_decode_effects() {
+    let effects = vec![(EffectKind::SpeedBoost, 2.5, 5.0), (EffectKind::Slow, 0.5, 1.0)];
+    test_protocol_encode_decode::<SmartProtocol>(Event::Effects(0xff, effects));
+    test_protocol_encode_decode::<SmartProtocol>(Event::Effects(0xff, Vec::new()));
+}
+
+#[test]
+fn smart_protocol_encode_decode_countdown() {
+    test_protocol_encode_decode::<SmartProtocol>(Event::Countdown(0xff, 4.5, 10.0));
+}
+
 #[test]
 fn smart_protocol_encode_decode_key_down_all_keys() {
     for key in &ALL_KEYS {